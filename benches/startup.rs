@@ -0,0 +1,50 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Measures how long the binary takes from start to exit for a few typical invocations. Startup
+//! dominates the runtime of a tool like this, since most runs format a single short line.
+//!
+//! Run with `cargo bench --bench startup`.
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 200;
+
+fn time(args: &[&str]) -> Duration {
+    let bin = env!("CARGO_BIN_EXE_fmt");
+    // Warm up the page cache so the first run doesn't skew the average
+    Command::new(bin)
+        .args(args)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        let status = Command::new(bin)
+            .args(args)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .expect("Unable to run fmt");
+        assert!(status.success(), "fmt {:?} failed", args);
+    }
+    start.elapsed() / RUNS
+}
+
+fn main() {
+    let cases: &[(&str, &[&str])] = &[
+        ("no specs", &["just some text"]),
+        ("positional", &["{} and {}", "one", "two"]),
+        ("named + aligned", &["{name:>10}|{0:^8}", "x", "name = y"]),
+        ("short help", &[]),
+        ("long help", &["--help"]),
+    ];
+    for (name, args) in cases {
+        println!("{:<16} {:>10.2?}", name, time(args));
+    }
+}
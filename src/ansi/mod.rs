@@ -0,0 +1,292 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+mod sgr;
+
+pub use sgr::{downgrade, styled_runs, Style};
+
+#[cfg(feature = "unicode")]
+use unicode_segmentation::UnicodeSegmentation;
+#[cfg(feature = "unicode")]
+use unicode_width::UnicodeWidthChar;
+
+const ESC: char = '\x1b';
+
+/// The SGR sequence that resets all styling
+pub const RESET: &str = "\x1b[0m";
+
+/// A piece of a string that is either visible text or a terminal escape sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Escape(&'a str),
+}
+
+/// Iterator over the [`Segment`]s of a string, see [`segments`].
+#[derive(Clone, Debug)]
+pub struct Segments<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = Segment<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let len = if self.rest.starts_with(ESC) {
+            escape_len(self.rest)
+        } else {
+            self.rest.find(ESC).unwrap_or(self.rest.len())
+        };
+        let (seg, rest) = self.rest.split_at(len);
+        self.rest = rest;
+        if seg.starts_with(ESC) {
+            Some(Segment::Escape(seg))
+        } else {
+            Some(Segment::Text(seg))
+        }
+    }
+}
+
+/// Splits `s` into visible text and escape sequences. CSI (`ESC [`) and OSC (`ESC ]`) sequences
+/// are recognized fully, any other escape is assumed to be two characters long.
+pub fn segments(s: &str) -> Segments<'_> {
+    Segments { rest: s }
+}
+
+/// Returns the byte length of the escape sequence at the start of `s`, which must start with ESC.
+fn escape_len(s: &str) -> usize {
+    let mut chars = s.char_indices().skip(1);
+    match chars.next() {
+        // CSI: parameter and intermediate bytes followed by a single final byte in `@..=~`
+        Some((_, '[')) => chars
+            .find(|(_, c)| ('@'..='~').contains(c))
+            .map(|(i, c)| i + c.len_utf8())
+            .unwrap_or(s.len()),
+        // OSC: terminated by BEL or ST (`ESC \`)
+        Some((_, ']')) => {
+            let mut prev_esc = false;
+            for (i, c) in chars {
+                if c == '\x07' || (prev_esc && c == '\\') {
+                    return i + c.len_utf8();
+                }
+                prev_esc = c == ESC;
+            }
+            s.len()
+        }
+        Some((i, c)) => i + c.len_utf8(),
+        None => s.len(),
+    }
+}
+
+/// Removes every escape sequence from `s`.
+pub fn strip(s: &str) -> String {
+    segments(s)
+        .filter_map(|seg| match seg {
+            Segment::Text(t) => Some(t),
+            Segment::Escape(_) => None,
+        })
+        .collect()
+}
+
+/// Removes every escape sequence from `s` like [`strip`] does, along with any other control
+/// character (including stray C1 controls like the single byte CSI) except for tabs and newlines.
+/// Whatever is left can't change the state of a terminal.
+pub fn sanitize(s: &str) -> String {
+    strip(s)
+        .chars()
+        .filter(|&c| c == '\t' || c == '\n' || !c.is_control())
+        .collect()
+}
+
+/// Returns true if `s` contains anything that looks like an escape sequence.
+pub fn has_escapes(s: &str) -> bool {
+    s.contains(ESC)
+}
+
+#[cfg(feature = "unicode")]
+pub fn char_width(c: char) -> usize {
+    UnicodeWidthChar::width(c).unwrap_or(0)
+}
+
+/// Without the `unicode` feature every character is assumed to be one cell wide, except for
+/// control characters.
+#[cfg(not(feature = "unicode"))]
+pub fn char_width(c: char) -> usize {
+    usize::from(!c.is_control())
+}
+
+/// The number of cells `s` occupies, without treating escape sequences specially.
+pub fn text_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+/// The grapheme clusters of `s` along with their byte offsets.
+#[cfg(feature = "unicode")]
+pub fn grapheme_indices(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    s.grapheme_indices(true)
+}
+
+/// Without the `unicode` feature, every character is treated as a grapheme cluster of its own.
+#[cfg(not(feature = "unicode"))]
+pub fn grapheme_indices(s: &str) -> impl Iterator<Item = (usize, &str)> {
+    s.char_indices()
+        .map(move |(i, c)| (i, &s[i..i + c.len_utf8()]))
+}
+
+/// The number of terminal cells `s` occupies once printed, ignoring escape sequences.
+pub fn width(s: &str) -> usize {
+    segments(s)
+        .map(|seg| match seg {
+            Segment::Text(t) => t.chars().map(char_width).sum(),
+            Segment::Escape(_) => 0,
+        })
+        .sum()
+}
+
+/// Splits `s` so that the first part occupies at most `max` cells, without breaking up grapheme
+/// clusters or escape sequences. Escapes sitting right at the split point go with the second part
+/// so they still apply to the text they precede. At least one grapheme is always taken when `max`
+/// is not zero, even if it is wider than `max`, so repeated splitting always makes progress.
+pub fn split_at_width(s: &str, max: usize) -> (&str, &str) {
+    let mut used = 0usize;
+    let mut cut = 0usize;
+    let mut pos = 0usize;
+
+    for seg in segments(s) {
+        match seg {
+            Segment::Escape(e) => pos += e.len(),
+            Segment::Text(t) => {
+                for (i, g) in grapheme_indices(t) {
+                    let w = g.chars().map(char_width).sum::<usize>();
+                    if used + w > max && (cut > 0 || max == 0) {
+                        return s.split_at(cut);
+                    }
+                    used += w;
+                    cut = pos + i + g.len();
+                }
+                pos += t.len();
+            }
+        }
+    }
+
+    s.split_at(s.len())
+}
+
+/// Shortens `s` so that it occupies at most `max` cells, with `marker` appended when anything
+/// was cut off. Escape sequences are kept intact, and styling is reset after the marker so it
+/// does not bleed into whatever comes next.
+pub fn truncate(s: &str, max: usize, marker: &str) -> String {
+    if width(s) <= max {
+        return s.to_string();
+    }
+
+    let budget = max.saturating_sub(width(marker));
+    let mut used = 0usize;
+    let mut output = String::with_capacity(s.len());
+    let mut styled = false;
+    let mut full = false;
+
+    for seg in segments(s) {
+        match seg {
+            Segment::Escape(e) => {
+                styled = true;
+                output.push_str(e);
+            }
+            Segment::Text(_) if full => {}
+            Segment::Text(t) => {
+                for (_, g) in grapheme_indices(t) {
+                    let w = g.chars().map(char_width).sum::<usize>();
+                    if used + w > budget {
+                        full = true;
+                        break;
+                    }
+                    used += w;
+                    output.push_str(g);
+                }
+            }
+        }
+    }
+
+    output.push_str(marker);
+    if styled {
+        output.push_str(RESET);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn segments_and_strip() {
+        let s = "a\x1b[31mred\x1b[0m\x1b]0;title\x07b";
+        let segs = segments(s).collect::<Vec<_>>();
+        assert_eq!(
+            segs,
+            vec![
+                Segment::Text("a"),
+                Segment::Escape("\x1b[31m"),
+                Segment::Text("red"),
+                Segment::Escape("\x1b[0m"),
+                Segment::Escape("\x1b]0;title\x07"),
+                Segment::Text("b"),
+            ]
+        );
+        assert_eq!(strip(s), "aredb");
+        assert_eq!(strip("\x1b]8;;http://x\x1b\\link"), "link");
+        assert_eq!(strip("no escapes"), "no escapes");
+        assert_eq!(
+            sanitize("a\x1b[1mb\u{9b}31mc\rd\x07\te\nf\x1b"),
+            "ab31mcd\te\nf"
+        );
+    }
+
+    #[test]
+    fn widths() {
+        assert_eq!(width("hello"), 5);
+        assert_eq!(width("\x1b[1;38;2;1;2;3mhello\x1b[0m"), 5);
+        #[cfg(feature = "unicode")]
+        assert_eq!(width("读文"), 4);
+        assert_eq!(width(""), 0);
+    }
+
+    #[test]
+    fn truncation() {
+        assert_eq!(truncate("hello world", 20, "…"), "hello world");
+        assert_eq!(truncate("hello world", 6, "…"), "hello…");
+        assert_eq!(
+            truncate("\x1b[31mhello\x1b[0m world", 6, "…"),
+            "\x1b[31mhello\x1b[0m…\x1b[0m"
+        );
+        // Wide characters are never split in half
+        #[cfg(feature = "unicode")]
+        assert_eq!(truncate("读文读文", 6, "…"), "读文…");
+        assert_eq!(truncate("abc", 0, "…"), "…");
+    }
+
+    #[test]
+    fn splitting() {
+        assert_eq!(split_at_width("abcdef", 4), ("abcd", "ef"));
+        assert_eq!(split_at_width("abc", 4), ("abc", ""));
+        assert_eq!(split_at_width("ab\x1b[31mcdef", 2), ("ab", "\x1b[31mcdef"));
+        assert_eq!(split_at_width("\x1b[31mabcdef", 2), ("\x1b[31mab", "cdef"));
+        // Combining marks stay attached to their base character
+        #[cfg(feature = "unicode")]
+        assert_eq!(
+            split_at_width("e\u{301}e\u{301}e\u{301}", 2),
+            ("e\u{301}e\u{301}", "e\u{301}")
+        );
+        #[cfg(feature = "unicode")]
+        assert_eq!(split_at_width("读文", 1), ("读", "文"));
+        assert_eq!(split_at_width("abc", 0), ("", "abc"));
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{segments, Segment};
+use crate::color::{Color, ColorLevel};
+
+/// The styling SGR sequences have applied at some point of a string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+    pub strike: bool,
+}
+
+impl Style {
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The parameters of the SGR sequence that turns this style on, with its colors downgraded to
+    /// what `level` supports.
+    pub fn sgr(&self, level: ColorLevel) -> String {
+        let flags = [
+            (self.bold, "1"),
+            (self.dim, "2"),
+            (self.italic, "3"),
+            (self.underline, "4"),
+            (self.inverse, "7"),
+            (self.strike, "9"),
+        ];
+        let mut params = flags
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, p)| p.to_string())
+            .collect::<Vec<_>>();
+        if let Some(c) = self.fg.and_then(|c| c.downgrade(level)) {
+            params.push(c.fg_sgr());
+        }
+        if let Some(c) = self.bg.and_then(|c| c.downgrade(level)) {
+            params.push(c.bg_sgr());
+        }
+        params.join(";")
+    }
+
+    /// Wraps `text` in this style, or returns it as it is when there is nothing to show at `level`.
+    /// The style is turned back on after every reset inside `text`, so styles nested in it end
+    /// without ending this one.
+    pub fn paint(&self, text: &str, level: ColorLevel) -> String {
+        if self.is_plain() || level == ColorLevel::None {
+            return text.to_string();
+        }
+        let on = format!("\x1b[{}m", self.sgr(level));
+        // A reset at the very end is taken over by the one closing this style
+        let text = text.strip_suffix("\x1b[0m").unwrap_or(text);
+        let text = text.replace("\x1b[0m", &format!("\x1b[0m{}", on));
+        format!("{}{}\x1b[0m", on, text)
+    }
+
+    /// Updates the style with the parameters of an SGR sequence (the part between `ESC [` and
+    /// `m`). Unknown parameters are ignored.
+    pub fn apply(&mut self, params: &str) {
+        let mut params = params
+            .split([';', ':'])
+            .map(|p| p.parse::<u8>().unwrap_or(0));
+        while let Some(p) = params.next() {
+            match p {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.inverse = true,
+                9 => self.strike = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                29 => self.strike = false,
+                30..=37 => self.fg = Some(Color::Basic(p - 30)),
+                38 => self.fg = extended_color(&mut params),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(Color::Basic(p - 40)),
+                48 => self.bg = extended_color(&mut params),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(Color::Basic(p - 90 + 8)),
+                100..=107 => self.bg = Some(Color::Basic(p - 100 + 8)),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Reads the rest of a `38;5;N` or `38;2;R;G;B` color.
+fn extended_color<I: Iterator<Item = u8>>(params: &mut I) -> Option<Color> {
+    match params.next()? {
+        5 => params.next().map(Color::Ansi256),
+        2 => Some(Color::Rgb(params.next()?, params.next()?, params.next()?)),
+        _ => None,
+    }
+}
+
+/// Splits `s` into runs of visible text along with the [`Style`] they are shown in. SGR sequences
+/// change the style, any other escape sequence is dropped.
+pub fn styled_runs(s: &str) -> Vec<(Style, &str)> {
+    let mut style = Style::default();
+    let mut runs = Vec::new();
+    for seg in segments(s) {
+        match seg {
+            Segment::Escape(e) => {
+                if let Some(params) = e.strip_prefix("\x1b[").and_then(|e| e.strip_suffix('m')) {
+                    style.apply(params);
+                }
+            }
+            Segment::Text(t) => runs.push((style, t)),
+        }
+    }
+    runs
+}
+
+/// Rewrites the styling of `s` with its colors downgraded to what `level` supports, dropping all
+/// styling at [`ColorLevel::None`]. Other escape sequences are dropped.
+pub fn downgrade(s: &str, level: ColorLevel) -> String {
+    styled_runs(s)
+        .into_iter()
+        .map(|(style, text)| style.paint(text, level))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn sgr_params() {
+        let mut style = Style::default();
+        style.apply("1;31;48;5;200");
+        assert!(style.bold);
+        assert_eq!(style.fg, Some(Color::Basic(1)));
+        assert_eq!(style.bg, Some(Color::Ansi256(200)));
+        style.apply("22;38;2;1;2;3;49");
+        assert!(!style.bold);
+        assert_eq!(style.fg, Some(Color::Rgb(1, 2, 3)));
+        assert_eq!(style.bg, None);
+        style.apply("");
+        assert!(style.is_plain());
+    }
+
+    #[test]
+    fn painting() {
+        let style = Style {
+            fg: Some(Color::Rgb(255, 0, 0)),
+            bold: true,
+            ..Style::default()
+        };
+        assert_eq!(style.sgr(ColorLevel::TrueColor), "1;38;2;255;0;0");
+        assert_eq!(style.paint("hi", ColorLevel::Basic), "\x1b[1;91mhi\x1b[0m");
+        assert_eq!(style.paint("hi", ColorLevel::None), "hi");
+        assert_eq!(Style::default().paint("hi", ColorLevel::Basic), "hi");
+        assert_eq!(
+            style.paint("a\x1b[4mb\x1b[0mc", ColorLevel::Basic),
+            "\x1b[1;91ma\x1b[4mb\x1b[0m\x1b[1;91mc\x1b[0m"
+        );
+        assert_eq!(
+            style.paint("\x1b[4mb\x1b[0m", ColorLevel::Basic),
+            "\x1b[1;91m\x1b[4mb\x1b[0m"
+        );
+        let mut parsed = Style::default();
+        parsed.apply(&style.sgr(ColorLevel::TrueColor));
+        assert_eq!(parsed, style);
+    }
+
+    #[test]
+    fn runs() {
+        let runs = styled_runs("a\x1b[1mb\x1b]0;t\x07c\x1b[0md");
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        assert_eq!(
+            runs,
+            vec![
+                (Style::default(), "a"),
+                (bold, "b"),
+                (bold, "c"),
+                (Style::default(), "d"),
+            ]
+        );
+    }
+    #[test]
+    fn downgrading() {
+        let styled = "\x1b[38;2;255;255;0mhelp\x1b[0m: \x1b[1mfmt\x1b[0m";
+        assert_eq!(
+            downgrade(styled, ColorLevel::Basic),
+            "\x1b[93mhelp\x1b[0m: \x1b[1mfmt\x1b[0m"
+        );
+        assert_eq!(
+            downgrade(styled, ColorLevel::Ansi256),
+            "\x1b[38;5;226mhelp\x1b[0m: \x1b[1mfmt\x1b[0m"
+        );
+        assert_eq!(downgrade(styled, ColorLevel::None), "help: fmt");
+    }
+}
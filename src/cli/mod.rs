@@ -0,0 +1,1219 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::time::Duration;
+
+use crate::{
+    ansi::Style,
+    color::{Color, ColorChoice},
+    decor::BoxStyle,
+    output::OutputFormat,
+    records::RecordKey,
+    sink::Target,
+    snapshot::Snapshot,
+    table::Table,
+    term::Screen,
+    Alignment, Dialect, Error,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Help {
+    Short,
+    Long,
+}
+
+/// How many columns an output-wide operation (wrapping, clamping, etc.) has to work with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Width {
+    Terminal,
+    Fixed(usize),
+}
+
+impl Width {
+    pub fn resolve(self) -> usize {
+        match self {
+            Width::Terminal => crate::term::width(),
+            Width::Fixed(n) => n,
+        }
+    }
+}
+
+/// Where the records of a record mode come from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RecordMode {
+    /// `--each`, every argument is a record
+    Each,
+    /// `--stream`, every line of stdin is a record
+    Stream,
+    /// `--chunk N`, every N arguments are a record
+    Chunk(usize),
+    /// `--zip N`, the arguments are N lists and every record takes the next one of each
+    Zip(usize),
+}
+
+/// How many columns `--columns` lays records out in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Columns {
+    Auto,
+    Count(usize),
+}
+
+/// What `--title` does with the output besides making it the title of the terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Title {
+    /// `--title`, the output isn't printed
+    Only,
+    /// `--title=also`, the output is printed as well
+    Also,
+}
+
+/// How `--rainbow` or `--gradient` color the output, one grapheme at a time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Colorize {
+    Rainbow,
+    Gradient(Color, Color),
+}
+
+/// How many milliseconds `--typewriter` pauses for after every grapheme, unless told otherwise
+pub const DEFAULT_TYPEWRITER_MS: u64 = 40;
+
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    pub help: Option<Help>,
+    pub debug: bool,
+    pub clamp: bool,
+    pub wrap: Option<Width>,
+    pub hang: usize,
+    pub hard_wrap: bool,
+    pub justify: bool,
+    pub block_align: Option<Alignment>,
+    pub center: bool,
+    /// `--hr[=pattern]`, a horizontal rule is printed in place of a FMT_STRING
+    pub hr: Option<String>,
+    /// `--box[=style]`, the output is drawn inside a frame
+    pub frame: Option<BoxStyle>,
+    pub width: Option<usize>,
+    pub records: Option<RecordMode>,
+    pub columns: Option<Columns>,
+    pub sort: Option<RecordKey>,
+    pub unique: Option<RecordKey>,
+    pub header: Option<String>,
+    pub footer: Option<String>,
+    pub separator: Option<String>,
+    pub stats: bool,
+    pub stats_perf: bool,
+    pub deterministic: bool,
+    pub seed: Option<u64>,
+    /// `--utc`, `{now}` shows the time in UTC rather than the local time
+    pub utc: bool,
+    pub dry_run: bool,
+    pub export: bool,
+    pub copy: bool,
+    pub plain: bool,
+    /// `--printf`, the format string uses printf(1) conversions
+    pub printf: bool,
+    pub printf_compat_check: bool,
+    /// `--rust-fmt` or `--python`, whose rules specs render args with
+    pub dialect: Dialect,
+    /// `fmt snapshot`, records or verifies the output instead of printing it
+    pub snapshot: Option<Snapshot>,
+    /// `fmt table`, rows are read from stdin and printed as a table instead of a FMT_STRING
+    pub table: Option<Table>,
+    /// Where the output goes, stdout unless `-o` or `--tee` say otherwise
+    pub targets: Vec<Target>,
+    pub output: OutputFormat,
+    /// `-e`, backslash escapes in the format string are interpreted like `echo -e` does
+    pub escapes: bool,
+    /// `-n`, the output doesn't end with a newline
+    pub no_newline: bool,
+    /// `--bold`, `--dim`, `--italic` and `--underline`, the style of the whole output
+    pub style: Style,
+    pub colorize: Option<Colorize>,
+    /// `--progress CURRENT TOTAL`, a progress bar follows the output
+    pub progress: Option<(usize, usize)>,
+    /// `--spinner`, the command after `--` runs with a spinner next to the output
+    pub spinner: bool,
+    /// `--countdown SECS`, the output is rendered every second with `{remaining}` counting down
+    pub countdown: Option<u64>,
+    /// `--overwrite`, the output is drawn over the line the cursor is on rather than ending a line
+    pub overwrite: bool,
+    /// `--every DURATION`, the output is rendered again and again, this far apart
+    pub every: Option<Duration>,
+    pub title: Option<Title>,
+    /// `--at ROW,COL`, `--clear` and `--clear-line`, where on the terminal the output is drawn
+    pub screen: Screen,
+    /// `--stdin-args`, every line of stdin is one more of the ARGS
+    pub stdin_args: bool,
+    /// `--no-config`, the config file is left unread
+    pub no_config: bool,
+    /// `--ascii`, decorations are drawn with ASCII only
+    pub ascii: bool,
+    /// `--qr`, the output is drawn as a QR code
+    pub qr: bool,
+    /// `--bell`, the terminal beeps once the output is printed
+    pub bell: bool,
+    /// `--notify`, the output is also sent as a desktop notification
+    pub notify: bool,
+    /// `--typewriter[=ms]`, the output is written one grapheme at a time with a pause after each
+    pub typewriter: Option<u64>,
+    /// The command `--spinner` runs, everything after `--` in the ARGS
+    pub command: Vec<String>,
+    /// `--color`, whether output is colored, in place of what the environment says
+    pub color: Option<ColorChoice>,
+    /// `--highlight`, substitutions are underlined and colored to tell them apart from the text
+    pub highlight: bool,
+    /// `--pad-lines`, every line of a multi-line value is padded to the width of its spec
+    pub pad_lines: bool,
+    pub fmt_str: Option<String>,
+    pub args: Vec<String>,
+}
+
+impl Options {
+    pub fn parse<I, S>(input: I) -> crate::Result<Self>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut opts = Self::default();
+        let mut input = input.into_iter().map(Into::into).peekable();
+        let mut hang = None;
+        let mut tee = Vec::new();
+        let subcommand = input.next_if(|arg| arg == "snapshot").is_some();
+        let table = !subcommand && input.next_if(|arg| arg == "table").is_some();
+        let mut table_opts = Table::default();
+        let mut table_flag = None;
+        let mut template = None;
+        let mut args_files = Vec::new();
+
+        // Flags are only recognized before the format string, and only when they are known, so a
+        // format string that happens to start with a dash is still treated as a format string.
+        while let Some(arg) = input.next() {
+            let (flag, inline) = split_flag(&arg);
+            match flag {
+                "-h" => opts.help = Some(Help::Short),
+                "--help" => opts.help = Some(Help::Long),
+                "-d" | "-D" | "--debug" => opts.debug = true,
+                "-e" => opts.escapes = true,
+                "-n" => opts.no_newline = true,
+                "--stdin-args" => {
+                    no_value(flag, inline)?;
+                    opts.stdin_args = true;
+                }
+                "--args-file" => args_files.push(take_value(flag, inline, &mut input)?),
+                "-F" | "--template" => template = Some(take_value(flag, inline, &mut input)?),
+                "--clamp" => {
+                    no_value(flag, inline)?;
+                    opts.clamp = true;
+                }
+                "--wrap" => {
+                    opts.wrap = Some(match inline {
+                        Some(v) => Width::Fixed(parse_num(flag, v)?),
+                        None => Width::Terminal,
+                    });
+                }
+                "--hard-wrap" => {
+                    no_value(flag, inline)?;
+                    opts.hard_wrap = true;
+                }
+                "--justify" => {
+                    no_value(flag, inline)?;
+                    opts.justify = true;
+                }
+                "--hang" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    hang = Some(parse_num(flag, &value)?);
+                }
+                "--block-align" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    opts.block_align = Some(parse_align(flag, &value)?);
+                }
+                "--center" => {
+                    no_value(flag, inline)?;
+                    opts.center = true;
+                }
+                "--hr" => opts.hr = Some(inline.unwrap_or("─").to_string()),
+                "--box" => {
+                    opts.frame = Some(match inline {
+                        None => BoxStyle::default(),
+                        Some(v) => {
+                            BoxStyle::parse(v).ok_or_else(|| Error::bad_flag_value(flag, v))?
+                        }
+                    });
+                }
+                "--width" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    match parse_num(flag, &value)? {
+                        0 => return Err(Error::bad_flag_value(flag, &value)),
+                        n => opts.width = Some(n),
+                    }
+                }
+                "--each" => {
+                    no_value(flag, inline)?;
+                    opts.records = Some(RecordMode::Each);
+                }
+                "--stream" => {
+                    no_value(flag, inline)?;
+                    opts.records = Some(RecordMode::Stream);
+                }
+                "--chunk" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    match parse_num(flag, &value)? {
+                        0 => return Err(Error::bad_flag_value(flag, &value)),
+                        n => opts.records = Some(RecordMode::Chunk(n)),
+                    }
+                }
+                "--zip" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    match parse_num(flag, &value)? {
+                        0 => return Err(Error::bad_flag_value(flag, &value)),
+                        n => opts.records = Some(RecordMode::Zip(n)),
+                    }
+                }
+                "--output" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    opts.output = OutputFormat::parse(&value)
+                        .ok_or_else(|| Error::bad_flag_value(flag, &value))?;
+                }
+                "--columns" => {
+                    opts.columns = Some(match inline {
+                        None | Some("auto") => Columns::Auto,
+                        Some(v) => match parse_num(flag, v)? {
+                            0 => return Err(Error::bad_flag_value(flag, v)),
+                            n => Columns::Count(n),
+                        },
+                    });
+                }
+                "--sort" => opts.sort = Some(parse_key(flag, inline)?),
+                "--unique" => opts.unique = Some(parse_key(flag, inline)?),
+                "--header" => opts.header = Some(take_value(flag, inline, &mut input)?),
+                "--footer" => opts.footer = Some(take_value(flag, inline, &mut input)?),
+                "-o" | "--output-file" => {
+                    let path = take_value(flag, inline, &mut input)?;
+                    opts.targets.push(Target::File(path.into()));
+                }
+                "--tee" => match inline {
+                    None => tee.push(Target::Stdout),
+                    Some(list) => {
+                        for dest in list.split(',') {
+                            let target = Target::parse_stream(dest)
+                                .ok_or_else(|| Error::bad_flag_value(flag, list))?;
+                            tee.push(target);
+                        }
+                    }
+                },
+                "--deterministic" => {
+                    no_value(flag, inline)?;
+                    opts.deterministic = true;
+                }
+                "--utc" => {
+                    no_value(flag, inline)?;
+                    opts.utc = true;
+                }
+                "--seed" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    let seed = value
+                        .parse()
+                        .map_err(|_| Error::bad_flag_value(flag, &value))?;
+                    opts.seed = Some(seed);
+                }
+                "--record" => {
+                    let file = take_value(flag, inline, &mut input)?;
+                    opts.snapshot = Some(Snapshot::Record(file.into()));
+                }
+                "--verify" => {
+                    let file = take_value(flag, inline, &mut input)?;
+                    opts.snapshot = Some(Snapshot::Verify(file.into()));
+                }
+                "--printf" => {
+                    no_value(flag, inline)?;
+                    opts.printf = true;
+                }
+                "--rust-fmt" | "--python" => {
+                    no_value(flag, inline)?;
+                    let dialect = match flag {
+                        "--python" => Dialect::Python,
+                        _ => Dialect::Rust,
+                    };
+                    if opts.dialect != Dialect::Default && opts.dialect != dialect {
+                        return Err(Error::InvalidFlag(
+                            "--rust-fmt cannot be used with --python".to_string(),
+                        ));
+                    }
+                    opts.dialect = dialect;
+                }
+                "--printf-compat-check" => {
+                    no_value(flag, inline)?;
+                    opts.printf_compat_check = true;
+                }
+                "--bold" | "--dim" | "--italic" | "--underline" => {
+                    no_value(flag, inline)?;
+                    match flag {
+                        "--bold" => opts.style.bold = true,
+                        "--dim" => opts.style.dim = true,
+                        "--italic" => opts.style.italic = true,
+                        _ => opts.style.underline = true,
+                    }
+                }
+                "--color" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    let choice = ColorChoice::parse(&value)
+                        .ok_or_else(|| Error::bad_flag_value(flag, &value))?;
+                    opts.color = Some(choice);
+                }
+                "--highlight" => {
+                    no_value(flag, inline)?;
+                    opts.highlight = true;
+                }
+                "--pad-lines" => {
+                    no_value(flag, inline)?;
+                    opts.pad_lines = true;
+                }
+                "--rainbow" => {
+                    no_value(flag, inline)?;
+                    opts.colorize = Some(Colorize::Rainbow);
+                }
+                "--gradient" => {
+                    no_value(flag, inline)?;
+                    let mut color = || -> crate::Result<Color> {
+                        let value = take_value(flag, None, &mut input)?;
+                        Color::parse(&value).ok_or_else(|| Error::bad_flag_value(flag, &value))
+                    };
+                    opts.colorize = Some(Colorize::Gradient(color()?, color()?));
+                }
+                "--progress" => {
+                    no_value(flag, inline)?;
+                    let current = take_value(flag, None, &mut input)?;
+                    let total = take_value(flag, None, &mut input)?;
+                    match (parse_num(flag, &current)?, parse_num(flag, &total)?) {
+                        (_, 0) => return Err(Error::bad_flag_value(flag, &total)),
+                        progress => opts.progress = Some(progress),
+                    }
+                }
+                "--countdown" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    opts.countdown = Some(parse_num(flag, &value)? as u64);
+                }
+                "--typewriter" => {
+                    opts.typewriter = Some(match inline {
+                        Some(ms) => parse_num(flag, ms)? as u64,
+                        None => DEFAULT_TYPEWRITER_MS,
+                    });
+                }
+                "--title" => {
+                    opts.title = Some(match inline {
+                        None => Title::Only,
+                        Some("also") => Title::Also,
+                        Some(v) => return Err(Error::bad_flag_value(flag, v)),
+                    });
+                }
+                "--at" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    let (row, col) = value
+                        .split_once(',')
+                        .ok_or_else(|| Error::bad_flag_value(flag, &value))?;
+                    let (row, col) = (parse_num(flag, row)?, parse_num(flag, col)?);
+                    if row == 0 || col == 0 {
+                        return Err(Error::bad_flag_value(flag, &value));
+                    }
+                    opts.screen.at = Some((row, col));
+                }
+                "--clear" => {
+                    no_value(flag, inline)?;
+                    opts.screen.clear = true;
+                }
+                "--clear-line" => {
+                    no_value(flag, inline)?;
+                    opts.screen.clear_line = true;
+                }
+                "--no-config" => {
+                    no_value(flag, inline)?;
+                    opts.no_config = true;
+                }
+                "--ascii" => {
+                    no_value(flag, inline)?;
+                    opts.ascii = true;
+                }
+                "--qr" => {
+                    no_value(flag, inline)?;
+                    opts.qr = true;
+                }
+                "--bell" => {
+                    no_value(flag, inline)?;
+                    opts.bell = true;
+                }
+                "--notify" => {
+                    no_value(flag, inline)?;
+                    opts.notify = true;
+                }
+                "--every" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    opts.every = Some(parse_duration(flag, &value)?);
+                }
+                "--overwrite" => {
+                    no_value(flag, inline)?;
+                    opts.overwrite = true;
+                }
+                "--spinner" => {
+                    no_value(flag, inline)?;
+                    opts.spinner = true;
+                }
+                "--plain" => {
+                    no_value(flag, inline)?;
+                    opts.plain = true;
+                }
+                "--copy" => {
+                    no_value(flag, inline)?;
+                    opts.copy = true;
+                }
+                "--export" => {
+                    no_value(flag, inline)?;
+                    opts.export = true;
+                }
+                "--dry-run" => {
+                    no_value(flag, inline)?;
+                    opts.dry_run = true;
+                }
+                "--stats-perf" => {
+                    no_value(flag, inline)?;
+                    opts.stats_perf = true;
+                }
+                "--stats" => {
+                    no_value(flag, inline)?;
+                    opts.stats = true;
+                }
+                "--separator" => opts.separator = Some(take_value(flag, inline, &mut input)?),
+                "--csv" | "--tsv" => {
+                    no_value(flag, inline)?;
+                    table_opts.delimiter = Some(if flag == "--csv" { ',' } else { '\t' });
+                    table_flag = Some(flag.to_string());
+                }
+                "--headers" => {
+                    no_value(flag, inline)?;
+                    table_opts.headers = true;
+                    table_flag = Some(flag.to_string());
+                }
+                "--align" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    table_opts.align = Table::parse_align(&value)
+                        .ok_or_else(|| Error::bad_flag_value(flag, &value))?;
+                    table_flag = Some(flag.to_string());
+                }
+                "--border" => {
+                    let value = take_value(flag, inline, &mut input)?;
+                    table_opts.border = Some(
+                        BoxStyle::parse(&value)
+                            .ok_or_else(|| Error::bad_flag_value(flag, &value))?,
+                    );
+                    table_flag = Some(flag.to_string());
+                }
+                "--" if template.is_some() => break,
+                "--" => {
+                    opts.fmt_str = input.next();
+                    break;
+                }
+                // With a template, the first arg that isn't a flag is already one of the ARGS
+                _ if template.is_some() => {
+                    opts.args.push(arg);
+                    break;
+                }
+                // Like -F -, a FMT_STRING of a lone dash is read from stdin
+                "-" => {
+                    template = Some(arg);
+                    break;
+                }
+                _ => {
+                    opts.fmt_str = Some(arg);
+                    break;
+                }
+            }
+        }
+        let stdin_template = template.as_deref() == Some("-");
+        if opts.stdin_args && (table || stdin_template || opts.records == Some(RecordMode::Stream))
+        {
+            return Err(Error::InvalidFlag(
+                "--stdin-args cannot be used with table, --stream or a FMT_STRING read from stdin"
+                    .to_string(),
+            ));
+        }
+        if stdin_template && (table || opts.records == Some(RecordMode::Stream)) {
+            return Err(Error::InvalidFlag(
+                "The FMT_STRING cannot be read from stdin with table or --stream, which read it too"
+                    .to_string(),
+            ));
+        }
+        if let Some(path) = template {
+            let text = match path.as_str() {
+                "-" => std::io::read_to_string(std::io::stdin()),
+                _ => std::fs::read_to_string(&path),
+            };
+            let text = text.map_err(|err| match path.as_str() {
+                "-" => Error::Other(format!("Unable to read the FMT_STRING from stdin: {}", err)),
+                _ => Error::Other(format!("Unable to read {}: {}", path, err)),
+            })?;
+            // Files end with a newline, which would double the one every output line gets
+            let text = text.strip_suffix('\n').unwrap_or(&text);
+            opts.fmt_str = Some(text.strip_suffix('\r').unwrap_or(text).to_string());
+        }
+
+        match (subcommand, &opts.snapshot) {
+            (true, None) => {
+                return Err(Error::InvalidFlag(
+                    "snapshot requires --record FILE or --verify FILE".to_string(),
+                ))
+            }
+            (false, Some(_)) => {
+                return Err(Error::InvalidFlag(
+                    "--record and --verify can only be used with snapshot".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        match (table, table_flag) {
+            (true, _) if opts.fmt_str.is_some() => {
+                return Err(Error::InvalidFlag(
+                    "table reads its rows from stdin, it cannot be used with a FMT_STRING"
+                        .to_string(),
+                ))
+            }
+            // Delimited output is the rows themselves, there is nothing to align or draw
+            (true, _)
+                if opts.output.is_tabular()
+                    && (!table_opts.align.is_empty() || table_opts.border.is_some()) =>
+            {
+                return Err(Error::InvalidFlag(
+                    "--align and --border cannot be used with --output csv or tsv".to_string(),
+                ))
+            }
+            (true, _) => opts.table = Some(table_opts),
+            (false, Some(flag)) => {
+                return Err(Error::InvalidFlag(format!(
+                    "{} can only be used with table",
+                    flag
+                )))
+            }
+            _ => {}
+        }
+
+        // Hard wrapping and justification are kinds of wrapping, so they don't need --wrap as well
+        if (opts.hard_wrap || opts.justify) && opts.wrap.is_none() {
+            opts.wrap = Some(Width::Terminal);
+        }
+        if let Some(n) = hang {
+            if opts.wrap.is_none() {
+                return Err(Error::InvalidFlag("--hang requires --wrap".to_string()));
+            }
+            opts.hang = n;
+        }
+
+        if opts.records.is_none() {
+            let record_flags = [
+                ("--columns", opts.columns.is_some()),
+                ("--sort", opts.sort.is_some()),
+                ("--unique", opts.unique.is_some()),
+                ("--header", opts.header.is_some()),
+                ("--footer", opts.footer.is_some()),
+                ("--separator", opts.separator.is_some()),
+                ("--stats", opts.stats),
+            ];
+            if let Some((flag, _)) = record_flags.iter().find(|(_, used)| *used) {
+                return Err(Error::InvalidFlag(format!(
+                    "{} requires --each, --stream, --chunk or --zip",
+                    flag
+                )));
+            }
+            if opts.output.is_tabular() && opts.table.is_none() {
+                return Err(Error::InvalidFlag(
+                    "--output csv or tsv requires table, --each, --stream, --chunk or --zip"
+                        .to_string(),
+                ));
+            }
+        }
+
+        // Output files replace stdout, unless it is teed back in. Without any files, --tee adds
+        // its destinations to stdout.
+        if opts.targets.is_empty() {
+            opts.targets.push(Target::Stdout);
+        }
+        for target in tee {
+            if !opts.targets.contains(&target) {
+                opts.targets.push(target);
+            }
+        }
+
+        if opts.records.is_some() {
+            let flags = [
+                ("--dry-run", opts.dry_run),
+                ("--export", opts.export),
+                ("--printf", opts.printf),
+                ("--progress", opts.progress.is_some()),
+                ("--spinner", opts.spinner),
+                ("--countdown", opts.countdown.is_some()),
+                ("--every", opts.every.is_some()),
+                ("--title", opts.title.is_some()),
+                ("--qr", opts.qr),
+            ];
+            for (flag, used) in flags {
+                if used {
+                    return Err(Error::InvalidFlag(format!(
+                        "{} cannot be used with --each, --stream, --chunk or --zip",
+                        flag
+                    )));
+                }
+            }
+        }
+        if opts.qr {
+            // Anything that reflows or cuts lines would leave a code nothing can scan
+            let flags = [
+                ("--wrap", opts.wrap.is_some()),
+                ("--clamp", opts.clamp),
+                ("--progress", opts.progress.is_some()),
+                ("--spinner", opts.spinner),
+                ("--countdown", opts.countdown.is_some()),
+                ("--every", opts.every.is_some()),
+            ];
+            for (flag, used) in flags {
+                if used {
+                    return Err(Error::InvalidFlag(format!(
+                        "{} cannot be used with --qr",
+                        flag
+                    )));
+                }
+            }
+        }
+        if opts.printf && (opts.dry_run || opts.export) {
+            return Err(Error::InvalidFlag(
+                "--printf cannot be used with --dry-run or --export".to_string(),
+            ));
+        }
+        if opts.printf && opts.escapes {
+            return Err(Error::InvalidFlag(
+                "-e cannot be used with --printf, which interprets escapes already".to_string(),
+            ));
+        }
+        if opts.printf && opts.dialect != Dialect::Default {
+            return Err(Error::InvalidFlag(
+                "--printf cannot be used with --rust-fmt or --python".to_string(),
+            ));
+        }
+        if opts.plain && opts.colorize.is_some() {
+            return Err(Error::InvalidFlag(
+                "--rainbow and --gradient cannot be used with --plain".to_string(),
+            ));
+        }
+        if opts.hr.is_some() && opts.fmt_str.is_some() {
+            return Err(Error::InvalidFlag(
+                "--hr prints a rule in place of FMT_STRING, it cannot be used with one".to_string(),
+            ));
+        }
+        if opts.every.is_some() && opts.countdown.is_some() {
+            return Err(Error::InvalidFlag(
+                "--every cannot be used with --countdown".to_string(),
+            ));
+        }
+        if opts.center && opts.block_align.is_some() {
+            return Err(Error::InvalidFlag(
+                "--center cannot be used with --block-align".to_string(),
+            ));
+        }
+        if opts.separator.is_some() && opts.columns.is_some() {
+            return Err(Error::InvalidFlag(
+                "--separator cannot be used with --columns".to_string(),
+            ));
+        }
+
+        opts.args.extend(input);
+        if opts.spinner {
+            match opts.args.iter().position(|arg| arg == "--") {
+                Some(end) if end + 1 < opts.args.len() => {
+                    opts.command = opts.args.split_off(end + 1);
+                    opts.args.pop();
+                }
+                _ => {
+                    return Err(Error::InvalidFlag(
+                        "--spinner requires a command to run after --".to_string(),
+                    ))
+                }
+            }
+        }
+        // The command of --spinner keeps its args as they are, @file might mean something to it
+        let args = std::mem::take(&mut opts.args);
+        for arg in args {
+            match arg.strip_prefix('@') {
+                Some(rest) if rest.starts_with('@') => opts.args.push(rest.to_string()),
+                Some(path) if !path.is_empty() => opts.args.extend(read_args(path)?),
+                _ => opts.args.push(arg),
+            }
+        }
+        for path in args_files {
+            opts.args.extend(read_args(&path)?);
+        }
+        Ok(opts)
+    }
+
+    /// Switches every decoration to ASCII, for terminals that can't show box drawing characters.
+    /// Rules made of anything else become dashes.
+    pub fn ascii_fallback(&mut self) {
+        self.ascii = true;
+        if self.frame.is_some() {
+            self.frame = Some(BoxStyle::Ascii);
+        }
+        if let Some(ref mut table) = self.table {
+            if table.border.is_some() {
+                table.border = Some(BoxStyle::Ascii);
+            }
+        }
+        if self.hr.as_deref().is_some_and(|hr| !hr.is_ascii()) {
+            self.hr = Some("-".to_string());
+        }
+    }
+}
+
+/// Reads the args of an args file, one per line. A line can be a name and a value like any arg.
+fn read_args(path: &str) -> crate::Result<Vec<String>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|err| Error::Other(format!("Unable to read args from {}: {}", path, err)))?;
+    Ok(text.lines().map(String::from).collect())
+}
+
+/// Splits `--flag=value` into its flag and value. Anything else is returned unchanged.
+fn split_flag(arg: &str) -> (&str, Option<&str>) {
+    match arg.split_once('=') {
+        Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+        _ => (arg, None),
+    }
+}
+
+fn no_value(flag: &str, inline: Option<&str>) -> crate::Result<()> {
+    match inline {
+        Some(_) => Err(Error::unexpected_flag_value(flag)),
+        None => Ok(()),
+    }
+}
+
+/// Returns the value given as `--flag=value`, or otherwise the next argument.
+fn take_value<I: Iterator<Item = String>>(
+    flag: &str,
+    inline: Option<&str>,
+    input: &mut I,
+) -> crate::Result<String> {
+    match inline {
+        Some(v) => Ok(v.to_string()),
+        None => input.next().ok_or_else(|| Error::missing_flag_value(flag)),
+    }
+}
+
+fn parse_num(flag: &str, value: &str) -> crate::Result<usize> {
+    value
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| Error::bad_flag_value(flag, value))
+}
+
+/// Parses a duration like `2s`, `500ms`, `1.5m` or `1h`, where a bare number is in seconds.
+fn parse_duration(flag: &str, value: &str) -> crate::Result<Duration> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let scale = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return Err(Error::bad_flag_value(flag, value)),
+    };
+    match number.parse::<f64>() {
+        Ok(n) if n > 0.0 => Ok(Duration::from_secs_f64(n * scale)),
+        _ => Err(Error::bad_flag_value(flag, value)),
+    }
+}
+
+/// `--flag` alone means the rendered output, `--flag=key` means a field of each record.
+fn parse_key(flag: &str, inline: Option<&str>) -> crate::Result<RecordKey> {
+    match inline.map(str::trim) {
+        Some("") => Err(Error::missing_flag_value(flag)),
+        Some(key) => Ok(RecordKey::Field(key.to_string())),
+        None => Ok(RecordKey::Rendered),
+    }
+}
+
+fn parse_align(flag: &str, value: &str) -> crate::Result<Alignment> {
+    match value.trim() {
+        "left" => Ok(Alignment::Left),
+        "center" => Ok(Alignment::Center),
+        "right" => Ok(Alignment::Right),
+        _ => Err(Error::bad_flag_value(flag, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn flags_and_args() {
+        let opts = Options::parse(["-D", "--clamp", "{} {}", "a", "--help"]).unwrap();
+        assert!(opts.debug);
+        assert!(opts.clamp);
+        assert_eq!(opts.help, None);
+        assert_eq!(opts.fmt_str.as_deref(), Some("{} {}"));
+        assert_eq!(opts.args, vec!["a", "--help"]);
+
+        let opts = Options::parse(["--help"]).unwrap();
+        assert_eq!(opts.help, Some(Help::Long));
+        assert_eq!(opts.fmt_str, None);
+
+        let opts = Options::parse(Vec::<String>::new()).unwrap();
+        assert_eq!(opts.help, None);
+        assert_eq!(opts.fmt_str, None);
+        assert!(opts.args.is_empty());
+    }
+
+    #[test]
+    fn dashed_format_strings() {
+        let opts = Options::parse(["-{}-", "a"]).unwrap();
+        assert_eq!(opts.fmt_str.as_deref(), Some("-{}-"));
+        assert_eq!(opts.args, vec!["a"]);
+
+        let opts = Options::parse(["--", "--clamp", "a"]).unwrap();
+        assert!(!opts.clamp);
+        assert_eq!(opts.fmt_str.as_deref(), Some("--clamp"));
+        assert_eq!(opts.args, vec!["a"]);
+
+        let opts = Options::parse(["--{}={}", "a", "b"]).unwrap();
+        assert_eq!(opts.fmt_str.as_deref(), Some("--{}={}"));
+    }
+
+    #[test]
+    fn values() {
+        let opts = Options::parse(["--wrap", "--hang", "4", "{}"]).unwrap();
+        assert_eq!(opts.wrap, Some(Width::Terminal));
+        assert_eq!(opts.hang, 4);
+        assert_eq!(opts.fmt_str.as_deref(), Some("{}"));
+
+        let opts = Options::parse(["--wrap=40", "--hang=2", "{}"]).unwrap();
+        assert_eq!(opts.wrap, Some(Width::Fixed(40)));
+        assert_eq!(opts.hang, 2);
+
+        let opts = Options::parse(["--hard-wrap", "--hang", "2", "{}"]).unwrap();
+        assert!(opts.hard_wrap);
+        assert_eq!(opts.wrap, Some(Width::Terminal));
+        assert_eq!(opts.hang, 2);
+        let opts = Options::parse(["--justify", "{}"]).unwrap();
+        assert!(opts.justify);
+        assert_eq!(opts.wrap, Some(Width::Terminal));
+        let opts = Options::parse(["--justify", "--wrap=40", "{}"]).unwrap();
+        assert_eq!(opts.wrap, Some(Width::Fixed(40)));
+
+        let opts = Options::parse(["--block-align", "right", "{}"]).unwrap();
+        assert_eq!(opts.block_align, Some(Alignment::Right));
+        let opts = Options::parse(["--block-align=center", "{}"]).unwrap();
+        assert_eq!(opts.block_align, Some(Alignment::Center));
+        let opts = Options::parse(["--center", "--width", "60", "{}"]).unwrap();
+        assert!(opts.center);
+        assert_eq!(opts.width, Some(60));
+        assert!(Options::parse(["--width=0", "{}"]).is_err());
+        assert_eq!(Options::parse(["--hr"]).unwrap().hr.as_deref(), Some("─"));
+        assert_eq!(
+            Options::parse(["--hr=-="]).unwrap().hr.as_deref(),
+            Some("-=")
+        );
+        assert!(Options::parse(["--hr", "{}"]).is_err());
+        let opts = Options::parse(["--box", "{}"]).unwrap();
+        assert_eq!(opts.frame, Some(BoxStyle::Single));
+        let opts = Options::parse(["--box=rounded", "{}"]).unwrap();
+        assert_eq!(opts.frame, Some(BoxStyle::Rounded));
+        assert!(Options::parse(["--box=heavy", "{}"]).is_err());
+        assert!(Options::parse(["--center", "--block-align=left", "{}"]).is_err());
+
+        let opts = Options::parse(["--each", "--columns", "{}"]).unwrap();
+        assert_eq!(opts.records, Some(RecordMode::Each));
+        assert_eq!(opts.columns, Some(Columns::Auto));
+        let opts = Options::parse(["--each", "--columns=3", "{}"]).unwrap();
+        assert_eq!(opts.columns, Some(Columns::Count(3)));
+        let opts = Options::parse(["--each", "--columns=auto", "{}"]).unwrap();
+        assert_eq!(opts.columns, Some(Columns::Auto));
+
+        let opts = Options::parse(["--each", "--sort", "{}"]).unwrap();
+        assert_eq!(opts.sort, Some(RecordKey::Rendered));
+        let opts = Options::parse(["--each", "--sort=size", "{}"]).unwrap();
+        assert_eq!(opts.sort, Some(RecordKey::Field("size".to_string())));
+
+        let opts = Options::parse(["--each", "--unique=0", "{}"]).unwrap();
+        assert_eq!(opts.unique, Some(RecordKey::Field("0".to_string())));
+
+        let opts = Options::parse([
+            "--stream",
+            "--header",
+            "Items:",
+            "--footer={count} items",
+            "{}",
+        ])
+        .unwrap();
+        assert_eq!(opts.records, Some(RecordMode::Stream));
+        assert_eq!(opts.header.as_deref(), Some("Items:"));
+        assert_eq!(opts.footer.as_deref(), Some("{count} items"));
+
+        let opts = Options::parse(["--each", "--separator=-", "{}"]).unwrap();
+        assert_eq!(opts.separator.as_deref(), Some("-"));
+
+        assert!(Options::parse(["--stream", "--stats", "{}"]).unwrap().stats);
+
+        assert!(Options::parse(["--dry-run", "{}"]).unwrap().dry_run);
+        assert!(Options::parse(["--copy", "{}"]).unwrap().copy);
+        assert!(Options::parse(["--plain", "{}"]).unwrap().plain);
+        assert!(Options::parse(["--stats-perf", "{}"]).unwrap().stats_perf);
+        assert!(
+            Options::parse(["--deterministic", "{}"])
+                .unwrap()
+                .deterministic
+        );
+        assert_eq!(Options::parse(["--seed=7", "{}"]).unwrap().seed, Some(7));
+        assert!(Options::parse(["--utc", "{now}"]).unwrap().utc);
+        assert!(Options::parse(["--seed", "x", "{}"]).is_err());
+
+        let opts = Options::parse(["snapshot", "--verify", "a.snap", "{}", "x"]).unwrap();
+        assert_eq!(opts.snapshot, Some(Snapshot::Verify("a.snap".into())));
+        assert_eq!(opts.args, vec!["x"]);
+        assert_eq!(
+            Options::parse(["--record=b.snap", "snapshot"])
+                .unwrap_err()
+                .to_string(),
+            "Invalid flag: --record and --verify can only be used with snapshot"
+        );
+        assert!(Options::parse(["snapshot", "{}"]).is_err());
+
+        let opts = Options::parse([
+            "table",
+            "--csv",
+            "--headers",
+            "--align=<>",
+            "--border=double",
+        ])
+        .unwrap();
+        let table = opts.table.unwrap();
+        assert_eq!(table.delimiter, Some(','));
+        assert!(table.headers);
+        assert_eq!(table.align, vec![Alignment::Left, Alignment::Right]);
+        assert_eq!(table.border, Some(BoxStyle::Double));
+        assert_eq!(
+            Options::parse(["table"]).unwrap().table,
+            Some(Table::default())
+        );
+        assert!(Options::parse(["table", "{}"]).is_err());
+        assert!(Options::parse(["table", "--align=<?"]).is_err());
+        let opts = Options::parse(["table", "--headers", "--output", "tsv"]).unwrap();
+        assert_eq!(opts.output, OutputFormat::Tsv);
+        assert!(Options::parse(["table", "--border=double", "--output=csv"]).is_err());
+        assert_eq!(
+            Options::parse(["--headers", "{}"]).unwrap_err().to_string(),
+            "Invalid flag: --headers can only be used with table"
+        );
+
+        let opts = Options::parse(["-e", "-n", "-n{}"]).unwrap();
+        assert!(opts.escapes);
+        assert!(opts.no_newline);
+        assert_eq!(opts.fmt_str.as_deref(), Some("-n{}"));
+        assert!(Options::parse(["-e", "--printf", "%s"]).is_err());
+        let style = Options::parse(["--bold", "--underline", "{}"])
+            .unwrap()
+            .style;
+        assert!(style.bold && style.underline && !style.italic);
+        assert!(Options::parse(["--bold=1", "{}"]).is_err());
+        assert!(Options::parse(["--highlight", "{}"]).unwrap().highlight);
+        assert!(Options::parse(["--pad-lines", "{}"]).unwrap().pad_lines);
+        let opts = Options::parse(["--color=never", "{}"]).unwrap();
+        assert_eq!(opts.color, Some(ColorChoice::Never));
+        let opts = Options::parse(["--color", "always", "{}"]).unwrap();
+        assert_eq!(opts.color, Some(ColorChoice::Always));
+        assert!(Options::parse(["--color=maybe", "{}"]).is_err());
+        let opts = Options::parse(["--rainbow", "{}"]).unwrap();
+        assert_eq!(opts.colorize, Some(Colorize::Rainbow));
+        let opts = Options::parse(["--gradient", "red", "#0000ff", "{}"]).unwrap();
+        assert_eq!(
+            opts.colorize,
+            Some(Colorize::Gradient(Color::Basic(1), Color::Rgb(0, 0, 255)))
+        );
+        assert!(Options::parse(["--gradient", "red", "nope", "{}"]).is_err());
+        assert!(Options::parse(["--gradient", "red"]).is_err());
+        assert!(Options::parse(["--rainbow", "--plain", "{}"]).is_err());
+        let opts = Options::parse(["--progress", "3", "10", "step {}"]).unwrap();
+        assert_eq!(opts.progress, Some((3, 10)));
+        assert_eq!(opts.fmt_str.as_deref(), Some("step {}"));
+        assert!(Options::parse(["--progress", "3", "0"]).is_err());
+        assert!(Options::parse(["--progress", "3"]).is_err());
+        assert!(Options::parse(["--each", "--progress", "1", "2", "{}"]).is_err());
+        let opts =
+            Options::parse(["--spinner", "building {}", "core", "--", "cargo", "build"]).unwrap();
+        assert!(opts.spinner);
+        assert_eq!(opts.args, vec!["core"]);
+        assert_eq!(opts.command, vec!["cargo", "build"]);
+        assert!(Options::parse(["--spinner", "building {}", "core"]).is_err());
+        assert!(Options::parse(["--spinner", "building", "--"]).is_err());
+        let opts = Options::parse(["--countdown", "5", "retrying in {remaining}s"]).unwrap();
+        assert_eq!(opts.countdown, Some(5));
+        assert!(Options::parse(["--countdown=-1", "{remaining}"]).is_err());
+        assert!(Options::parse(["--overwrite", "{}"]).unwrap().overwrite);
+        assert_eq!(
+            Options::parse(["--title", "{}"]).unwrap().title,
+            Some(Title::Only)
+        );
+        let opts = Options::parse(["--title=also", "{}"]).unwrap();
+        assert_eq!(opts.title, Some(Title::Also));
+        assert!(Options::parse(["--title=both", "{}"]).is_err());
+        let opts = Options::parse(["--at", "2,5", "--clear", "--clear-line", "{}"]).unwrap();
+        assert_eq!(
+            opts.screen,
+            Screen {
+                clear: true,
+                clear_line: true,
+                at: Some((2, 5)),
+            }
+        );
+        assert!(Options::parse(["--at=0,5", "{}"]).is_err());
+        assert!(Options::parse(["--at=2", "{}"]).is_err());
+        assert!(Options::parse(["--at=a,b", "{}"]).is_err());
+        let mut opts = Options::parse(["--box=double", "{}"]).unwrap();
+        opts.ascii_fallback();
+        assert!(opts.ascii);
+        assert_eq!(opts.frame, Some(BoxStyle::Ascii));
+        let mut opts = Options::parse(["--hr"]).unwrap();
+        opts.ascii_fallback();
+        assert_eq!(opts.hr.as_deref(), Some("-"));
+        assert_eq!(opts.frame, None);
+        let mut opts = Options::parse(["--hr=+="]).unwrap();
+        opts.ascii_fallback();
+        assert_eq!(opts.hr.as_deref(), Some("+="));
+        let mut opts = Options::parse(["table", "--border", "rounded"]).unwrap();
+        opts.ascii_fallback();
+        assert_eq!(opts.table.unwrap().border, Some(BoxStyle::Ascii));
+        assert!(Options::parse(["--ascii", "{}"]).unwrap().ascii);
+        assert!(Options::parse(["--no-config", "{}"]).unwrap().no_config);
+
+        let path = std::env::temp_dir().join(format!("fmt-template-{}.txt", std::process::id()));
+        std::fs::write(&path, "Dear {name},\n\n{0}\n").unwrap();
+        let template = path.to_str().unwrap();
+        let opts = Options::parse(["-F", template, "--wrap", "hi", "name=Tony"]).unwrap();
+        assert_eq!(opts.fmt_str.as_deref(), Some("Dear {name},\n\n{0}"));
+        assert_eq!(opts.args, vec!["hi", "name=Tony"]);
+        assert!(opts.wrap.is_some());
+        let opts = Options::parse([&format!("--template={}", template), "--", "-x"]).unwrap();
+        assert_eq!(opts.args, vec!["-x"]);
+        let opts = Options::parse(["-F", template]).unwrap();
+        assert!(opts.fmt_str.is_some() && opts.args.is_empty());
+        assert!(Options::parse(["--hr", "-F", template]).is_err());
+        std::fs::remove_file(&path).unwrap();
+        assert!(Options::parse(["-F", template, "x"]).is_err());
+        assert!(Options::parse(["-F"]).is_err());
+        assert!(Options::parse(["--stream", "-"]).is_err());
+        assert!(Options::parse(["--stdin-args", "{0}"]).unwrap().stdin_args);
+        assert!(Options::parse(["--stdin-args", "--stream", "{}"]).is_err());
+        assert!(Options::parse(["--stdin-args", "-"]).is_err());
+        assert!(Options::parse(["table", "--stdin-args"]).is_err());
+
+        let path = std::env::temp_dir().join(format!("fmt-args-{}.txt", std::process::id()));
+        std::fs::write(&path, "one\nname = Tony\r\n\nlast\n").unwrap();
+        let file = path.to_str().unwrap();
+        let at = format!("@{}", file);
+        let opts = Options::parse(["{}", "first", &at, "@@home", "@"]).unwrap();
+        assert_eq!(
+            opts.args,
+            vec!["first", "one", "name = Tony", "", "last", "@home", "@"]
+        );
+        let opts = Options::parse(["--args-file", file, "{}", "first"]).unwrap();
+        assert_eq!(opts.args, vec!["first", "one", "name = Tony", "", "last"]);
+        let opts = Options::parse(["--spinner", "{}", &at, "--", "curl", &at]).unwrap();
+        assert_eq!(opts.args.len(), 4);
+        assert_eq!(opts.command, vec!["curl".to_string(), at.clone()]);
+        std::fs::remove_file(&path).unwrap();
+        assert!(Options::parse(["{}", &at]).is_err());
+        assert!(Options::parse(["--args-file", file, "{}"]).is_err());
+        assert!(Options::parse(["--stream", "-F", "-"]).is_err());
+        assert!(Options::parse(["table", "-F", "-"]).is_err());
+        let opts = Options::parse(["--", "-", "x"]).unwrap();
+        assert_eq!(opts.fmt_str.as_deref(), Some("-"));
+        assert!(Options::parse(["--qr", "{}"]).unwrap().qr);
+        assert!(Options::parse(["--qr", "--wrap", "{}"]).is_err());
+        assert!(Options::parse(["--qr", "--each", "{}"]).is_err());
+        let opts = Options::parse(["--bell", "--notify", "{}"]).unwrap();
+        assert!(opts.bell && opts.notify);
+        let every = |value: &str| Options::parse(["--every", value, "{now}"]).map(|o| o.every);
+        assert_eq!(every("2s").unwrap(), Some(Duration::from_secs(2)));
+        assert_eq!(every("500ms").unwrap(), Some(Duration::from_millis(500)));
+        assert_eq!(every("1.5m").unwrap(), Some(Duration::from_secs(90)));
+        assert_eq!(every("3").unwrap(), Some(Duration::from_secs(3)));
+        assert!(every("0s").is_err());
+        assert!(every("2d").is_err());
+        assert!(every("s").is_err());
+        assert!(Options::parse(["--every=1", "--countdown=3", "{}"]).is_err());
+        let opts = Options::parse(["--typewriter", "{}"]).unwrap();
+        assert_eq!(opts.typewriter, Some(DEFAULT_TYPEWRITER_MS));
+        let opts = Options::parse(["--typewriter=5", "{}"]).unwrap();
+        assert_eq!(opts.typewriter, Some(5));
+        assert!(Options::parse(["--typewriter=fast", "{}"]).is_err());
+        let opts = Options::parse(["{} {}", "--", "x"]).unwrap();
+        assert_eq!(opts.args, vec!["--", "x"]);
+
+        assert!(Options::parse(["--printf", "%s"]).unwrap().printf);
+        assert!(Options::parse(["--printf", "--each", "%s"]).is_err());
+        assert!(Options::parse(["--printf", "--dry-run", "%s"]).is_err());
+        let opts = Options::parse(["--rust-fmt", "{:05}", "7"]).unwrap();
+        assert_eq!(opts.dialect, Dialect::Rust);
+        assert!(Options::parse(["--rust-fmt", "--printf", "%s"]).is_err());
+        let opts = Options::parse(["--python", "{0!r:>10}", "x"]).unwrap();
+        assert_eq!(opts.dialect, Dialect::Python);
+        assert!(Options::parse(["--python", "--rust-fmt", "{}"]).is_err());
+        assert_eq!(
+            Options::parse(["{}", "snapshot"])
+                .unwrap()
+                .fmt_str
+                .as_deref(),
+            Some("{}")
+        );
+        assert!(Options::parse(["--export", "{}"]).unwrap().export);
+        assert!(Options::parse(["--stream", "--export", "{}"]).is_err());
+
+        let opts = Options::parse(["{}"]).unwrap();
+        assert_eq!(opts.targets, vec![Target::Stdout]);
+        let opts = Options::parse(["-o", "out.txt", "{}"]).unwrap();
+        assert_eq!(opts.targets, vec![Target::File("out.txt".into())]);
+        let opts = Options::parse(["-o", "out.txt", "--tee", "{}"]).unwrap();
+        assert_eq!(
+            opts.targets,
+            vec![Target::File("out.txt".into()), Target::Stdout]
+        );
+        let opts = Options::parse(["--tee=stderr,stdout", "{}"]).unwrap();
+        assert_eq!(opts.targets, vec![Target::Stdout, Target::Stderr]);
+        assert!(Options::parse(["--tee=file", "{}"]).is_err());
+
+        let opts = Options::parse(["--chunk", "2", "--output=csv", "{}"]).unwrap();
+        assert_eq!(opts.records, Some(RecordMode::Chunk(2)));
+        assert_eq!(opts.output, OutputFormat::Csv);
+
+        assert!(Options::parse(["--output", "tsv", "{}"]).is_err());
+        assert!(Options::parse(["--each", "--output", "xml", "{}"]).is_err());
+        assert!(Options::parse(["--chunk=0", "{}"]).is_err());
+        let opts = Options::parse(["--zip=2", "--output", "tsv", "{}"]).unwrap();
+        assert_eq!(opts.records, Some(RecordMode::Zip(2)));
+        assert!(Options::parse(["--zip", "0", "{}"]).is_err());
+        assert!(Options::parse(["--footer", "done", "{}"]).is_err());
+        assert!(Options::parse(["--each", "--dry-run", "{}"]).is_err());
+        assert!(Options::parse(["--stats", "{}"]).is_err());
+        assert!(Options::parse(["--each", "--columns", "--separator", "-", "{}"]).is_err());
+        assert!(Options::parse(["--each", "--sort=", "{}"]).is_err());
+        assert!(Options::parse(["--unique", "{}"]).is_err());
+        assert!(Options::parse(["--sort", "{}"]).is_err());
+        assert!(Options::parse(["--columns", "{}"]).is_err());
+        assert!(Options::parse(["--each", "--columns=0", "{}"]).is_err());
+        assert!(Options::parse(["--block-align=up", "{}"]).is_err());
+        assert!(Options::parse(["--wrap=wide", "{}"]).is_err());
+        assert!(Options::parse(["--wrap", "--hang"]).is_err());
+        assert!(Options::parse(["--hang", "4", "{}"]).is_err());
+        assert!(Options::parse(["--clamp=10", "{}"]).is_err());
+    }
+}
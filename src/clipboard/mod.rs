@@ -0,0 +1,88 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use crate::encode;
+
+/// Commands that put what they read from stdin on the system clipboard: macOS, Wayland, X11 (with
+/// either of the two usual tools) and Windows, which WSL can run as well.
+const COMMANDS: [(&str, &[&str]); 5] = [
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Builds the OSC 52 sequence asking the terminal to put `text` on the system clipboard. Inside of
+/// tmux the sequence is wrapped in a passthrough, so it reaches the terminal tmux is running in.
+pub fn osc52(text: &str, tmux: bool) -> String {
+    let seq = format!("\x1b]52;c;{}\x07", encode::base64(text.as_bytes()));
+    if tmux {
+        format!("\x1bPtmux;\x1b{}\x1b\\", seq)
+    } else {
+        seq
+    }
+}
+
+/// Copies `text` to the system clipboard with the first of the [`COMMANDS`] that works, or else
+/// through the terminal using [`osc52`], which also works over ssh. Returns false if neither was
+/// possible.
+pub fn copy(text: &str) -> bool {
+    COMMANDS
+        .iter()
+        .any(|(command, args)| copy_with(command, args, text))
+        || copy_to_terminal(text).is_ok()
+}
+
+/// Pipes `text` into `command`, returning whether it ran and succeeded.
+fn copy_with(command: &str, args: &[&str], text: &str) -> bool {
+    let child = Command::new(command)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return false;
+    };
+    let written = match child.stdin.take() {
+        Some(mut stdin) => stdin.write_all(text.as_bytes()).is_ok(),
+        None => false,
+    };
+    matches!(child.wait(), Ok(status) if status.success()) && written
+}
+
+/// Sends the [`osc52`] sequence to whichever of stdout, stderr, or the controlling terminal is
+/// actually a terminal, so copying still works when the output itself is piped somewhere else.
+fn copy_to_terminal(text: &str) -> crate::Result<()> {
+    let seq = osc52(text, std::env::var_os("TMUX").is_some());
+    if std::io::stdout().is_terminal() {
+        let mut out = std::io::stdout().lock();
+        out.write_all(seq.as_bytes())?;
+        out.flush()?;
+    } else if std::io::stderr().is_terminal() {
+        std::io::stderr().lock().write_all(seq.as_bytes())?;
+    } else {
+        let mut tty = std::fs::OpenOptions::new().write(true).open("/dev/tty")?;
+        tty.write_all(seq.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn sequences() {
+        assert_eq!(osc52("hi", false), "\x1b]52;c;aGk=\x07");
+        assert_eq!(osc52("hi", true), "\x1bPtmux;\x1b\x1b]52;c;aGk=\x07\x1b\\");
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use super::{Color, ColorLevel};
+use crate::ansi::{self, Segment};
+
+/// How many graphemes it takes `--rainbow` to go through every hue once
+const RAINBOW_PERIOD: usize = 30;
+
+/// Colors every grapheme of `text` with the hues of a rainbow, shifting them on every line so the
+/// colors run diagonally like lolcat's do.
+pub fn rainbow(text: &str, level: ColorLevel) -> String {
+    colorize(text, level, |row, col, _| {
+        let hue = (col + 3 * row) % RAINBOW_PERIOD;
+        hsv(hue as f64 * 360.0 / RAINBOW_PERIOD as f64)
+    })
+}
+
+/// Colors every line of `text` with a gradient going from `from` at its first grapheme to `to`
+/// at its last one.
+pub fn gradient(text: &str, from: Color, to: Color, level: ColorLevel) -> String {
+    let (from, to) = (from.to_rgb(), to.to_rgb());
+    colorize(text, level, |_, col, len| {
+        let t = match len {
+            0 | 1 => 0.0,
+            _ => col as f64 / (len - 1) as f64,
+        };
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Color::Rgb(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+    })
+}
+
+/// Gives every visible grapheme of `text` the foreground picked by `color` from its line, its
+/// column (in graphemes) and the number of graphemes on its line. Escape sequences already in
+/// `text` are kept, so other styling like bold survives. Nothing changes at [`ColorLevel::None`].
+fn colorize<F>(text: &str, level: ColorLevel, color: F) -> String
+where
+    F: Fn(usize, usize, usize) -> Color,
+{
+    if level == ColorLevel::None {
+        return text.to_string();
+    }
+    let mut output = String::with_capacity(text.len() * 8);
+    for (row, line) in text.split('\n').enumerate() {
+        if row > 0 {
+            output.push('\n');
+        }
+        let len = ansi::grapheme_indices(&ansi::strip(line)).count();
+        let mut col = 0usize;
+        for seg in ansi::segments(line) {
+            let text = match seg {
+                Segment::Escape(e) => {
+                    output.push_str(e);
+                    continue;
+                }
+                Segment::Text(t) => t,
+            };
+            for (_, g) in ansi::grapheme_indices(text) {
+                match color(row, col, len).downgrade(level) {
+                    Some(c) if !g.trim().is_empty() => {
+                        output.push_str(&format!("\x1b[{}m{}", c.fg_sgr(), g))
+                    }
+                    _ => output.push_str(g),
+                }
+                col += 1;
+            }
+        }
+        if col > 0 {
+            output.push_str("\x1b[39m");
+        }
+    }
+    output
+}
+
+/// The fully saturated, fully bright color with the hue `h` in degrees.
+fn hsv(h: f64) -> Color {
+    let x = 1.0 - ((h / 60.0) % 2.0 - 1.0).abs();
+    let (r, g, b) = match (h / 60.0) as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    let channel = |v: f64| (v * 255.0).round() as u8;
+    Color::Rgb(channel(r), channel(g), channel(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn hues() {
+        assert_eq!(hsv(0.0), Color::Rgb(255, 0, 0));
+        assert_eq!(hsv(60.0), Color::Rgb(255, 255, 0));
+        assert_eq!(hsv(120.0), Color::Rgb(0, 255, 0));
+        assert_eq!(hsv(240.0), Color::Rgb(0, 0, 255));
+        assert_eq!(hsv(300.0), Color::Rgb(255, 0, 255));
+    }
+
+    #[test]
+    fn gradients() {
+        let black = Color::Rgb(0, 0, 0);
+        let white = Color::Rgb(254, 254, 254);
+        assert_eq!(
+            gradient("a b", black, white, ColorLevel::TrueColor),
+            "\x1b[38;2;0;0;0ma \x1b[38;2;254;254;254mb\x1b[39m"
+        );
+        assert_eq!(
+            gradient("abc\nd", black, white, ColorLevel::TrueColor),
+            "\x1b[38;2;0;0;0ma\x1b[38;2;127;127;127mb\x1b[38;2;254;254;254mc\x1b[39m\n\
+             \x1b[38;2;0;0;0md\x1b[39m"
+        );
+        assert_eq!(gradient("ab", black, white, ColorLevel::None), "ab");
+        assert_eq!(gradient("", black, white, ColorLevel::TrueColor), "");
+    }
+
+    #[test]
+    fn rainbows() {
+        let output = rainbow("\x1b[1mab\x1b[0m\nc", ColorLevel::TrueColor);
+        assert_eq!(ansi::strip(&output), "ab\nc");
+        assert!(output.starts_with("\x1b[1m\x1b[38;2;255;0;0ma\x1b[38;2;255;51;0mb"));
+        // Every line starts a little further along the rainbow
+        assert!(output.ends_with("\n\x1b[38;2;255;153;0mc\x1b[39m"));
+        assert_eq!(
+            rainbow("ab", ColorLevel::Basic),
+            "\x1b[91ma\x1b[91mb\x1b[39m"
+        );
+    }
+}
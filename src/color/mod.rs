@@ -0,0 +1,365 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+mod gradient;
+mod policy;
+
+pub use gradient::{gradient, rainbow};
+pub use policy::{choice, enabled, set_choice, ColorChoice};
+
+use once_cell::sync::OnceCell;
+
+/// The richest color representation the attached terminal is believed to understand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorLevel {
+    None,
+    /// The 16 standard ANSI colors (`30-37`, `90-97`)
+    Basic,
+    /// The xterm 256 color palette (`38;5;n`)
+    Ansi256,
+    /// 24-bit color (`38;2;r;g;b`)
+    TrueColor,
+}
+
+impl ColorLevel {
+    pub fn detect() -> Self {
+        Self::from_env(|key| std::env::var(key).ok())
+    }
+
+    pub(crate) fn from_env<F: Fn(&str) -> Option<String>>(var: F) -> Self {
+        if let Some(ct) = var("COLORTERM") {
+            if ct.eq_ignore_ascii_case("truecolor") || ct.eq_ignore_ascii_case("24bit") {
+                return Self::TrueColor;
+            }
+        }
+
+        // Windows Terminal and a handful of emulators support 24-bit color without advertising it
+        if var("WT_SESSION").is_some() {
+            return Self::TrueColor;
+        }
+        if let Some(program) = var("TERM_PROGRAM") {
+            if matches!(program.as_str(), "iTerm.app" | "WezTerm" | "vscode") {
+                return Self::TrueColor;
+            }
+        }
+
+        match var("TERM") {
+            Some(term) if term.ends_with("-direct") || term.ends_with("truecolor") => {
+                Self::TrueColor
+            }
+            Some(term) if term.contains("256") => Self::Ansi256,
+            // TODO: Is there any reasonable way to tell if a terminal supports *no* colors at all?
+            _ => Self::Basic,
+        }
+    }
+}
+
+/// Returns the detected [`ColorLevel`] of the current terminal. Detection only happens once.
+pub fn level() -> ColorLevel {
+    static LEVEL: OnceCell<ColorLevel> = OnceCell::new();
+    *LEVEL.get_or_init(ColorLevel::detect)
+}
+
+/// Returns the [`ColorLevel`] that output should actually use, which is [`ColorLevel::None`]
+/// whenever the color policy says not to color it (by default, when it isn't going to a
+/// terminal), or when built without `color`. See [`crate::term::capabilities`].
+pub fn effective_level() -> ColorLevel {
+    crate::term::capabilities().color
+}
+
+/// The 16 standard colors, using the default xterm values
+const BASIC_PALETTE: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The channel values used by the 6x6x6 color cube of the 256 color palette
+const CUBE_STEPS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The names of the 8 normal colors, in the order of their SGR codes. Their bright versions are
+/// prefixed with `bright_`.
+const NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    /// One of the 16 standard colors, `0..8` are normal and `8..16` are bright
+    Basic(u8),
+    Ansi256(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// Parses a color name like `red` or `bright_red`, a hex color like `#ff8800`, or an index
+    /// into the 256 color palette like `color256=208`.
+    pub fn parse(name: &str) -> Option<Color> {
+        if let Some(hex) = name.strip_prefix('#') {
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+        if let Some(index) = name.strip_prefix("color256=") {
+            return index.parse().ok().map(Color::Ansi256);
+        }
+        if matches!(name, "gray" | "grey") {
+            return Some(Color::Basic(8));
+        }
+        let (name, bright) = match name.strip_prefix("bright_") {
+            Some(name) => (name, 8),
+            None => (name, 0),
+        };
+        let n = NAMES.iter().position(|c| *c == name)?;
+        Some(Color::Basic(n as u8 + bright))
+    }
+
+    /// Converts this color into the best representation available at `level`, or `None` if
+    /// colors are not available at all.
+    pub fn downgrade(self, level: ColorLevel) -> Option<Color> {
+        match (level, self) {
+            (ColorLevel::None, _) => None,
+            (ColorLevel::TrueColor, c) => Some(c),
+            (ColorLevel::Ansi256, Color::Rgb(r, g, b)) => Some(Color::Ansi256(rgb_to_256(r, g, b))),
+            (ColorLevel::Ansi256, c) => Some(c),
+            (ColorLevel::Basic, Color::Basic(n)) => Some(Color::Basic(n)),
+            (ColorLevel::Basic, c) => {
+                let (r, g, b) = c.to_rgb();
+                Some(Color::Basic(rgb_to_basic(r, g, b)))
+            }
+        }
+    }
+
+    pub fn to_rgb(self) -> (u8, u8, u8) {
+        match self {
+            Color::Basic(n) => BASIC_PALETTE[(n & 0xF) as usize],
+            Color::Ansi256(n) if n < 16 => BASIC_PALETTE[n as usize],
+            Color::Ansi256(n) if n >= 232 => {
+                let v = 8 + (n - 232) * 10;
+                (v, v, v)
+            }
+            Color::Ansi256(n) => {
+                let n = n - 16;
+                (
+                    CUBE_STEPS[(n / 36) as usize],
+                    CUBE_STEPS[((n / 6) % 6) as usize],
+                    CUBE_STEPS[(n % 6) as usize],
+                )
+            }
+            Color::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+
+    /// This color as a CSS style hex color, like `#cd0000`
+    pub fn to_hex(self) -> String {
+        let (r, g, b) = self.to_rgb();
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    /// The SGR parameters that select this color as the foreground
+    pub fn fg_sgr(self) -> String {
+        match self {
+            Color::Basic(n) if n < 8 => format!("{}", 30 + n),
+            Color::Basic(n) => format!("{}", 90 + (n & 0x7)),
+            Color::Ansi256(n) => format!("38;5;{}", n),
+            Color::Rgb(r, g, b) => format!("38;2;{};{};{}", r, g, b),
+        }
+    }
+
+    /// The SGR parameters that select this color as the background
+    pub fn bg_sgr(self) -> String {
+        match self {
+            Color::Basic(n) if n < 8 => format!("{}", 40 + n),
+            Color::Basic(n) => format!("{}", 100 + (n & 0x7)),
+            Color::Ansi256(n) => format!("48;5;{}", n),
+            Color::Rgb(r, g, b) => format!("48;2;{};{};{}", r, g, b),
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let dr = r1.abs_diff(r2) as u32;
+    let dg = g1.abs_diff(g2) as u32;
+    let db = b1.abs_diff(b2) as u32;
+    dr * dr + dg * dg + db * db
+}
+
+fn nearest_cube_step(v: u8) -> u8 {
+    CUBE_STEPS
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, step)| step.abs_diff(v))
+        .map(|(i, _)| i as u8)
+        .unwrap_or(0)
+}
+
+fn rgb_to_256(r: u8, g: u8, b: u8) -> u8 {
+    let (ri, gi, bi) = (
+        nearest_cube_step(r),
+        nearest_cube_step(g),
+        nearest_cube_step(b),
+    );
+    let cube = 16 + 36 * ri + 6 * gi + bi;
+    let cube_rgb = Color::Ansi256(cube).to_rgb();
+
+    // The grayscale ramp is often a much better match for desaturated colors
+    let avg = ((r as u16 + g as u16 + b as u16) / 3) as u8;
+    let gray = if avg < 8 {
+        232
+    } else {
+        232 + ((avg - 8) / 10).min(23)
+    };
+    let gray_rgb = Color::Ansi256(gray).to_rgb();
+
+    if distance((r, g, b), gray_rgb) < distance((r, g, b), cube_rgb) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn rgb_to_basic(r: u8, g: u8, b: u8) -> u8 {
+    BASIC_PALETTE
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, &c)| distance((r, g, b), c))
+        .map(|(i, _)| i as u8)
+        .unwrap_or(7)
+}
+
+/// Wraps `text` in the escape sequences needed to color it `fg`, downgrading the color to
+/// whatever the terminal supports, or leaving it untouched if color is disabled.
+pub fn paint<S: std::fmt::Display>(text: S, fg: Color) -> String {
+    paint_at(text, fg, effective_level())
+}
+
+pub fn paint_at<S: std::fmt::Display>(text: S, fg: Color, level: ColorLevel) -> String {
+    match fg.downgrade(level) {
+        Some(c) => format!("\x1b[{}m{}\x1b[0m", c.fg_sgr(), text),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn detection() {
+        assert_eq!(
+            ColorLevel::from_env(env(&[("COLORTERM", "truecolor"), ("TERM", "xterm")])),
+            ColorLevel::TrueColor
+        );
+        assert_eq!(
+            ColorLevel::from_env(env(&[("COLORTERM", "24bit")])),
+            ColorLevel::TrueColor
+        );
+        assert_eq!(
+            ColorLevel::from_env(env(&[("TERM", "xterm-256color")])),
+            ColorLevel::Ansi256
+        );
+        assert_eq!(
+            ColorLevel::from_env(env(&[("TERM", "xterm-direct")])),
+            ColorLevel::TrueColor
+        );
+        assert_eq!(
+            ColorLevel::from_env(env(&[("TERM", "xterm")])),
+            ColorLevel::Basic
+        );
+        assert_eq!(
+            ColorLevel::from_env(env(&[("WT_SESSION", "abc"), ("TERM", "xterm")])),
+            ColorLevel::TrueColor
+        );
+        assert_eq!(ColorLevel::from_env(env(&[])), ColorLevel::Basic);
+    }
+
+    #[test]
+    fn downgrade() {
+        let orange = Color::Rgb(255, 135, 0);
+        assert_eq!(orange.downgrade(ColorLevel::TrueColor), Some(orange));
+        assert_eq!(
+            orange.downgrade(ColorLevel::Ansi256),
+            Some(Color::Ansi256(208))
+        );
+        assert_eq!(orange.downgrade(ColorLevel::None), None);
+
+        let gray = Color::Rgb(128, 128, 128);
+        assert_eq!(
+            gray.downgrade(ColorLevel::Ansi256),
+            Some(Color::Ansi256(244))
+        );
+        assert_eq!(gray.downgrade(ColorLevel::Basic), Some(Color::Basic(8)));
+
+        assert_eq!(
+            Color::Rgb(250, 10, 10).downgrade(ColorLevel::Basic),
+            Some(Color::Basic(9))
+        );
+        assert_eq!(
+            Color::Ansi256(21).downgrade(ColorLevel::Basic),
+            Some(Color::Basic(4))
+        );
+        assert_eq!(
+            Color::Basic(3).downgrade(ColorLevel::Ansi256),
+            Some(Color::Basic(3))
+        );
+    }
+
+    #[test]
+    fn palette_round_trip() {
+        for n in 16..=255u8 {
+            let (r, g, b) = Color::Ansi256(n).to_rgb();
+            // A few gray ramp entries are also exactly representable in the cube, either is fine
+            assert_eq!(
+                Color::Ansi256(rgb_to_256(r, g, b)).to_rgb(),
+                (r, g, b),
+                "palette index {} did not round trip",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn sgr() {
+        assert_eq!(Color::Basic(1).fg_sgr(), "31");
+        assert_eq!(Color::Basic(9).fg_sgr(), "91");
+        assert_eq!(Color::Basic(4).bg_sgr(), "44");
+        assert_eq!(Color::Basic(12).bg_sgr(), "104");
+        assert_eq!(Color::Ansi256(208).fg_sgr(), "38;5;208");
+        assert_eq!(Color::Rgb(1, 2, 3).bg_sgr(), "48;2;1;2;3");
+        assert_eq!(
+            paint_at("hi", Color::Rgb(255, 0, 0), ColorLevel::Basic),
+            "\x1b[91mhi\x1b[0m"
+        );
+        assert_eq!(
+            paint_at("hi", Color::Rgb(255, 0, 0), ColorLevel::None),
+            "hi"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use once_cell::sync::OnceCell;
+
+/// Whether colored output should be produced at all, independent of what the terminal supports.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// Reads the common color conventions from the environment:
+    /// - `CLICOLOR_FORCE` (anything but `0`) forces color on, even over `NO_COLOR`
+    /// - `NO_COLOR` (any non-empty value) turns color off, see <https://no-color.org>
+    /// - `CLICOLOR=0` turns color off
+    /// - `TERM=dumb` turns color off
+    pub fn from_env() -> Self {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    pub(crate) fn from_vars<F: Fn(&str) -> Option<String>>(var: F) -> Self {
+        if matches!(var("CLICOLOR_FORCE"), Some(v) if !v.is_empty() && v != "0") {
+            return Self::Always;
+        }
+        if matches!(var("NO_COLOR"), Some(v) if !v.is_empty()) {
+            return Self::Never;
+        }
+        if matches!(var("CLICOLOR"), Some(v) if v == "0") {
+            return Self::Never;
+        }
+        if matches!(var("TERM"), Some(v) if v == "dumb") {
+            return Self::Never;
+        }
+
+        Self::Auto
+    }
+
+    /// Parses the value of `--color`: `auto`, `always` or `never`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "auto" => Some(Self::Auto),
+            "always" => Some(Self::Always),
+            "never" => Some(Self::Never),
+            _ => None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !matches!(self, Self::Never)
+    }
+
+    /// Whether a stream should get color, `terminal` being whether that stream is a terminal.
+    pub fn for_stream(&self, terminal: bool) -> bool {
+        match self {
+            Self::Auto => terminal,
+            Self::Always => true,
+            Self::Never => false,
+        }
+    }
+}
+
+static CHOICE: OnceCell<ColorChoice> = OnceCell::new();
+
+/// Makes `choice` the active [`ColorChoice`] in place of the one in the environment, for `--color`.
+/// Only the first call has any effect, and only if color wasn't needed before it.
+pub fn set_choice(choice: ColorChoice) {
+    let _ = CHOICE.set(choice);
+}
+
+/// The active [`ColorChoice`]. Unless [`set_choice`] says otherwise, this is read from the environment
+/// the first time it is needed.
+pub fn choice() -> ColorChoice {
+    *CHOICE.get_or_init(ColorChoice::from_env)
+}
+
+/// Returns true if anything (help, styled specs, errors) is allowed to emit color.
+pub fn enabled() -> bool {
+    choice().is_enabled()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    fn env<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(ColorChoice::parse("auto"), Some(ColorChoice::Auto));
+        assert_eq!(ColorChoice::parse("always"), Some(ColorChoice::Always));
+        assert_eq!(ColorChoice::parse(" never"), Some(ColorChoice::Never));
+        assert_eq!(ColorChoice::parse("sometimes"), None);
+    }
+
+    #[test]
+    fn per_stream() {
+        assert!(ColorChoice::Auto.for_stream(true));
+        assert!(!ColorChoice::Auto.for_stream(false));
+        assert!(ColorChoice::Always.for_stream(false));
+        assert!(!ColorChoice::Never.for_stream(true));
+    }
+
+    #[test]
+    fn conventions() {
+        assert_eq!(ColorChoice::from_vars(env(&[])), ColorChoice::Auto);
+        assert_eq!(
+            ColorChoice::from_vars(env(&[("NO_COLOR", "1")])),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            ColorChoice::from_vars(env(&[("NO_COLOR", "")])),
+            ColorChoice::Auto
+        );
+        assert_eq!(
+            ColorChoice::from_vars(env(&[("CLICOLOR", "0")])),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            ColorChoice::from_vars(env(&[("CLICOLOR", "1")])),
+            ColorChoice::Auto
+        );
+        assert_eq!(
+            ColorChoice::from_vars(env(&[("TERM", "dumb")])),
+            ColorChoice::Never
+        );
+        assert_eq!(
+            ColorChoice::from_vars(env(&[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")])),
+            ColorChoice::Always
+        );
+        assert_eq!(
+            ColorChoice::from_vars(env(&[("CLICOLOR_FORCE", "0"), ("TERM", "dumb")])),
+            ColorChoice::Never
+        );
+    }
+}
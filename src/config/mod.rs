@@ -0,0 +1,236 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The config file, `~/.config/term-println/config.toml`, with defaults for some of the flags and
+//! named styles (themes) that specs can use like colors:
+//!
+//! ```toml
+//! color = "always"
+//! fill = "."
+//! wrap = 80
+//!
+//! [themes]
+//! warn = "bold.yellow"
+//! ok = "green"
+//!
+//! [aliases]
+//! greeting = "Hello {name}, it is {now:%H:%M}"
+//! ```
+//!
+//! Flags take precedence over the environment, which takes precedence over the config file.
+
+use std::{borrow::Cow, collections::HashMap, path::PathBuf};
+
+use crate::{ansi::Style, color::ColorChoice, style, Error};
+
+/// What the config file sets, everything it doesn't is `None` or empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// `color`, like `--color`
+    pub color: Option<ColorChoice>,
+    /// `fill`, the fill character of specs that don't give one
+    pub fill: Option<char>,
+    /// `wrap`, like `--wrap=N`
+    pub wrap: Option<usize>,
+    /// `[themes]`, styles by name
+    pub themes: HashMap<String, Style>,
+    /// `[aliases]`, format strings by name, used as `@name` in place of a FMT_STRING
+    pub aliases: HashMap<String, String>,
+}
+
+impl Config {
+    /// Where the config file is: `TERM_PRINTLN_CONFIG` if it is set, otherwise
+    /// `term-println/config.toml` in `XDG_CONFIG_HOME` or in `~/.config`.
+    pub fn path() -> Option<PathBuf> {
+        let var = |key: &str| std::env::var_os(key).filter(|v| !v.is_empty());
+        if let Some(path) = var("TERM_PRINTLN_CONFIG") {
+            return Some(PathBuf::from(path));
+        }
+        let dir = match var("XDG_CONFIG_HOME") {
+            Some(dir) => PathBuf::from(dir),
+            None => PathBuf::from(var("HOME").or_else(|| var("USERPROFILE"))?).join(".config"),
+        };
+        Some(dir.join("term-println").join("config.toml"))
+    }
+
+    /// Reads the config file, if there is one, and lets the environment override it with
+    /// `TERM_PRINTLN_FILL` and `TERM_PRINTLN_WRAP`.
+    pub fn load() -> crate::Result<Self> {
+        let config = match Self::path() {
+            Some(path) if path.is_file() => {
+                let text = std::fs::read_to_string(&path)?;
+                Self::parse(&text).map_err(|err| {
+                    Error::Other(format!("Invalid config file {}: {}", path.display(), err))
+                })?
+            }
+            _ => Self::default(),
+        };
+        config.with_env(|key| std::env::var(key).ok())
+    }
+
+    /// Parses the text of a config file, or returns a description of what is wrong with it.
+    #[cfg(feature = "config")]
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let table = text.parse::<toml::Value>().map_err(|err| err.to_string())?;
+        let table = table.as_table().ok_or("expected a table")?;
+        let mut config = Self::default();
+        for (key, value) in table {
+            let string = || value.as_str().ok_or(format!("{} must be a string", key));
+            match key.as_str() {
+                "color" => {
+                    config.color = Some(
+                        ColorChoice::parse(string()?)
+                            .ok_or("color must be auto, always or never")?,
+                    )
+                }
+                "fill" => config.fill = Some(parse_fill(string()?)?),
+                "wrap" => {
+                    let wrap = value.as_integer().filter(|&n| n > 0);
+                    config.wrap = Some(wrap.ok_or("wrap must be a positive number")? as usize);
+                }
+                "themes" => {
+                    let themes = value.as_table().ok_or("themes must be a table")?;
+                    for (name, value) in themes {
+                        let style = value
+                            .as_str()
+                            .and_then(style::parse)
+                            .ok_or(format!("theme {} must be a style like bold.red", name))?;
+                        config.themes.insert(name.clone(), style);
+                    }
+                }
+                "aliases" => {
+                    let aliases = value.as_table().ok_or("aliases must be a table")?;
+                    for (name, value) in aliases {
+                        let fmt_str = value
+                            .as_str()
+                            .ok_or(format!("alias {} must be a string", name))?;
+                        config.aliases.insert(name.clone(), fmt_str.to_string());
+                    }
+                }
+                _ => return Err(format!("unknown key {}", key)),
+            }
+        }
+        Ok(config)
+    }
+
+    #[cfg(not(feature = "config"))]
+    pub fn parse(_: &str) -> Result<Self, String> {
+        Err("fmt was built without the config feature, remove the file or rebuild".to_string())
+    }
+
+    /// The format string `fmt_str` stands for: the alias it names if it is like `@name`, or
+    /// itself. A leading `@@` stands for a single `@`, for format strings that start with one.
+    pub fn resolve<'a>(&'a self, fmt_str: &'a str) -> crate::Result<Cow<'a, str>> {
+        if let Some(rest) = fmt_str.strip_prefix("@@") {
+            return Ok(Cow::Owned(format!("@{}", rest)));
+        }
+        match fmt_str.strip_prefix('@') {
+            Some(name) if !name.is_empty() && !name.contains(char::is_whitespace) => {
+                match self.aliases.get(name) {
+                    Some(fmt_str) => Ok(Cow::Borrowed(fmt_str)),
+                    None => Err(Error::Other(format!(
+                        "There is no alias named {} in the config file",
+                        name
+                    ))),
+                }
+            }
+            _ => Ok(Cow::Borrowed(fmt_str)),
+        }
+    }
+
+    /// The config with the values of `TERM_PRINTLN_FILL` and `TERM_PRINTLN_WRAP` from `var` in
+    /// place of its own.
+    fn with_env<F: Fn(&str) -> Option<String>>(mut self, var: F) -> crate::Result<Self> {
+        let var = |key: &str| var(key).filter(|v| !v.is_empty());
+        if let Some(fill) = var("TERM_PRINTLN_FILL") {
+            self.fill = Some(parse_fill(&fill).map_err(|err| Error::Other(err.to_string()))?);
+        }
+        if let Some(wrap) = var("TERM_PRINTLN_WRAP") {
+            let parsed = wrap.trim().parse::<usize>().ok().filter(|&n| n > 0);
+            self.wrap = Some(parsed.ok_or_else(|| {
+                Error::Other(format!(
+                    "TERM_PRINTLN_WRAP must be a positive number: {}",
+                    wrap
+                ))
+            })?);
+        }
+        Ok(self)
+    }
+}
+
+fn parse_fill(s: &str) -> Result<char, &'static str> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err("fill must be a single character"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    #[cfg(feature = "config")]
+    fn files() {
+        let config = Config::parse(
+            "color = \"never\"\nfill = \"·\"\nwrap = 72\n\n[themes]\nwarn = \"bold.yellow\"\n",
+        )
+        .unwrap();
+        assert_eq!(config.color, Some(ColorChoice::Never));
+        assert_eq!(config.fill, Some('·'));
+        assert_eq!(config.wrap, Some(72));
+        let warn = config.themes["warn"];
+        assert!(warn.bold);
+        assert_eq!(warn.fg, Some(Color::Basic(3)));
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+
+        assert!(Config::parse("color = \"sometimes\"").is_err());
+        assert!(Config::parse("fill = \"ab\"").is_err());
+        assert!(Config::parse("wrap = 0").is_err());
+        assert!(Config::parse("wrap = \"80\"").is_err());
+        assert!(Config::parse("[themes]\nwarn = \"blinking\"").is_err());
+        assert!(Config::parse("colour = \"never\"").is_err());
+        assert!(Config::parse("color = ").is_err());
+        assert!(Config::parse("[aliases]\nx = 1").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "config")]
+    fn aliases() {
+        let config = Config::parse("[aliases]\ngreeting = \"Hello {name}\"").unwrap();
+        assert_eq!(config.resolve("@greeting").unwrap(), "Hello {name}");
+        assert_eq!(config.resolve("{} @greeting").unwrap(), "{} @greeting");
+        assert_eq!(config.resolve("@ {}").unwrap(), "@ {}");
+        assert_eq!(config.resolve("@").unwrap(), "@");
+        assert_eq!(config.resolve("@@greeting").unwrap(), "@greeting");
+        assert!(config.resolve("@farewell").is_err());
+    }
+
+    #[test]
+    fn environment() {
+        let env = |key: &str| match key {
+            "TERM_PRINTLN_FILL" => Some("-".to_string()),
+            "TERM_PRINTLN_WRAP" => Some("60".to_string()),
+            _ => None,
+        };
+        let config = Config {
+            fill: Some('.'),
+            wrap: Some(80),
+            ..Config::default()
+        };
+        let config = config.with_env(env).unwrap();
+        assert_eq!(config.fill, Some('-'));
+        assert_eq!(config.wrap, Some(60));
+        let config = Config::default().with_env(|_| None).unwrap();
+        assert_eq!(config, Config::default());
+        assert!(Config::default()
+            .with_env(|_| Some("wide".to_string()))
+            .is_err());
+    }
+}
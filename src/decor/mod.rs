@@ -0,0 +1,100 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Decorations drawn around the finished output, like the frame of `--box`.
+
+use crate::ansi;
+
+/// The lines `--box` draws its frame with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BoxStyle {
+    #[default]
+    Single,
+    Double,
+    Rounded,
+    Ascii,
+}
+
+impl BoxStyle {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "single" => Some(Self::Single),
+            "double" => Some(Self::Double),
+            "rounded" => Some(Self::Rounded),
+            "ascii" => Some(Self::Ascii),
+            _ => None,
+        }
+    }
+
+    /// The corners (top left, top right, bottom left, bottom right), then the horizontal and
+    /// vertical lines.
+    pub fn parts(self) -> [char; 6] {
+        match self {
+            Self::Single => ['┌', '┐', '└', '┘', '─', '│'],
+            Self::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+            Self::Rounded => ['╭', '╮', '╰', '╯', '─', '│'],
+            Self::Ascii => ['+', '+', '+', '+', '-', '|'],
+        }
+    }
+
+    /// Where lines meet inside a frame, like between the cells of a table: the top and bottom
+    /// edges, a crossing, and the left and right edges.
+    pub fn joints(self) -> [char; 5] {
+        match self {
+            Self::Single | Self::Rounded => ['┬', '┴', '┼', '├', '┤'],
+            Self::Double => ['╦', '╩', '╬', '╠', '╣'],
+            Self::Ascii => ['+', '+', '+', '+', '+'],
+        }
+    }
+}
+
+/// Draws a frame around `text`, with a space of padding on either side of every line. The frame
+/// is as wide as the widest line, measured in cells, so styling and wide characters don't throw
+/// it off.
+pub fn frame(text: &str, style: BoxStyle) -> String {
+    let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = style.parts();
+    let widest = text.split('\n').map(ansi::width).max().unwrap_or(0);
+    let edge = horizontal.to_string().repeat(widest + 2);
+
+    let mut lines = vec![format!("{}{}{}", top_left, edge, top_right)];
+    for line in text.split('\n') {
+        let pad = " ".repeat(widest - ansi::width(line));
+        lines.push(format!("{} {}{} {}", vertical, line, pad, vertical));
+    }
+    lines.push(format!("{}{}{}", bottom_left, edge, bottom_right));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn frames() {
+        assert_eq!(
+            frame("hello\nworld!", BoxStyle::Single),
+            "┌────────┐\n│ hello  │\n│ world! │\n└────────┘"
+        );
+        assert_eq!(frame("", BoxStyle::Ascii), "+--+\n|  |\n+--+");
+        assert_eq!(
+            frame("\x1b[1mok\x1b[0m", BoxStyle::Rounded),
+            "╭────╮\n│ \x1b[1mok\x1b[0m │\n╰────╯"
+        );
+        #[cfg(feature = "unicode")]
+        assert_eq!(
+            frame("读\nab", BoxStyle::Double),
+            "╔════╗\n║ 读 ║\n║ ab ║\n╚════╝"
+        );
+    }
+
+    #[test]
+    fn styles() {
+        assert_eq!(BoxStyle::parse("double"), Some(BoxStyle::Double));
+        assert_eq!(BoxStyle::parse("ascii"), Some(BoxStyle::Ascii));
+        assert_eq!(BoxStyle::parse("heavy"), None);
+    }
+}
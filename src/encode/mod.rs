@@ -0,0 +1,372 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `bytes` as standard, padded base64 (RFC 4648).
+pub fn base64(bytes: &[u8]) -> String {
+    let mut output = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (n >> (18 - 6 * i)) & 0x3f;
+                output.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+/// Quotes `s` for a POSIX shell, so it is taken literally as a single word.
+pub fn sh_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Escapes the characters that are special in HTML text and attribute values.
+pub fn html_escape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+/// Quotes `s` as a CSV field (RFC 4180) when it contains anything that would otherwise break up
+/// the row, doubling any quotes inside of it.
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+/// Escapes `s` as a TSV field, which has no quoting, so tabs, newlines and backslashes are written
+/// as `\t`, `\n`, `\r` and `\\` instead.
+pub fn tsv_field(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => output.push_str("\\\\"),
+            '\t' => output.push_str("\\t"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+/// Decodes standard or url safe base64, with or without padding. Whitespace is ignored, so
+/// wrapped output of the `base64` tool decodes too. Returns `None` if `s` isn't base64.
+pub fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let digits = s
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+    let unpadded = match digits.iter().position(|&b| b == b'=') {
+        Some(pad) if digits[pad..].iter().all(|&b| b == b'=') && digits.len() % 4 == 0 => {
+            &digits[..pad]
+        }
+        Some(_) => return None,
+        None => &digits[..],
+    };
+    if unpadded.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut output = Vec::with_capacity(unpadded.len() * 3 / 4);
+    for chunk in unpadded.chunks(4) {
+        let mut n = 0u32;
+        for (i, &b) in chunk.iter().enumerate() {
+            let value = match b {
+                b'A'..=b'Z' => b - b'A',
+                b'a'..=b'z' => b - b'a' + 26,
+                b'0'..=b'9' => b - b'0' + 52,
+                b'+' | b'-' => 62,
+                b'/' | b'_' => 63,
+                _ => return None,
+            };
+            n |= u32::from(value) << (18 - 6 * i);
+        }
+        output.extend(n.to_be_bytes()[1..chunk.len()].iter());
+    }
+    Some(output)
+}
+
+/// Percent encodes every byte of `s` other than the unreserved characters of RFC 3986 (letters,
+/// digits, `-`, `.`, `_` and `~`), so it can be used anywhere in a URL.
+pub fn url_encode(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                output.push(b as char)
+            }
+            _ => output.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    output
+}
+
+/// Decodes the percent encoded bytes of `s`, keeping everything else (`+` included) as it is.
+/// Returns `None` if a `%` isn't followed by two hexadecimal digits or the result isn't UTF-8.
+pub fn url_decode(s: &str) -> Option<String> {
+    let mut output = Vec::with_capacity(s.len());
+    let mut bytes = s.bytes();
+    while let Some(b) = bytes.next() {
+        if b != b'%' {
+            output.push(b);
+            continue;
+        }
+        let hex = [bytes.next()?, bytes.next()?];
+        if !hex.iter().all(u8::is_ascii_hexdigit) {
+            return None;
+        }
+        output.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+    }
+    String::from_utf8(output).ok()
+}
+
+/// Escapes quotes, backslashes and control characters in `s`, so it can be put between the quotes
+/// of a JSON string.
+pub fn json_escape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\u{8}' => output.push_str("\\b"),
+            '\u{c}' => output.push_str("\\f"),
+            c if c <= '\u{1f}' => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+/// Replaces every `\u{HEX}` in `s` with the character of that code point, like Rust's own escape.
+/// Anything that isn't a valid escape (no brackets, too many digits, a surrogate, ..) is kept as
+/// it is.
+pub fn unicode_unescape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("\\u{") {
+        output.push_str(&rest[..start]);
+        let escape = &rest[start..];
+        let decoded = escape[3..].split_once('}').and_then(|(hex, _)| {
+            let valid = (1..=6).contains(&hex.len()) && hex.chars().all(|c| c.is_ascii_hexdigit());
+            valid
+                .then(|| u32::from_str_radix(hex, 16).ok().and_then(char::from_u32))
+                .flatten()
+                .map(|c| (c, hex.len() + 4))
+        });
+        match decoded {
+            Some((c, len)) => {
+                output.push(c);
+                rest = &escape[len..];
+            }
+            None => {
+                output.push_str(&escape[..3]);
+                rest = &escape[3..];
+            }
+        }
+    }
+    output.push_str(rest);
+    output
+}
+
+/// Interprets the backslash escapes of `echo -e` in `s`: `\\`, `\a`, `\b`, `\e`, `\f`, `\n`,
+/// `\r`, `\t`, `\v`, `\0NNN` (up to three octal digits) and `\xHH` (one or two hexadecimal
+/// digits). Unknown escapes are kept as they are.
+pub fn echo_unescape(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        let unescaped = match chars.peek().copied() {
+            Some('\\') => '\\',
+            Some('a') => '\x07',
+            Some('b') => '\x08',
+            Some('e') => '\x1b',
+            Some('f') => '\x0c',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('v') => '\x0b',
+            Some('0') => {
+                chars.next();
+                output.push(char::from(digits(&mut chars, 8, 3) as u8));
+                continue;
+            }
+            Some('x') if chars.clone().nth(1).is_some_and(|c| c.is_ascii_hexdigit()) => {
+                chars.next();
+                output.push(char::from(digits(&mut chars, 16, 2) as u8));
+                continue;
+            }
+            _ => {
+                output.push('\\');
+                continue;
+            }
+        };
+        chars.next();
+        output.push(unescaped);
+    }
+    output
+}
+
+/// Takes up to `max` digits in `radix` from `chars`, returning their value.
+fn digits(chars: &mut std::iter::Peekable<std::str::Chars>, radix: u32, max: usize) -> u32 {
+    let mut n = 0;
+    for _ in 0..max {
+        match chars.peek().and_then(|c| c.to_digit(radix)) {
+            Some(d) => n = n * radix + d,
+            None => break,
+        }
+        chars.next();
+    }
+    n
+}
+
+/// Returns true if `s` can be used as the name of a shell variable.
+pub fn is_sh_name(s: &str) -> bool {
+    let mut chars = s.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn base64_vectors() {
+        // Test vectors from RFC 4648, section 10
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(b"fooba"), "Zm9vYmE=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(base64("读".as_bytes()), "6K+7");
+    }
+
+    #[test]
+    fn base64_decoding() {
+        for encoded in [
+            "", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy", "6K+7",
+        ] {
+            assert_eq!(
+                base64_decode(encoded).map(|b| base64(&b)),
+                Some(encoded.to_string())
+            );
+        }
+        assert_eq!(base64_decode("Zm8"), Some(b"fo".to_vec()));
+        assert_eq!(base64_decode("Zm9v\nYmFy\n"), Some(b"foobar".to_vec()));
+        assert_eq!(base64_decode("-_8="), base64_decode("+/8="));
+        assert_eq!(base64_decode("Zm9=v"), None);
+        assert_eq!(base64_decode("Zm8=="), None);
+        assert_eq!(base64_decode("Z"), None);
+        assert_eq!(base64_decode("Zm9*"), None);
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(url_encode("hello world"), "hello%20world");
+        assert_eq!(url_encode("a+b=c&d/é~_.-"), "a%2Bb%3Dc%26d%2F%C3%A9~_.-");
+        assert_eq!(url_decode("hello%20world"), Some("hello world".to_string()));
+        assert_eq!(url_decode("%c3%a9+x"), Some("é+x".to_string()));
+        assert_eq!(url_decode("100%"), None);
+        assert_eq!(url_decode("%zz"), None);
+        assert_eq!(url_decode("%+f"), None);
+        assert_eq!(url_decode("%ff"), None);
+    }
+
+    #[test]
+    fn json_strings() {
+        assert_eq!(json_escape(r#"say "hi" \ bye"#), r#"say \"hi\" \\ bye"#);
+        assert_eq!(json_escape("a\nb\tc\r\u{8}\u{c}"), r"a\nb\tc\r\b\f");
+        assert_eq!(json_escape("\u{1}\u{1f}\u{7f}é"), "\\u0001\\u001f\u{7f}é");
+    }
+
+    #[test]
+    fn unicode_escapes() {
+        assert_eq!(unicode_unescape(r"\u{1F600} hi"), "\u{1F600} hi");
+        assert_eq!(unicode_unescape(r"{}\u{2500}\u{2500}{0}"), "{}──{0}");
+        assert_eq!(unicode_unescape(r"\u{e9}\u{00E9}"), "éé");
+        assert_eq!(unicode_unescape(r"\u{}"), r"\u{}");
+        assert_eq!(unicode_unescape(r"\u{D800}"), r"\u{D800}");
+        assert_eq!(unicode_unescape(r"\u{1234567}"), r"\u{1234567}");
+        assert_eq!(unicode_unescape(r"\u{zz}\u{41"), r"\u{zz}\u{41");
+        assert_eq!(unicode_unescape(r"\u0041 \\u{41}"), r"\u0041 \A");
+    }
+
+    #[test]
+    fn echo_escapes() {
+        assert_eq!(echo_unescape(r"a\tb\nc\r"), "a\tb\nc\r");
+        assert_eq!(
+            echo_unescape(r"\\n \e[1m \a\b\f\v"),
+            "\\n \x1b[1m \x07\x08\x0c\x0b"
+        );
+        assert_eq!(echo_unescape(r"\0101\0\01010"), "A\0A0");
+        assert_eq!(echo_unescape(r"\x41\x4a2\xz\x"), "AJ2\\xz\\x");
+        assert_eq!(echo_unescape(r"\q \u{41} \"), r"\q \u{41} \");
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(tsv_field("a\tb\\c\nd"), "a\\tb\\\\c\\nd");
+        assert_eq!(tsv_field("a,b"), "a,b");
+    }
+
+    #[test]
+    fn html() {
+        assert_eq!(
+            html_escape("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn shell_quoting() {
+        assert_eq!(sh_quote("plain"), "'plain'");
+        assert_eq!(sh_quote(""), "''");
+        assert_eq!(sh_quote("it's $HOME"), "'it'\\''s $HOME'");
+        assert!(is_sh_name("PATH_2"));
+        assert!(is_sh_name("_x"));
+        assert!(!is_sh_name("2x"));
+        assert!(!is_sh_name("a-b"));
+        assert!(!is_sh_name(""));
+    }
+}
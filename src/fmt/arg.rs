@@ -78,10 +78,8 @@ impl FormatArgs {
             return true;
         }
 
-        if self.0.iter().any(|fa| !fa.has_value()) {
-            eprintln!("FormatArgs contains empty arg(s)");
-            return false;
-        }
+        // Empty args are fine, `name=` is how a script passes a variable that isn't set and
+        // conditionals like `{?name:..}` check for exactly that
 
         // Check that all positions exist
         let mut positions = self.0.iter().map(|fa| fa.pos).collect::<Vec<_>>();
@@ -0,0 +1,577 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Placeholders the tool fills in by itself when no arg of the same name was given.
+//!
+//! `{#}` is the number of args given, which only the formatter knows, so it is filled in there.
+//! `{now}` is the current local time (in the time zone `TZ` names, if it is set), as RFC 3339 or
+//! with a strftime(3) format like `{now:%Y-%m-%d %H:%M}`, or the time in UTC after [`utc`]. It is
+//! read once per run (or per render of `--every`), so every line rendered at once shows the same
+//! time. `{rand}` (a 32 bit number, or one in a range like `{rand:1..=6}`) and `{uuid}` (a version
+//! 4 UUID) change on every use. `{hostname}`, `{user}`, `{cwd}` and `{pid}` describe the machine
+//! and process the tool runs in. All of them can be pinned with [`pin_now`], [`seed`] and
+//! [`deterministic`] (which is what `--deterministic` and `--seed` do) so output using them can be
+//! snapshot tested.
+//!
+//! `{remaining}` is the number of seconds left of `--countdown`, and has no value without it.
+//!
+//! Args always come first, so `fmt "{user}" user=root` shows `root` whoever runs it.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use once_cell::sync::OnceCell;
+
+/// The names of every builtin placeholder
+pub const BUILTINS: [&str; 9] = [
+    ARG_COUNT,
+    "cwd",
+    "hostname",
+    "now",
+    "pid",
+    "rand",
+    "remaining",
+    "user",
+    "uuid",
+];
+
+/// The name of `{#}`, the number of args given
+pub const ARG_COUNT: &str = "#";
+
+/// Increment of the splitmix64 generator
+const GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// The time `{now}` is pinned to
+static NOW: OnceCell<SystemTime> = OnceCell::new();
+/// The time `{now}` shows until the next [`tick`]
+static CLOCK: Mutex<Option<SystemTime>> = Mutex::new(None);
+static SEED: OnceCell<u64> = OnceCell::new();
+static DRAWN: AtomicU64 = AtomicU64::new(0);
+static PINNED: AtomicBool = AtomicBool::new(false);
+static UTC: AtomicBool = AtomicBool::new(false);
+/// The seconds left of `--countdown`, [`u64::MAX`] when there is none
+static REMAINING: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// Makes `{now}` show `time` instead of the current time. Only the first call has any effect.
+pub fn pin_now(time: SystemTime) {
+    let _ = NOW.set(time);
+}
+
+/// Makes `{now}` read the clock again the next time it is used, for output that is rendered over
+/// and over like that of `--every`. A pinned time stays pinned.
+pub fn tick() {
+    *CLOCK.lock().expect("The clock lock is poisoned") = None;
+}
+
+/// The time `{now}` shows, read from the clock the first time it is needed after every [`tick`].
+fn now() -> SystemTime {
+    if let Some(&pinned) = NOW.get() {
+        return pinned;
+    }
+    *CLOCK
+        .lock()
+        .expect("The clock lock is poisoned")
+        .get_or_insert_with(SystemTime::now)
+}
+
+/// Makes `{now}` show the time in UTC instead of the local time, for `--utc`.
+pub fn utc() {
+    UTC.store(true, Ordering::Relaxed);
+}
+
+/// A UTC offset and the abbreviation of the time zone it is in, like `+0200` and `CEST`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Zone {
+    /// Seconds east of UTC
+    pub offset: i64,
+    pub name: String,
+}
+
+impl Zone {
+    pub fn utc() -> Self {
+        Self {
+            offset: 0,
+            name: "UTC".to_string(),
+        }
+    }
+
+    /// The zone `{now}` shows `time` in: the local one, unless [`utc`] was called or the local
+    /// zone can't be found out on this platform.
+    pub fn at(time: SystemTime) -> Self {
+        if UTC.load(Ordering::Relaxed) {
+            return Self::utc();
+        }
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_secs() as i64,
+            Err(before) => -(before.duration().as_secs() as i64),
+        };
+        local::zone(secs).unwrap_or_else(Self::utc)
+    }
+
+    /// The offset as `+HHMM`, or `+HH:MM` with a `separator` of `:`.
+    fn offset(&self, separator: &str) -> String {
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let minutes = self.offset.abs() / 60;
+        format!(
+            "{}{:02}{}{:02}",
+            sign,
+            minutes / 60,
+            separator,
+            minutes % 60
+        )
+    }
+}
+
+/// Seeds `{rand}` and `{uuid}`, so the same seed produces the same sequence of values. Only the
+/// first call has any effect.
+pub fn seed(seed: u64) {
+    let _ = SEED.set(seed);
+}
+
+/// Makes `{remaining}` show `secs`, the seconds left of `--countdown`.
+pub fn set_remaining(secs: u64) {
+    REMAINING.store(secs, Ordering::Relaxed);
+}
+
+/// Pins every builtin: `{now}` to the unix epoch in UTC, `{rand}` and `{uuid}` to seed `0`, and
+/// the system info to `localhost`, `user`, `/` and pid `0`.
+pub fn deterministic() {
+    pin_now(UNIX_EPOCH);
+    utc();
+    seed(0);
+    PINNED.store(true, Ordering::Relaxed);
+}
+
+/// Whether `option` (what follows the colon of a spec) is meant for the builtin `name` rather
+/// than being a regular format spec, like the time format of `{now:%H:%M}` or the range of
+/// `{rand:1..100}`.
+pub fn takes_option(name: &str, option: &str) -> bool {
+    match name {
+        "now" => option.contains('%'),
+        "rand" => range(option).is_some(),
+        _ => false,
+    }
+}
+
+/// Parses a range like `1..100` (which excludes `100`) or `1..=100` (which includes it) into its
+/// inclusive bounds, or `None` if it isn't one or is empty.
+fn range(option: &str) -> Option<(i64, i64)> {
+    let (start, end) = option.split_once("..")?;
+    let start = start.trim().parse::<i64>().ok()?;
+    let end = match end.strip_prefix('=') {
+        Some(end) => end.trim().parse::<i64>().ok()?,
+        None => end.trim().parse::<i64>().ok()?.checked_sub(1)?,
+    };
+    (start <= end).then_some((start, end))
+}
+
+/// Returns the value of the builtin placeholder `name`, or `None` if there is no such builtin.
+/// `option` is the option of the spec, see [`takes_option`].
+pub fn value(name: &str, option: Option<&str>) -> Option<String> {
+    match (name, option) {
+        ("now", option) => {
+            let time = now();
+            let zone = Zone::at(time);
+            Some(match option {
+                Some(format) => strftime(time, &zone, format),
+                None => rfc3339(time, &zone),
+            })
+        }
+        ("rand", Some(option)) => {
+            let (start, end) = range(option)?;
+            let span = (i128::from(end) - i128::from(start) + 1) as u128;
+            let offset = (u128::from(next_u64()) * span) >> 64;
+            Some((i128::from(start) + offset as i128).to_string())
+        }
+        ("rand", None) => Some((next_u64() >> 32).to_string()),
+        ("uuid", _) => Some(uuid_v4(next_u64(), next_u64())),
+        ("hostname" | "user" | "cwd" | "pid", _) if PINNED.load(Ordering::Relaxed) => Some(
+            match name {
+                "hostname" => "localhost",
+                "user" => "user",
+                "cwd" => "/",
+                _ => "0",
+            }
+            .to_string(),
+        ),
+        ("hostname", _) => Some(hostname()),
+        ("user", _) => Some(user()),
+        ("cwd", _) => std::env::current_dir()
+            .ok()
+            .map(|dir| dir.display().to_string()),
+        ("pid", _) => Some(std::process::id().to_string()),
+        ("remaining", _) => match REMAINING.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            secs => Some(secs.to_string()),
+        },
+        _ => None,
+    }
+}
+
+/// The name of this machine, from `$HOSTNAME` or the files the kernel and the system keep it in,
+/// falling back to `localhost`.
+fn hostname() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .or_else(|| std::fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| std::fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// The name of the user running the tool, from the environment, falling back to `unknown`.
+fn user() -> String {
+    ["USER", "LOGNAME", "USERNAME"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|name| !name.is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn next_u64() -> u64 {
+    let seed = *SEED.get_or_init(|| RandomState::new().build_hasher().finish());
+    let n = DRAWN.fetch_add(1, Ordering::Relaxed);
+    splitmix64(seed.wrapping_add(n.wrapping_add(1).wrapping_mul(GAMMA)))
+}
+
+/// The output function of splitmix64, turning a counter into well mixed bits.
+fn splitmix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Formats 128 random bits as a version 4 UUID.
+fn uuid_v4(hi: u64, lo: u64) -> String {
+    let hi = (hi & !0xf000) | 0x4000;
+    let lo = (lo & !(0b11 << 62)) | (0b10 << 62);
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        hi >> 32,
+        (hi >> 16) & 0xffff,
+        hi & 0xffff,
+        lo >> 48,
+        lo & 0xffff_ffff_ffff
+    )
+}
+
+/// Formats `time` in `zone` as `YYYY-MM-DDTHH:MM:SS`, followed by `Z` in UTC or the offset like
+/// `+02:00` anywhere else. Times before the epoch show the epoch.
+fn rfc3339(time: SystemTime, zone: &Zone) -> String {
+    let offset = match zone.offset {
+        0 => "Z".to_string(),
+        _ => zone.offset(":"),
+    };
+    strftime(time, zone, "%Y-%m-%dT%H:%M:%S") + &offset
+}
+
+const DAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Formats `time` in `zone` with the conversions of strftime(3). Conversions it doesn't know are
+/// kept as they are. Times before the epoch show the epoch.
+pub fn strftime(time: SystemTime, zone: &Zone, format: &str) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or(Duration::ZERO)
+        .as_secs();
+    let local = secs as i64 + zone.offset;
+    let days = local.div_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let rem = local.rem_euclid(86_400);
+    let (hour, minute, second) = (rem / 3600, rem % 3600 / 60, rem % 60);
+    // The epoch was a thursday
+    let weekday = (days + 4).rem_euclid(7);
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let before = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334][month as usize - 1];
+    let yday = before + day + u32::from(leap && month > 2);
+
+    let mut output = String::with_capacity(format.len() * 2);
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+        let part = match chars.next() {
+            Some('Y') => year.to_string(),
+            Some('C') => format!("{:02}", year / 100),
+            Some('y') => format!("{:02}", year % 100),
+            Some('m') => format!("{:02}", month),
+            Some('d') => format!("{:02}", day),
+            Some('e') => format!("{:>2}", day),
+            Some('j') => format!("{:03}", yday),
+            Some('H') => format!("{:02}", hour),
+            Some('I') => format!("{:02}", (hour + 11) % 12 + 1),
+            Some('M') => format!("{:02}", minute),
+            Some('S') => format!("{:02}", second),
+            Some('p') => if hour < 12 { "AM" } else { "PM" }.to_string(),
+            Some('a') => DAYS[weekday as usize][..3].to_string(),
+            Some('A') => DAYS[weekday as usize].to_string(),
+            Some('b' | 'h') => MONTHS[month as usize - 1][..3].to_string(),
+            Some('B') => MONTHS[month as usize - 1].to_string(),
+            Some('u') => (if weekday == 0 { 7 } else { weekday }).to_string(),
+            Some('w') => weekday.to_string(),
+            Some('s') => secs.to_string(),
+            Some('z') => zone.offset(""),
+            Some('Z') => zone.name.clone(),
+            Some('F') => strftime(time, zone, "%Y-%m-%d"),
+            Some('T') => strftime(time, zone, "%H:%M:%S"),
+            Some('D') => strftime(time, zone, "%m/%d/%y"),
+            Some('R') => strftime(time, zone, "%H:%M"),
+            Some('c') => strftime(time, zone, "%a %b %e %H:%M:%S %Y"),
+            Some('n') => "\n".to_string(),
+            Some('t') => "\t".to_string(),
+            Some('%') => "%".to_string(),
+            Some(other) => format!("%{}", other),
+            None => "%".to_string(),
+        };
+        output.push_str(&part);
+    }
+    output
+}
+
+/// Converts days since the unix epoch into a proleptic gregorian `(year, month, day)`, using
+/// Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Just enough of the C library to find out the local time zone, which isn't worth a dependency.
+/// Only where `struct tm` is known to carry the offset and the name of the zone.
+#[cfg(all(
+    target_pointer_width = "64",
+    any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )
+))]
+mod local {
+    use std::ffi::CStr;
+    use std::os::raw::{c_char, c_int};
+
+    use super::Zone;
+
+    #[repr(C)]
+    struct Tm {
+        tm_sec: c_int,
+        tm_min: c_int,
+        tm_hour: c_int,
+        tm_mday: c_int,
+        tm_mon: c_int,
+        tm_year: c_int,
+        tm_wday: c_int,
+        tm_yday: c_int,
+        tm_isdst: c_int,
+        // `long`, which is 64 bits wide like `time_t` on all of these platforms
+        tm_gmtoff: i64,
+        tm_zone: *const c_char,
+    }
+
+    extern "C" {
+        fn tzset();
+        fn localtime_r(time: *const i64, tm: *mut Tm) -> *mut Tm;
+    }
+
+    /// The local zone at `secs` since the unix epoch, following `TZ` like the C library does.
+    pub fn zone(secs: i64) -> Option<Zone> {
+        // SAFETY: `Tm` matches `struct tm` on the platforms this module is built for, every field
+        // of it is plain data, and localtime_r only writes to the one it is given
+        unsafe {
+            let mut tm = std::mem::zeroed::<Tm>();
+            tzset();
+            if localtime_r(&secs, &mut tm).is_null() {
+                return None;
+            }
+            let name = match tm.tm_zone.is_null() {
+                true => String::new(),
+                false => CStr::from_ptr(tm.tm_zone).to_string_lossy().into_owned(),
+            };
+            Some(Zone {
+                offset: tm.tm_gmtoff,
+                name,
+            })
+        }
+    }
+}
+
+/// Everywhere else, `{now}` is shown in UTC.
+#[cfg(not(all(
+    target_pointer_width = "64",
+    any(
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    )
+)))]
+mod local {
+    pub fn zone(_: i64) -> Option<super::Zone> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn timestamps() {
+        let utc = Zone::utc();
+        assert_eq!(rfc3339(UNIX_EPOCH, &utc), "1970-01-01T00:00:00Z");
+        let leap = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        assert_eq!(rfc3339(leap, &utc), "2000-02-29T12:34:56Z");
+        let end = UNIX_EPOCH + Duration::from_secs(1_704_067_199);
+        assert_eq!(rfc3339(end, &utc), "2023-12-31T23:59:59Z");
+        let tokyo = Zone {
+            offset: 9 * 3600,
+            name: "JST".to_string(),
+        };
+        assert_eq!(rfc3339(end, &tokyo), "2024-01-01T08:59:59+09:00");
+        let newfoundland = Zone {
+            offset: -(3 * 3600 + 1800),
+            name: "NST".to_string(),
+        };
+        assert_eq!(rfc3339(end, &newfoundland), "2023-12-31T20:29:59-03:30");
+    }
+
+    #[test]
+    fn time_formats() {
+        // Checked against `date -u -d @SECS +FORMAT`
+        let utc = Zone::utc();
+        let leap = UNIX_EPOCH + Duration::from_secs(951_827_696);
+        assert_eq!(
+            strftime(leap, &utc, "%Y-%m-%d %H:%M:%S"),
+            "2000-02-29 12:34:56"
+        );
+        assert_eq!(
+            strftime(leap, &utc, "%a %A %b %B %e %j %u %w"),
+            "Tue Tuesday Feb February 29 060 2 2"
+        );
+        assert_eq!(
+            strftime(leap, &utc, "%I:%M %p|%y|%C|%D|%s"),
+            "12:34 PM|00|20|02/29/00|951827696"
+        );
+        let late = UNIX_EPOCH + Duration::from_secs(1_704_067_199);
+        assert_eq!(
+            strftime(late, &utc, "%c %z %Z"),
+            "Sun Dec 31 23:59:59 2023 +0000 UTC"
+        );
+        assert_eq!(
+            strftime(late, &utc, "%F %T|%j|%I%p|100%%|%Q|%"),
+            "2023-12-31 23:59:59|365|11PM|100%|%Q|%"
+        );
+        assert_eq!(strftime(UNIX_EPOCH, &utc, "%c"), "Thu Jan  1 00:00:00 1970");
+        // TZ=America/St_Johns date -d @0 +FORMAT
+        let newfoundland = Zone {
+            offset: -(3 * 3600 + 1800),
+            name: "NST".to_string(),
+        };
+        assert_eq!(
+            strftime(UNIX_EPOCH, &newfoundland, "%c %z %Z|%j %u|%s"),
+            "Wed Dec 31 20:30:00 1969 -0330 NST|365 3|0"
+        );
+    }
+
+    #[test]
+    fn uuids() {
+        assert_eq!(uuid_v4(0, 0), "00000000-0000-4000-8000-000000000000");
+        assert_eq!(
+            uuid_v4(u64::MAX, u64::MAX),
+            "ffffffff-ffff-4fff-bfff-ffffffffffff"
+        );
+        assert_ne!(splitmix64(GAMMA), splitmix64(GAMMA.wrapping_mul(2)));
+        assert!(value("nope", None).is_none());
+        assert!(takes_option("now", "%H:%M"));
+        assert!(!takes_option("now", ">10"));
+    }
+
+    #[test]
+    fn ranges() {
+        assert_eq!(range("1..100"), Some((1, 99)));
+        assert_eq!(range("1..=100"), Some((1, 100)));
+        assert_eq!(range("-5 ..= -5"), Some((-5, -5)));
+        assert_eq!(range("3..3"), None);
+        assert_eq!(range("1..x"), None);
+        assert_eq!(range(">10"), None);
+        assert!(takes_option("rand", "1..=6"));
+        assert!(!takes_option("rand", ">10"));
+        for _ in 0..100 {
+            let n = value("rand", Some("1..=6"))
+                .unwrap()
+                .parse::<i64>()
+                .unwrap();
+            assert!((1..=6).contains(&n));
+        }
+        let full = value("rand", Some(&format!("{}..={}", i64::MIN, i64::MAX)));
+        assert!(full.unwrap().parse::<i64>().is_ok());
+    }
+
+    #[test]
+    fn system_info() {
+        assert_eq!(value("pid", None), Some(std::process::id().to_string()));
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(value("cwd", None), Some(cwd.display().to_string()));
+        assert!(!hostname().is_empty());
+        assert!(!user().is_empty());
+    }
+
+    #[test]
+    fn clock() {
+        let first = now();
+        assert_eq!(now(), first);
+        std::thread::sleep(Duration::from_millis(2));
+        tick();
+        assert!(now() > first);
+    }
+
+    #[test]
+    fn countdown() {
+        set_remaining(3);
+        assert_eq!(value("remaining", None), Some("3".to_string()));
+        set_remaining(0);
+        assert_eq!(value("remaining", None), Some("0".to_string()));
+    }
+}
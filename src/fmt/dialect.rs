@@ -0,0 +1,330 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Whose rules specs follow once they are parsed.
+//!
+//! By default every arg is text, and only gets treated as a number when a spec asks for something
+//! only numbers have. `--rust-fmt` instead gives args the type a rust literal written the same way
+//! would have, so the same spec renders them exactly like `format!` would. `--python` does the
+//! same for python's `str.format`, whose specs mostly look like rust's but add conversions
+//! (`{0!r}`), grouping (`{:,}`) and types of their own.
+//!
+//! All dialects share one spec parser, so [`Dialect::supports`] decides which parts of a spec
+//! mean anything to a given dialect.
+
+use once_cell::sync::OnceCell;
+
+use super::{number, Alignment, Error, FormatSpec, FormatType, Formatter};
+
+/// The rules a [`crate::Formatter`] renders specs with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Dialect {
+    /// Args are text unless a spec needs a number
+    #[default]
+    Default,
+    /// `--rust-fmt`, args are integers, floats or strings and render like they would in `format!`
+    Rust,
+    /// `--python`, args are integers, floats or strings and render like they would in python's
+    /// `str.format`
+    Python,
+}
+
+impl Dialect {
+    /// Whether every part of `spec` means something in this dialect.
+    pub fn supports(self, spec: &FormatSpec) -> bool {
+        let python_only = spec.conversion.is_some()
+            || spec.after_sign
+            || spec.space
+            || spec.grouping.is_some()
+            || matches!(
+                spec.ty,
+                FormatType::Decimal
+                    | FormatType::Fixed
+                    | FormatType::UpperFixed
+                    | FormatType::General
+                    | FormatType::UpperGeneral
+                    | FormatType::Char
+                    | FormatType::Str
+            );
+        match self {
+            Dialect::Python => spec.ty != FormatType::Debug && !spec.precision_next,
+            // Percentages are handy enough to have without python's other types
+            Dialect::Default => !python_only,
+            Dialect::Rust => !python_only && spec.ty != FormatType::Percent,
+        }
+    }
+}
+
+static DIALECT: OnceCell<Dialect> = OnceCell::new();
+
+/// Makes every [`crate::Formatter`] created afterwards use `dialect`. Only the first call has any
+/// effect.
+pub fn set(dialect: Dialect) {
+    let _ = DIALECT.set(dialect);
+}
+
+/// The dialect new formatters use, see [`set`].
+pub fn current() -> Dialect {
+    DIALECT.get().copied().unwrap_or_default()
+}
+
+/// The rust type an arg would have if it were written as a literal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Value {
+    Int(i128),
+    Float(f64),
+    Str,
+}
+
+impl Value {
+    fn of(arg: &str) -> Self {
+        if let Some(n) = number::parse_int(arg) {
+            Self::Int(n)
+        } else if let Some(n) = number::parse_float(arg) {
+            Self::Float(n)
+        } else {
+            Self::Str
+        }
+    }
+}
+
+/// Renders `arg` the way `format!` renders a value of its [`Value`] type with `spec`, before any
+/// padding. Also returns whether the arg is a number, which rust right aligns and zero pads.
+///
+/// Negative integers in other bases are shown as `-` and their magnitude instead of rust's two's
+/// complement, since a literal doesn't say how many bits it has.
+pub fn rust_value(
+    arg: &str,
+    spec: &FormatSpec,
+    precision: Option<usize>,
+) -> crate::Result<(String, bool)> {
+    let value = Value::of(arg);
+    let shown = match (spec.ty, value, precision) {
+        // Integers ignore the precision, like they do in rust
+        (FormatType::Display | FormatType::Debug, Value::Int(n), _) => n.to_string(),
+        (FormatType::Display, Value::Float(n), Some(p)) => number::fixed(n, p),
+        (FormatType::Display, Value::Float(n), None) => n.to_string(),
+        (FormatType::Display, Value::Str, Some(p)) => crate::ansi::truncate(arg, p, ""),
+        (FormatType::Display, Value::Str, None) => arg.to_string(),
+        (FormatType::Debug, Value::Float(n), Some(p)) => format!("{:.*?}", p, n),
+        (FormatType::Debug, Value::Float(n), None) => format!("{:?}", n),
+        (FormatType::Debug, Value::Str, _) => format!("{:?}", arg),
+        (ty @ (FormatType::LowerExp | FormatType::UpperExp), Value::Int(n), p) => {
+            match (ty == FormatType::UpperExp, p) {
+                (false, Some(p)) => format!("{:.*e}", p, n),
+                (false, None) => format!("{:e}", n),
+                (true, Some(p)) => format!("{:.*E}", p, n),
+                (true, None) => format!("{:E}", n),
+            }
+        }
+        (ty @ (FormatType::LowerExp | FormatType::UpperExp), Value::Float(n), p) => {
+            number::exponent(n, ty == FormatType::UpperExp, p)
+        }
+        (FormatType::LowerExp | FormatType::UpperExp, Value::Str, _) => {
+            return Err(Error::bad_arg_value(arg, &spec.text, "a number"))
+        }
+        (ty, Value::Int(_), _) => number::integer(arg, ty, spec.alternate, spec.plus)
+            .ok_or_else(|| Error::bad_arg_value(arg, &spec.text, "an integer"))?,
+        (_, _, _) => return Err(Error::bad_arg_value(arg, &spec.text, "an integer")),
+    };
+    Ok((shown, value != Value::Str))
+}
+
+/// Renders `arg` the way python's `str.format` renders a value of its [`Value`] type with `spec`,
+/// before any padding. Also returns whether the arg is a number, which python right aligns.
+pub fn python_value(
+    arg: &str,
+    spec: &FormatSpec,
+    precision: Option<usize>,
+) -> crate::Result<(String, bool)> {
+    // Conversions turn the arg into a string before it is formatted
+    let converted;
+    let (arg, value) = match spec.conversion {
+        Some(c @ ('r' | 'a')) => {
+            converted = match Value::of(arg) {
+                Value::Int(n) => n.to_string(),
+                Value::Float(n) => number::python_repr(n),
+                Value::Str => python_repr(arg, c == 'a'),
+            };
+            (converted.as_str(), Value::Str)
+        }
+        Some(_) => (arg, Value::Str),
+        None => (arg, Value::of(arg)),
+    };
+    let wrong = |expected: &str| Error::bad_arg_value(arg, &spec.text, expected);
+
+    let x = match value {
+        Value::Str => {
+            let numeric_only = spec.plus || spec.space || spec.alternate || spec.after_sign;
+            let string_type = matches!(spec.ty, FormatType::Display | FormatType::Str);
+            if numeric_only || spec.grouping.is_some() || !string_type {
+                return Err(wrong("a number"));
+            }
+            return match precision {
+                Some(p) => Ok((crate::ansi::truncate(arg, p, ""), false)),
+                None => Ok((arg.to_string(), false)),
+            };
+        }
+        Value::Int(n) => n as f64,
+        Value::Float(n) => n,
+    };
+
+    let upper = matches!(
+        spec.ty,
+        FormatType::UpperExp | FormatType::UpperGeneral | FormatType::UpperHex
+    );
+    let (prefix, body, radix) = match (spec.ty, value) {
+        (FormatType::Display | FormatType::Decimal, Value::Int(_)) if precision.is_some() => {
+            return Err(wrong("a float"))
+        }
+        (FormatType::Display | FormatType::Decimal, Value::Int(n)) => {
+            ("", n.unsigned_abs().to_string(), 10)
+        }
+        (ty @ (FormatType::LowerHex | FormatType::UpperHex), Value::Int(n)) => {
+            let digits = number::integer(&n.unsigned_abs().to_string(), ty, false, false);
+            (
+                if upper { "0X" } else { "0x" },
+                digits.unwrap_or_default(),
+                16,
+            )
+        }
+        (ty @ (FormatType::Octal | FormatType::Binary), Value::Int(n)) => {
+            let digits = number::integer(&n.unsigned_abs().to_string(), ty, false, false);
+            match ty {
+                FormatType::Octal => ("0o", digits.unwrap_or_default(), 8),
+                _ => ("0b", digits.unwrap_or_default(), 2),
+            }
+        }
+        (FormatType::Char, Value::Int(n)) => match u32::try_from(n).ok().and_then(char::from_u32) {
+            Some(c) => return Ok((c.to_string(), true)),
+            None => return Err(wrong("a code point")),
+        },
+        (FormatType::Display, Value::Float(_)) => match precision {
+            Some(p) => (
+                "",
+                number::python_general(x.abs(), false, p, spec.alternate, true),
+                10,
+            ),
+            None => ("", number::python_repr(x.abs()), 10),
+        },
+        (FormatType::Fixed | FormatType::UpperFixed, _) => {
+            ("", number::fixed(x.abs(), precision.unwrap_or(6)), 10)
+        }
+        (FormatType::LowerExp | FormatType::UpperExp, _) => (
+            "",
+            number::python_exponent(x.abs(), upper, precision.unwrap_or(6)),
+            10,
+        ),
+        (FormatType::General | FormatType::UpperGeneral, _) => (
+            "",
+            number::python_general(
+                x.abs(),
+                upper,
+                precision.unwrap_or(6),
+                spec.alternate,
+                false,
+            ),
+            10,
+        ),
+        (FormatType::Percent, _) => (
+            "",
+            format!(
+                "{}%",
+                number::fixed(x.abs() * 100.0, precision.unwrap_or(6))
+            ),
+            10,
+        ),
+        (FormatType::Str, _) => return Err(wrong("a string")),
+        (_, _) => return Err(wrong("an integer")),
+    };
+
+    // Decimal numbers are grouped by thousands, other bases by four digits and only with `_`
+    let body = match (spec.grouping, radix) {
+        (None, _) => body,
+        (Some(','), radix) if radix != 10 => return Err(Error::bad_spec(&spec.text)),
+        (Some(sep), 10) => {
+            let end = body.find(['.', 'e', 'E', '%']).unwrap_or(body.len());
+            format!("{}{}", number::group(&body[..end], sep, 3), &body[end..])
+        }
+        (Some(sep), _) => number::group(&body, sep, 4),
+    };
+    let sign = match (x.is_sign_negative(), spec.plus, spec.space) {
+        (true, _, _) => "-",
+        (false, true, _) => "+",
+        (false, false, true) => " ",
+        (false, false, false) => "",
+    };
+    let prefix = if spec.alternate { prefix } else { "" };
+    Ok((format!("{}{}{}", sign, prefix, body), true))
+}
+
+/// Pads what [`python_value`] rendered to `width` cells like python would: numbers go to the
+/// right and strings to the left unless the spec says otherwise, and a `0` without an alignment
+/// pads numbers with zeros after their sign.
+pub fn python_pad(shown: &str, spec: &FormatSpec, width: usize, numeric: bool) -> String {
+    let fill = if spec.zero && spec.fill == ' ' {
+        '0'
+    } else {
+        spec.fill
+    };
+    if numeric && (spec.after_sign || spec.zero && !spec.aligned) {
+        return number::pad_after_sign(shown, width, fill);
+    }
+    let align = match (spec.aligned, numeric) {
+        (true, _) => spec.align,
+        (false, true) => Alignment::Right,
+        (false, false) => Alignment::Left,
+    };
+    Formatter::prepare_filled(shown, align, width, fill)
+}
+
+/// Python's `repr` of a string, quoted and with anything unprintable escaped. With `ascii`, like
+/// python's `ascii`, everything outside of ASCII is escaped as well.
+fn python_repr(s: &str, ascii: bool) -> String {
+    let quote = if s.contains('\'') && !s.contains('"') {
+        '"'
+    } else {
+        '\''
+    };
+    let mut output = String::with_capacity(s.len() + 2);
+    output.push(quote);
+    for c in s.chars() {
+        match c {
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if c == quote => {
+                output.push('\\');
+                output.push(c);
+            }
+            c if c.is_control() || (ascii && !c.is_ascii()) => match c as u32 {
+                n @ 0..=0xff => output.push_str(&format!("\\x{:02x}", n)),
+                n @ 0x100..=0xffff => output.push_str(&format!("\\u{:04x}", n)),
+                n => output.push_str(&format!("\\U{:08x}", n)),
+            },
+            c => output.push(c),
+        }
+    }
+    output.push(quote);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn values() {
+        assert_eq!(Value::of("42"), Value::Int(42));
+        assert_eq!(Value::of("-0x1f"), Value::Int(-31));
+        assert_eq!(Value::of("1.0"), Value::Float(1.0));
+        assert_eq!(Value::of("1e3"), Value::Float(1000.0));
+        assert_eq!(Value::of("1.0.0"), Value::Str);
+        assert_eq!(Value::of("inf"), Value::Str);
+    }
+}
@@ -10,7 +10,10 @@ pub enum Error {
     InvalidSpec(String),
     InvalidArgNumber(String),
     InvalidArgName(String),
+    InvalidArgValue(String),
     IncorrectNumberOfArgs,
+    InvalidFlag(String),
+    Io(std::io::Error),
     Other(String),
 }
 
@@ -30,6 +33,13 @@ impl Error {
         ))
     }
 
+    pub fn bad_arg_value(value: &str, spec: &str, expected: &str) -> Self {
+        Self::InvalidArgValue(format!(
+            "{} expects {}, but was given '{}'",
+            spec, expected, value
+        ))
+    }
+
     pub fn bad_spec(spec: &str) -> Self {
         Self::InvalidSpec(format!("Invalid format specifier: {}", spec))
     }
@@ -37,6 +47,18 @@ impl Error {
     pub fn zero_width(spec: &str) -> Self {
         Self::InvalidSpec(format!("Format specifier cannot be zero-width: {}", spec))
     }
+
+    pub fn missing_flag_value(flag: &str) -> Self {
+        Self::InvalidFlag(format!("{} requires a value", flag))
+    }
+
+    pub fn bad_flag_value(flag: &str, value: &str) -> Self {
+        Self::InvalidFlag(format!("'{}' is not a valid value for {}", value, flag))
+    }
+
+    pub fn unexpected_flag_value(flag: &str) -> Self {
+        Self::InvalidFlag(format!("{} does not take a value", flag))
+    }
 }
 
 impl std::fmt::Display for Error {
@@ -48,10 +70,19 @@ impl std::fmt::Display for Error {
             Error::Other(s) => write!(f, "{}", s),
             Error::InvalidArgNumber(s) => write!(f, "Invalid argument number: {}", s),
             Error::InvalidArgName(s) => write!(f, "Invalid argument name: {}", s),
+            Error::InvalidArgValue(s) => write!(f, "Invalid argument value: {}", s),
+            Error::InvalidFlag(s) => write!(f, "Invalid flag: {}", s),
+            Error::Io(err) => write!(f, "I/O error: {}", err),
         }
     }
 }
 
 impl std::error::Error for Error {}
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
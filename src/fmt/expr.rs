@@ -0,0 +1,252 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The arithmetic of expression specs like `{=2*{0}+1}` or `{= {price} * {qty} :.2}`.
+//!
+//! Expressions have numbers, args in brackets (by position or name), parentheses, unary `-`, and
+//! the operators `+`, `-`, `*`, `/`, `%` and `^` (or `**`) with their usual precedence. Every
+//! value is a float, so `7/2` is `3.5`.
+
+use super::number::show;
+
+/// A parsed expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    /// An arg, by its position or name
+    Arg(String),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, char, Box<Expr>),
+}
+
+impl Expr {
+    /// Parses `s`, or returns a description of what is wrong with it.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut parser = Parser { s, pos: 0 };
+        let expr = parser.sum()?;
+        parser.skip_space();
+        match parser.peek() {
+            None => Ok(expr),
+            Some(c) => Err(format!("unexpected '{}' at {}", c, parser.pos)),
+        }
+    }
+
+    /// The args the expression uses, by position or name, in the order they appear.
+    pub fn args(&self) -> Vec<&str> {
+        match self {
+            Expr::Number(_) => Vec::new(),
+            Expr::Arg(key) => vec![key.as_str()],
+            Expr::Neg(expr) => expr.args(),
+            Expr::Binary(left, _, right) => {
+                let mut args = left.args();
+                args.extend(right.args());
+                args
+            }
+        }
+    }
+
+    /// Evaluates the expression, looking up the value of every arg it uses with `arg`.
+    pub fn eval(&self, arg: &mut dyn FnMut(&str) -> crate::Result<f64>) -> crate::Result<f64> {
+        Ok(match self {
+            Expr::Number(n) => *n,
+            Expr::Arg(key) => arg(key)?,
+            Expr::Neg(expr) => -expr.eval(arg)?,
+            Expr::Binary(left, op, right) => {
+                let (left, right) = (left.eval(arg)?, right.eval(arg)?);
+                match op {
+                    '+' => left + right,
+                    '-' => left - right,
+                    '*' => left * right,
+                    '^' => left.powf(right),
+                    '/' | '%' if right == 0.0 => {
+                        return Err(crate::Error::Other("Division by zero".to_string()))
+                    }
+                    '/' => left / right,
+                    _ => left % right,
+                }
+            }
+        })
+    }
+}
+
+/// A recursive descent parser, one method per level of precedence.
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn skip_space(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    /// Takes `op` if it is next, skipping any whitespace before it.
+    fn eat(&mut self, op: &str) -> bool {
+        self.skip_space();
+        let found = self.s[self.pos..].starts_with(op);
+        if found {
+            self.pos += op.len();
+        }
+        found
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        loop {
+            let op = match () {
+                _ if self.eat("+") => '+',
+                _ if self.eat("-") => '-',
+                _ => return Ok(expr),
+            };
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        loop {
+            let op = match () {
+                _ if self.eat("*") => '*',
+                _ if self.eat("/") => '/',
+                _ if self.eat("%") => '%',
+                _ => return Ok(expr),
+            };
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("-") {
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        if self.eat("+") {
+            return self.unary();
+        }
+        self.power()
+    }
+
+    /// Powers bind tighter than a unary minus on their left, and group to the right
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.eat("^") || self.eat("**") {
+            return Ok(Expr::Binary(Box::new(base), '^', Box::new(self.unary()?)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        self.skip_space();
+        let rest = &self.s[self.pos..];
+        if self.eat("(") {
+            let expr = self.sum()?;
+            return match self.eat(")") {
+                true => Ok(expr),
+                false => Err(format!("missing ')' at {}", self.pos)),
+            };
+        }
+        if let Some(arg) = rest.strip_prefix('{') {
+            let end = arg
+                .find('}')
+                .ok_or_else(|| format!("missing '}}' at {}", self.pos))?;
+            let key = arg[..end].trim();
+            if key.is_empty() {
+                return Err(format!("missing arg at {}", self.pos));
+            }
+            self.pos += end + 2;
+            return Ok(Expr::Arg(key.to_string()));
+        }
+        let mantissa = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        // An exponent like the `e-3` of `1.5e-3` only counts if digits follow it
+        let exponent = rest[mantissa..]
+            .strip_prefix(['e', 'E'])
+            .map_or(0, |after| {
+                let unsigned = after.strip_prefix(['+', '-']).unwrap_or(after);
+                let digits = unsigned.len()
+                    - unsigned
+                        .trim_start_matches(|c: char| c.is_ascii_digit())
+                        .len();
+                match digits {
+                    0 => 0,
+                    _ => rest.len() - mantissa - unsigned.len() + digits,
+                }
+            });
+        let len = mantissa + exponent;
+        match rest[..len].parse::<f64>() {
+            Ok(n) => {
+                self.pos += len;
+                Ok(Expr::Number(n))
+            }
+            Err(_) => match rest.chars().next() {
+                Some(c) => Err(format!("unexpected '{}' at {}", c, self.pos)),
+                None => Err("unexpected end".to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    fn eval(s: &str) -> f64 {
+        let mut arg = |key: &str| match key {
+            "0" => Ok(4.0),
+            "price" => Ok(2.5),
+            _ => Err(crate::Error::bad_arg_name(key)),
+        };
+        Expr::parse(s).unwrap().eval(&mut arg).unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(eval("2*{0}+1"), 9.0);
+        assert_eq!(eval(" {price} * { 0 } "), 10.0);
+        assert_eq!(eval("1 + 2 * 3 - 4 / 2"), 5.0);
+        assert_eq!(eval("(1 + 2) * 3"), 9.0);
+        assert_eq!(eval("7 % 4 + 7/2"), 6.5);
+        assert_eq!(eval("2^3^2"), 512.0);
+        assert_eq!(eval("2**-1"), 0.5);
+        assert_eq!(eval("-2^2"), -4.0);
+        assert_eq!(eval("--3"), 3.0);
+        assert_eq!(eval("1e3 + 2.5E-1"), 1000.25);
+        assert_eq!(eval("1e+2*2"), 200.0);
+        assert!(Expr::parse("1e").is_err());
+        assert!(Expr::parse("1e+").is_err());
+        assert!(Expr::parse("1 +").is_err());
+        assert!(Expr::parse("(1").is_err());
+        assert!(Expr::parse("1 2").is_err());
+        assert!(Expr::parse("{}").is_err());
+        assert!(Expr::parse("{0").is_err());
+        assert!(Expr::parse("x").is_err());
+        let mut arg = |_: &str| Ok(0.0);
+        assert!(Expr::parse("1/{0}").unwrap().eval(&mut arg).is_err());
+        let expr = Expr::parse("-{0} * ({price} + 1) / {0}").unwrap();
+        assert_eq!(expr.args(), vec!["0", "price", "0"]);
+        assert!(Expr::parse("1+2").unwrap().args().is_empty());
+    }
+
+    #[test]
+    fn results() {
+        assert_eq!(show(9.0), "9");
+        assert_eq!(show(-0.0), "0");
+        assert_eq!(show(0.1 + 0.2), "0.3");
+        assert_eq!(show(3.5), "3.5");
+        assert_eq!(show(1.0 / 3.0), "0.333333333333");
+        assert_eq!(show(1e20), "100000000000000000000");
+        assert_eq!(show(eval("2^10000")), "inf");
+        assert_eq!(show(eval("-1e308*10")), "-inf");
+        assert_eq!(show(f64::NAN), "NaN");
+    }
+}
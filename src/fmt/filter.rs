@@ -0,0 +1,735 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Filters transform the value of an arg before it is formatted, like `{name:upper}`. They follow
+//! the colon of a spec and are separated by colons, so `{name:trim:upper:>10}` trims the value,
+//! uppercases it and then right aligns it. Anything after the last filter is a regular spec.
+//! Filters taking arguments have them in parentheses, like `{sep:repeat(20)}`. Text arguments
+//! are taken literally, spaces included, with `\,` for a comma and `\\` for a backslash. The
+//! regex filters `match` and `capture` need the `regex` feature.
+//!
+//! `color_if` is the one filter that leaves the value alone, it picks the style the value is
+//! shown in instead.
+
+#[cfg(feature = "regex")]
+use regex::Regex;
+
+use super::{hash, number, style};
+use crate::{ansi::Style, encode};
+
+/// The names of every filter
+pub const FILTERS: [&str; 24] = [
+    "upper",
+    "lower",
+    "title",
+    "snake",
+    "camel",
+    "trim",
+    "reverse",
+    "repeat",
+    "slice",
+    "replace",
+    "match",
+    "capture",
+    "bytes",
+    "duration",
+    "b64",
+    "b64d",
+    "urlencode",
+    "urldecode",
+    "json",
+    "sh",
+    "html",
+    "sha256",
+    "md5",
+    "color_if",
+];
+
+/// A transformation of an arg's value, see the [module docs](self).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// `upper`, every letter in uppercase
+    Upper,
+    /// `lower`, every letter in lowercase
+    Lower,
+    /// `title`, the first letter of every word in uppercase and the rest in lowercase
+    Title,
+    /// `snake`, the words in lowercase joined by underscores, like `hello_world`
+    Snake,
+    /// `camel`, the words joined with every word but the first capitalized, like `helloWorld`
+    Camel,
+    /// `trim`, without whitespace at the start and end
+    Trim,
+    /// `reverse`, the grapheme clusters in reverse order
+    Reverse,
+    /// `repeat(n)`, the value `n` times over
+    Repeat(usize),
+    /// `slice(start, end)`, the grapheme clusters from `start` up to `end`, or to the end of the
+    /// value if there is no `end`. Negative positions count from the end, like in python.
+    Slice(i64, Option<i64>),
+    /// `replace(old, new)`, every occurrence of `old` replaced with `new`
+    Replace(String, String),
+    /// `match(regex)`, the first match of `regex`, or nothing if it doesn't match
+    #[cfg(feature = "regex")]
+    Match(Pattern),
+    /// `capture(regex, n)`, capture group `n` (`1` if not given) of the first match of `regex`, or
+    /// nothing if it doesn't match
+    #[cfg(feature = "regex")]
+    Capture(Pattern, usize),
+    /// `bytes`, a number of bytes in the largest unit it is at least one of, like `1.5 MB`.
+    /// `bytes(binary)` uses powers of 1024 like `1.5 MiB`, and a number like `bytes(2)` sets the
+    /// decimal places (`1` if not given).
+    Bytes { binary: bool, decimals: usize },
+    /// `duration`, a number of seconds like `1d 2h 3m 4s`, or like `1:02:03:04` with
+    /// `duration(clock)`
+    Duration { clock: bool },
+    /// `b64`, the value encoded as base64
+    Base64,
+    /// `b64d`, the value decoded from base64, which has to be UTF-8 text
+    Base64Decode,
+    /// `urlencode`, the value percent encoded so it can be used anywhere in a URL
+    UrlEncode,
+    /// `urldecode`, the value with percent encoded bytes decoded
+    UrlDecode,
+    /// `json`, the value escaped so it can be put between the quotes of a JSON string
+    Json,
+    /// `sh`, the value in single quotes so a POSIX shell takes it literally as a single word
+    Shell,
+    /// `html`, the value escaped so it can be put in HTML text or attributes
+    Html,
+    /// `sha256`, the SHA-256 digest of the value in hexadecimal
+    Sha256,
+    /// `md5`, the MD5 digest of the value in hexadecimal
+    Md5,
+    /// `color_if(<0, red, green)`, the value unchanged but shown in the first style if the
+    /// comparison holds and in the second one (if given) if it doesn't
+    ColorIf(Comparison, Style, Style),
+}
+
+/// The comparison of a `color_if` filter, like the `<0` of `color_if(<0, red, green)`. Values are
+/// compared as numbers if both sides are numbers, and as text otherwise. Without an operator the
+/// value has to be equal to the operand.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comparison {
+    op: &'static str,
+    operand: String,
+}
+
+impl Comparison {
+    const OPS: [&'static str; 7] = ["<=", ">=", "==", "!=", "<", ">", "="];
+
+    fn parse(s: &str) -> Self {
+        let s = s.trim();
+        let op = Self::OPS.iter().find(|op| s.starts_with(*op)).copied();
+        Self {
+            op: op.unwrap_or("=="),
+            operand: s[op.map_or(0, str::len)..].trim().to_string(),
+        }
+    }
+
+    /// Whether `value` passes the comparison.
+    pub fn holds(&self, value: &str) -> bool {
+        let value = value.trim();
+        let ordering = match (
+            number::parse_float(value),
+            number::parse_float(&self.operand),
+        ) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => Some(value.cmp(self.operand.as_str())),
+        };
+        let Some(ordering) = ordering else {
+            return false;
+        };
+        match self.op {
+            "<" => ordering.is_lt(),
+            "<=" => ordering.is_le(),
+            ">" => ordering.is_gt(),
+            ">=" => ordering.is_ge(),
+            "!=" => ordering.is_ne(),
+            _ => ordering.is_eq(),
+        }
+    }
+}
+
+/// A compiled regex, compared by the pattern it was compiled from.
+#[cfg(feature = "regex")]
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+#[cfg(feature = "regex")]
+impl Pattern {
+    fn new(entire: &str, pattern: &str) -> crate::Result<Self> {
+        Regex::new(pattern).map(Self).map_err(|err| {
+            crate::Error::InvalidSpec(format!("Unable to compile regex '{}': {}", pattern, err))
+        })
+    }
+}
+
+#[cfg(feature = "regex")]
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+#[cfg(feature = "regex")]
+impl Eq for Pattern {}
+
+impl Filter {
+    /// Parses the filter `name` with the arguments between its parentheses, or returns `None` if
+    /// there is no filter called `name`.
+    fn parse(entire: &str, name: &str, args: Option<&str>) -> crate::Result<Option<Self>> {
+        let filter = match (name, args) {
+            ("upper", None) => Self::Upper,
+            ("lower", None) => Self::Lower,
+            ("title", None) => Self::Title,
+            ("snake", None) => Self::Snake,
+            ("camel", None) => Self::Camel,
+            ("trim", None) => Self::Trim,
+            ("reverse", None) => Self::Reverse,
+            ("b64", None) => Self::Base64,
+            ("b64d", None) => Self::Base64Decode,
+            ("urlencode", None) => Self::UrlEncode,
+            ("urldecode", None) => Self::UrlDecode,
+            ("json", None) => Self::Json,
+            ("sh", None) => Self::Shell,
+            ("html", None) => Self::Html,
+            ("sha256", None) => Self::Sha256,
+            ("md5", None) => Self::Md5,
+            ("repeat", Some(count)) => Self::Repeat(count_of(entire, count)?),
+            ("slice", Some(range)) => {
+                let (start, end) = match range.split_once(',') {
+                    Some((start, end)) if end.trim().is_empty() => (start, None),
+                    Some((start, end)) => (start, Some(position_of(entire, end)?)),
+                    None => (range, None),
+                };
+                Self::Slice(position_of(entire, start)?, end)
+            }
+            ("replace", Some(args)) => match <[String; 2]>::try_from(text_args(args)) {
+                Ok([old, new]) if !old.is_empty() => Self::Replace(old, new),
+                _ => {
+                    return Err(crate::Error::InvalidSpec(format!(
+                        "Filter replace needs an old and a new text: {}",
+                        entire
+                    )))
+                }
+            },
+            ("bytes", None) => Self::Bytes {
+                binary: false,
+                decimals: 1,
+            },
+            ("bytes", Some(args)) => {
+                let mut bytes = (false, 1);
+                for arg in args.split(',').map(str::trim) {
+                    match arg {
+                        "binary" => bytes.0 = true,
+                        "decimal" => bytes.0 = false,
+                        decimals => bytes.1 = count_of(entire, decimals)?,
+                    }
+                }
+                Self::Bytes {
+                    binary: bytes.0,
+                    decimals: bytes.1,
+                }
+            }
+            ("duration", None) => Self::Duration { clock: false },
+            ("duration", Some(style)) => match style.trim() {
+                "clock" => Self::Duration { clock: true },
+                "words" => Self::Duration { clock: false },
+                _ => {
+                    return Err(crate::Error::InvalidSpec(format!(
+                        "Filter duration takes clock or words: {}",
+                        entire
+                    )))
+                }
+            },
+            ("color_if", Some(args)) => {
+                let args = text_args(args);
+                let styles = args[1..]
+                    .iter()
+                    .map(|s| style::parse(s.trim()))
+                    .collect::<Option<Vec<_>>>();
+                let comparison = Comparison::parse(&args[0]);
+                match styles.as_deref() {
+                    Some([then]) => Self::ColorIf(comparison, *then, Style::default()),
+                    Some([then, otherwise]) => Self::ColorIf(comparison, *then, *otherwise),
+                    _ => {
+                        return Err(crate::Error::InvalidSpec(format!(
+                            "Filter color_if needs a comparison and one or two styles: {}",
+                            entire
+                        )))
+                    }
+                }
+            }
+            #[cfg(feature = "regex")]
+            ("match", Some(pattern)) => Self::Match(Pattern::new(entire, pattern)?),
+            #[cfg(feature = "regex")]
+            ("capture", Some(args)) => {
+                // Regexes may contain commas themselves, so only a number after the last one is
+                // taken as the group
+                let (pattern, group) = match args.rsplit_once(',') {
+                    Some((pattern, group)) => match group.trim().parse() {
+                        Ok(group) => (pattern, group),
+                        Err(_) => (args, 1),
+                    },
+                    None => (args, 1),
+                };
+                Self::Capture(Pattern::new(entire, pattern)?, group)
+            }
+            #[cfg(not(feature = "regex"))]
+            ("match" | "capture", Some(_)) => {
+                return Err(crate::Error::InvalidSpec(format!(
+                    "Filter {} needs the regex feature: {}",
+                    name, entire
+                )))
+            }
+            _ if !FILTERS.contains(&name) => return Ok(None),
+            (_, None) => {
+                return Err(crate::Error::InvalidSpec(format!(
+                    "Filter {} needs arguments: {}",
+                    name, entire
+                )))
+            }
+            (_, Some(_)) => {
+                return Err(crate::Error::InvalidSpec(format!(
+                    "Filter {} does not take arguments: {}",
+                    name, entire
+                )))
+            }
+        };
+        Ok(Some(filter))
+    }
+
+    /// Parses the filters at the start of `input`, what follows the colon of the spec `entire`.
+    /// Returns the filters along with the rest of `input`, which is a regular spec. If `input`
+    /// doesn't start with a filter no filters are returned and `input` is returned as is.
+    pub(crate) fn parse_chain<'a>(
+        entire: &str,
+        mut input: &'a str,
+    ) -> crate::Result<(Vec<Self>, &'a str)> {
+        let mut filters = Vec::new();
+        while !input.is_empty() {
+            let name_len = input
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(input.len());
+            let (name, mut rest) = input.split_at(name_len);
+            let mut args = None;
+            if let Some(after) = rest.strip_prefix('(') {
+                // The arguments end at the first `)` that ends the spec or is followed by a colon
+                let close = after.match_indices(')').map(|(i, _)| i).find(|&i| {
+                    let next = &after[i + 1..];
+                    next.is_empty() || next.starts_with(':')
+                });
+                match close {
+                    Some(i) => {
+                        args = Some(&after[..i]);
+                        rest = &after[i + 1..];
+                    }
+                    None => break,
+                }
+            }
+            if !rest.is_empty() && !rest.starts_with(':') {
+                break;
+            }
+            match Self::parse(entire, name, args)? {
+                Some(filter) => filters.push(filter),
+                None => break,
+            }
+            input = rest.strip_prefix(':').unwrap_or(rest);
+        }
+        Ok((filters, input))
+    }
+
+    /// Applies the filter to `value`, the value of the arg formatted by the spec `entire`. Filters
+    /// that only make sense for numbers fail for anything else.
+    pub fn apply(&self, value: &str, entire: &str) -> crate::Result<String> {
+        Ok(match self {
+            Self::Upper => value.to_uppercase(),
+            Self::Lower => value.to_lowercase(),
+            Self::Title => title(value),
+            Self::Snake => words(value)
+                .iter()
+                .map(|word| word.to_lowercase())
+                .collect::<Vec<_>>()
+                .join("_"),
+            Self::Camel => words(value)
+                .iter()
+                .enumerate()
+                .map(|(i, word)| match i {
+                    0 => word.to_lowercase(),
+                    _ => capitalize(word),
+                })
+                .collect(),
+            Self::Trim => value.trim().to_string(),
+            Self::Reverse => {
+                let graphemes = crate::ansi::grapheme_indices(value).collect::<Vec<_>>();
+                graphemes.iter().rev().map(|(_, g)| *g).collect()
+            }
+            Self::Repeat(count) => value.repeat(*count),
+            Self::Slice(start, end) => {
+                let graphemes = crate::ansi::grapheme_indices(value).collect::<Vec<_>>();
+                let len = graphemes.len() as i64;
+                let clamp = |pos: i64| (if pos < 0 { len + pos } else { pos }).clamp(0, len);
+                let (start, end) = (clamp(*start), clamp(end.unwrap_or(len)));
+                graphemes
+                    .iter()
+                    .take(end as usize)
+                    .skip(start as usize)
+                    .map(|(_, g)| *g)
+                    .collect()
+            }
+            Self::Replace(old, new) => value.replace(old.as_str(), new),
+            #[cfg(feature = "regex")]
+            Self::Match(Pattern(regex)) => regex
+                .find(value)
+                .map_or("", |found| found.as_str())
+                .to_string(),
+            #[cfg(feature = "regex")]
+            Self::Capture(Pattern(regex), group) => regex
+                .captures(value)
+                .and_then(|captures| captures.get(*group))
+                .map_or("", |found| found.as_str())
+                .to_string(),
+            Self::Bytes { binary, decimals } => {
+                number::bytes(number_of(value, entire, "bytes")?, *binary, *decimals)
+            }
+            Self::Duration { clock } => {
+                number::duration(number_of(value, entire, "seconds")?, *clock)
+            }
+            Self::Base64 => encode::base64(value.as_bytes()),
+            Self::Base64Decode => encode::base64_decode(value)
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .ok_or_else(|| crate::Error::bad_arg_value(value, entire, "base64 encoded text"))?,
+            Self::UrlEncode => encode::url_encode(value),
+            Self::UrlDecode => encode::url_decode(value).ok_or_else(|| {
+                crate::Error::bad_arg_value(value, entire, "percent encoded text")
+            })?,
+            Self::Json => encode::json_escape(value),
+            Self::Shell => encode::sh_quote(value),
+            Self::Html => encode::html_escape(value),
+            Self::Sha256 => hash::sha256(value.as_bytes()),
+            Self::Md5 => hash::md5(value.as_bytes()),
+            Self::ColorIf(..) => value.to_string(),
+        })
+    }
+}
+
+/// Splits the arguments of a filter like `replace(old, new)` at their commas, turning `\,` into a
+/// comma and `\\` into a backslash.
+fn text_args(args: &str) -> Vec<String> {
+    let mut split = vec![String::new()];
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(escaped @ (',' | '\\')) => split.last_mut().unwrap().push(escaped),
+                Some(other) => split.last_mut().unwrap().extend(['\\', other]),
+                None => split.last_mut().unwrap().push('\\'),
+            },
+            ',' => split.push(String::new()),
+            c => split.last_mut().unwrap().push(c),
+        }
+    }
+    split
+}
+
+/// Parses the value of an arg given to a filter that needs a number of `what`.
+fn number_of(value: &str, entire: &str, what: &str) -> crate::Result<f64> {
+    number::parse_float(value)
+        .ok_or_else(|| crate::Error::bad_arg_value(value, entire, &format!("a number of {}", what)))
+}
+
+/// Parses an argument of a filter like `slice(0, 8)` as a position, which may be negative.
+fn position_of(entire: &str, arg: &str) -> crate::Result<i64> {
+    arg.trim().parse().map_err(|_| {
+        crate::Error::InvalidSpec(format!(
+            "Filter argument '{}' is not a position: {}",
+            arg, entire
+        ))
+    })
+}
+
+/// Parses the argument of a filter like `repeat(3)` as a count.
+fn count_of(entire: &str, arg: &str) -> crate::Result<usize> {
+    arg.trim().parse().map_err(|_| {
+        crate::Error::InvalidSpec(format!(
+            "Filter argument '{}' is not a count: {}",
+            arg, entire
+        ))
+    })
+}
+
+/// Applies every filter in `filters` to `value`, in order. Returns the value along with the style
+/// picked by the `color_if` filters, each of them looking at the value as the filters before it
+/// left it.
+pub fn apply_all(
+    filters: &[Filter],
+    mut value: String,
+    entire: &str,
+) -> crate::Result<(String, Style)> {
+    let mut picked = Style::default();
+    for filter in filters {
+        if let Filter::ColorIf(comparison, then, otherwise) = filter {
+            let style = if comparison.holds(&value) {
+                then
+            } else {
+                otherwise
+            };
+            picked = style::merge(picked, *style);
+        }
+        value = filter.apply(&value, entire)?;
+    }
+    Ok((value, picked))
+}
+
+/// The first letter of `word` in uppercase and the rest in lowercase.
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(char::to_lowercase))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Capitalizes every word of `s`, keeping the whitespace between them as it is.
+fn title(s: &str) -> String {
+    let mut output = String::with_capacity(s.len());
+    let mut start = true;
+    for c in s.chars() {
+        if start {
+            output.extend(c.to_uppercase());
+        } else {
+            output.extend(c.to_lowercase());
+        }
+        start = c.is_whitespace();
+    }
+    output
+}
+
+/// Splits `s` into words at anything that isn't a letter or a digit, and where the case changes
+/// like in `camelCase` or `HTTPServer`.
+fn words(s: &str) -> Vec<String> {
+    let chars = s.chars().collect::<Vec<_>>();
+    let mut words = Vec::new();
+    let mut word = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|i| chars[i]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase()
+            && match prev {
+                Some(p) if p.is_lowercase() || p.is_numeric() => true,
+                Some(p) if p.is_uppercase() => next.is_some_and(|n| n.is_lowercase()),
+                _ => false,
+            };
+        if boundary && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+        word.push(c);
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    fn filter(name: &str, value: &str) -> String {
+        let (filters, rest) = Filter::parse_chain(name, name).unwrap();
+        assert_eq!(rest, "");
+        apply_all(&filters, value.to_string(), name).unwrap().0
+    }
+
+    #[test]
+    fn chains() {
+        let (filters, rest) = Filter::parse_chain("", "upper:lower:>10").unwrap();
+        assert_eq!(filters, vec![Filter::Upper, Filter::Lower]);
+        assert_eq!(rest, ">10");
+        let (filters, rest) = Filter::parse_chain("", "title::^5").unwrap();
+        assert_eq!(filters, vec![Filter::Title]);
+        assert_eq!(rest, ":^5");
+        assert_eq!(Filter::parse_chain("", ">10").unwrap(), (vec![], ">10"));
+        assert_eq!(Filter::parse_chain("", "x").unwrap(), (vec![], "x"));
+        assert_eq!(
+            Filter::parse_chain("", "uppers").unwrap(),
+            (vec![], "uppers")
+        );
+        assert!(Filter::parse_chain("", "upper(1)").is_err());
+        let (filters, rest) = Filter::parse_chain("", "trim:repeat( 3 ):<9").unwrap();
+        assert_eq!(filters, vec![Filter::Trim, Filter::Repeat(3)]);
+        assert_eq!(rest, "<9");
+        assert!(Filter::parse_chain("", "repeat").is_err());
+        assert!(Filter::parse_chain("", "repeat(x)").is_err());
+        assert!(Filter::parse_chain("", "repeat(-1)").is_err());
+        let (filters, _) = Filter::parse_chain("", "slice(0, 8):slice(-3):slice(1,)").unwrap();
+        assert_eq!(
+            filters,
+            vec![
+                Filter::Slice(0, Some(8)),
+                Filter::Slice(-3, None),
+                Filter::Slice(1, None)
+            ]
+        );
+        assert!(Filter::parse_chain("", "slice(a,b)").is_err());
+        assert!(Filter::parse_chain("", "slice").is_err());
+        let (filters, _) = Filter::parse_chain("", "replace(a\\,b, c\\\\d\\n)").unwrap();
+        let replace = Filter::Replace("a,b".to_string(), " c\\d\\n".to_string());
+        assert_eq!(filters, vec![replace]);
+        assert!(Filter::parse_chain("", "replace(a)").is_err());
+        assert!(Filter::parse_chain("", "replace(a,b,c)").is_err());
+        assert!(Filter::parse_chain("", "replace(,b)").is_err());
+    }
+
+    #[test]
+    fn cases() {
+        assert_eq!(filter("upper", "straße"), "STRASSE");
+        assert_eq!(filter("lower", "HeLLo"), "hello");
+        assert_eq!(filter("title", "hello  wORLD-wide"), "Hello  World-wide");
+        assert_eq!(filter("snake", "HelloWorld"), "hello_world");
+        assert_eq!(
+            filter("snake", "parse HTTPServer v2"),
+            "parse_http_server_v2"
+        );
+        assert_eq!(filter("snake", "--already_snake--"), "already_snake");
+        assert_eq!(filter("camel", "hello world"), "helloWorld");
+        assert_eq!(filter("camel", "user-ID"), "userId");
+        assert_eq!(filter("camel", ""), "");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(filter("trim", " \t padded \n"), "padded");
+        assert_eq!(filter("reverse", "abc"), "cba");
+        assert_eq!(filter("repeat(3)", "=-"), "=-=-=-");
+        assert_eq!(filter("repeat(0)", "x"), "");
+        assert_eq!(filter("trim:repeat(2)", " ab "), "abab");
+    }
+
+    #[test]
+    fn slices() {
+        let sha = "3f2a9c1d0e8b7a6f";
+        assert_eq!(filter("slice(0,8)", sha), "3f2a9c1d");
+        assert_eq!(filter("slice(12)", sha), "7a6f");
+        assert_eq!(filter("slice(-4)", sha), "7a6f");
+        assert_eq!(filter("slice(2,-12)", sha), "2a");
+        assert_eq!(filter("slice(0,100)", "short"), "short");
+        assert_eq!(filter("slice(4,2)", "short"), "");
+        assert_eq!(filter("slice(-100,2)", "short"), "sh");
+        assert_eq!(filter("slice(1,3)", "día😀x"), "ía");
+    }
+
+    #[test]
+    fn replacements() {
+        assert_eq!(filter("replace(-,_)", "a-b-c"), "a_b_c");
+        assert_eq!(
+            filter("replace(/, / )", "usr/local/bin"),
+            "usr / local / bin"
+        );
+        assert_eq!(filter("replace(\\,,;)", "a,b"), "a;b");
+        assert_eq!(filter("replace(x,)", "axbx"), "ab");
+        assert_eq!(filter("replace(ab,(b))", "abc"), "(b)c");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(filter("bytes", "1536000"), "1.5 MB");
+        assert_eq!(filter("bytes(binary, 2)", "1536000"), "1.46 MiB");
+        assert_eq!(filter("bytes(0)", " 2600 "), "3 kB");
+        assert!(Filter::parse_chain("", "bytes(huge)").is_err());
+        let (filters, _) = Filter::parse_chain("", "bytes").unwrap();
+        assert!(apply_all(&filters, "lots".to_string(), "{:bytes}").is_err());
+        assert_eq!(filter("duration", "93784"), "1d 2h 3m 4s");
+        assert_eq!(filter("duration(clock)", "93784"), "1:02:03:04");
+        assert!(Filter::parse_chain("", "duration(iso)").is_err());
+    }
+
+    #[test]
+    fn encodings() {
+        assert_eq!(filter("b64", "user:pass"), "dXNlcjpwYXNz");
+        assert_eq!(filter("b64d", "dXNlcjpwYXNz"), "user:pass");
+        assert_eq!(filter("b64:b64d", "round trip"), "round trip");
+        let (filters, _) = Filter::parse_chain("", "b64d").unwrap();
+        assert!(apply_all(&filters, "not base64!".to_string(), "{:b64d}").is_err());
+        assert!(apply_all(&filters, "/w==".to_string(), "{:b64d}").is_err());
+        assert_eq!(filter("urlencode", "hello world"), "hello%20world");
+        assert_eq!(filter("urldecode", "a%2Fb"), "a/b");
+        assert_eq!(filter("md5:slice(0,7)", "abc"), "9001509");
+        assert_eq!(filter("html", "a < b & c"), "a &lt; b &amp; c");
+        assert_eq!(filter("sh", "don't"), r"'don'\''t'");
+        assert_eq!(filter("json", "a \"quote\"\n"), "a \\\"quote\\\"\\n");
+    }
+
+    #[test]
+    fn conditional_styles() {
+        let style = |name: &str, value: &str| {
+            let (filters, _) = Filter::parse_chain(name, name).unwrap();
+            apply_all(&filters, value.to_string(), name).unwrap().1.fg
+        };
+        let (red, green) = (Some(Color::Basic(1)), Some(Color::Basic(2)));
+        assert_eq!(filter("color_if(<0,red,green)", " -3"), " -3");
+        assert_eq!(style("color_if(<0,red,green)", "-3"), red);
+        assert_eq!(style("color_if(<0,red,green)", "+12"), green);
+        assert_eq!(style("color_if(< 0, red, green)", "0"), green);
+        assert_eq!(style("color_if(>=1e3,red)", "1000"), red);
+        assert_eq!(style("color_if(>=1e3,red)", "999"), None);
+        assert_eq!(style("color_if(ok, green, bold.red)", "ok"), green);
+        assert_eq!(style("color_if(==ok, green, bold.red)", "fail"), red);
+        assert_eq!(style("color_if(!=ok,red)", "fail"), red);
+        assert_eq!(style("color_if(<m,red)", "apple"), red);
+        assert_eq!(style("color_if(<0,red,green)", "x"), green);
+        // Each one looks at the value as the filters before it left it
+        assert_eq!(
+            style("color_if(abc,red):upper:color_if(ABC,green)", "abc"),
+            green
+        );
+        assert_eq!(style("upper:color_if(abc,red,green)", "abc"), green);
+
+        assert!(Filter::parse_chain("", "color_if(<0)").is_err());
+        assert!(Filter::parse_chain("", "color_if(<0,red,green,blue)").is_err());
+        assert!(Filter::parse_chain("", "color_if(<0,nope)").is_err());
+        assert!(Filter::parse_chain("", "color_if").is_err());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regexes() {
+        assert_eq!(filter("match(\\d+)", "build 1234 ok"), "1234");
+        assert_eq!(filter("match(\\d+)", "no digits"), "");
+        assert_eq!(filter("match(\\d{1,2})", "12345"), "12");
+        let version = "capture(v(\\d+)\\.(\\d+), 2)";
+        assert_eq!(filter(version, "fmt v1.42.0"), "42");
+        assert_eq!(filter("capture(v(\\d+))", "fmt v1.42.0"), "1");
+        assert_eq!(filter("capture(a(b)?, 1)", "a"), "");
+        assert_eq!(filter("capture(x(\\d{1,3}))", "x12345"), "123");
+        assert!(Filter::parse_chain("", "match(()").is_err());
+        assert!(Filter::parse_chain("", "match").is_err());
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn regexes() {
+        assert!(Filter::parse_chain("", "match(a)").is_err());
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn reverse_graphemes() {
+        assert_eq!(filter("reverse", "ae\u{301}o"), "oe\u{301}a");
+    }
+}
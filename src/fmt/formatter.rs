@@ -4,18 +4,113 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use unicode_width::UnicodeWidthStr;
+use std::sync::atomic::{AtomicBool, Ordering};
 
+use super::expr::Expr;
+use super::spec::{MAX_PRECISION, MAX_WIDTH};
+use super::{dialect, filter, number, style};
 use crate::{
-    spec_regex_brackets_only as format_regex, Alignment, Error, FormatArg, FormatArgs, FormatSpec,
-    Result,
+    ansi::Style,
+    color::{self, Color, ColorLevel},
+    find_spec, replace_escapes, Alignment, Dialect, Error, FormatArg, FormatArgs, FormatSpec,
+    FormatType, Result,
 };
 
+static HIGHLIGHT: AtomicBool = AtomicBool::new(false);
+static PAD_LINES: AtomicBool = AtomicBool::new(false);
+
+/// The colors `--highlight` gives substitutions, one after the other
+const HIGHLIGHTS: [Color; 6] = [
+    Color::Basic(14),
+    Color::Basic(13),
+    Color::Basic(11),
+    Color::Basic(10),
+    Color::Basic(12),
+    Color::Basic(9),
+];
+
+/// Makes every [`Formatter`] created afterwards underline each substitution and show it in a
+/// color of its own, so it is easy to tell apart from the literal text around it.
+pub fn highlight() {
+    HIGHLIGHT.store(true, Ordering::Relaxed);
+}
+
+/// Makes every [`Formatter`] created afterwards pad and align each line of a multi-line value on
+/// its own, so the value stays a rectangle as wide as the spec (or its widest line).
+pub fn pad_lines() {
+    PAD_LINES.store(true, Ordering::Relaxed);
+}
+
+/// Returns an error if `s` contains `\u{1}` or `\u{2}`, the characters the parser uses in place
+/// of escaped brackets. Those would only end up in a format string through decoded escapes like
+/// `\u{1}` or `-e`'s `\x01` and `\0001`, and would otherwise make the parser panic.
+pub fn check_placeholders(s: &str) -> Result<()> {
+    match s.find(['\u{1}', '\u{2}']) {
+        Some(pos) => Err(Error::Other(format!(
+            "The format string can't contain the control character {:?} (found at position {})",
+            &s[pos..pos + 1],
+            pos
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// What a single spec resolves to for a given set of args, see [`Formatter::substitutions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Substitution {
+    /// The spec as written in the format string
+    pub spec: String,
+    /// Byte offset of the spec in the format string
+    pub src_pos: usize,
+    /// The argument the spec consumes, `#N` for positional args or the name for named args
+    pub arg: String,
+    /// The value of that argument, as it was given
+    pub value: String,
+    /// The value after the filters of the spec, before it is formatted
+    pub filtered: String,
+    /// The value after alignment and padding, as it ends up in the output
+    pub prepared: String,
+}
+
+/// A [`Formatter`] paired with its args, rendered only once it is displayed, see
+/// [`Formatter::display`].
+#[derive(Debug, Clone, Copy)]
+pub struct Formatted<'f, 'a, S> {
+    formatter: &'f Formatter,
+    args: &'a [S],
+}
+
+impl<S: std::fmt::Display> std::fmt::Display for Formatted<'_, '_, S> {
+    /// Writes the literal text and each substitution straight to `f`. Since `Display` can't say
+    /// what went wrong, args that don't fit the format string are reported as [`std::fmt::Error`].
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let subs = self
+            .formatter
+            .resolve(self.args)
+            .map_err(|_| std::fmt::Error)?;
+        let text = &self.formatter.fmt_str;
+        let mut last = 0usize;
+        for (sub, spec) in subs.iter().zip(&self.formatter.fmt_spec) {
+            f.write_str(&text[last..spec.fmt_pos])?;
+            f.write_str(&sub.prepared)?;
+            last = spec.fmt_pos;
+        }
+        f.write_str(&text[last..])
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Formatter {
     expected_args: u8,
     fmt_str: String,
     fmt_spec: Vec<FormatSpec>,
+    dialect: Dialect,
+    /// Whether substitutions are highlighted, see [`highlight`]
+    highlight: bool,
+    /// Whether multi-line values are padded line by line, see [`pad_lines`]
+    pad_lines: bool,
+    /// The colors styles and highlights are painted with, see [`color::effective_level`]
+    color: ColorLevel,
 }
 
 impl Formatter {
@@ -31,6 +126,16 @@ impl Formatter {
     }
 
     pub fn new(fmt_str: &str) -> crate::Result<Self> {
+        Self::with_dialect(fmt_str, dialect::current())
+    }
+
+    /// Like [`Formatter::new`], but renders specs with the rules of `dialect` instead of the one
+    /// set for the whole run.
+    pub fn with_dialect(fmt_str: &str, dialect: Dialect) -> crate::Result<Self> {
+        crate::perf::measure(crate::perf::Phase::Parse, || Self::parse(fmt_str, dialect))
+    }
+
+    fn parse(fmt_str: &str, dialect: Dialect) -> crate::Result<Self> {
         let (s, spec) = match Self::parse_fmt(fmt_str) {
             Ok((s, spec)) => (s, spec),
             Err(err) => return Err(err),
@@ -69,11 +174,42 @@ impl Formatter {
         all_names.dedup();
         let unique_names = all_names.len();
 
+        // The grammar is shared, but some of it only means something to one dialect
+        if let Some(spec) = spec.iter().find(|s| !dialect.supports(s)) {
+            return Err(Error::InvalidSpec(format!(
+                "Format spec is not supported by this dialect: {}",
+                spec.text
+            )));
+        }
+        // Conditional bodies are format strings of their own, so check them now rather than only
+        // once they are rendered
+        for body in spec.iter().filter_map(|s| s.conditional.as_deref()) {
+            Self::parse(body, dialect)?;
+        }
+        // Python won't switch between numbered args and taking the next one
+        let numbered = spec.iter().any(|s| s.arg_num.is_some());
+        let next = spec.iter().find(|s| {
+            s.arg_num.is_none()
+                && s.arg_name.is_none()
+                && s.splat.is_none()
+                && s.expression.is_none()
+        });
+        if let (Dialect::Python, true, Some(spec)) = (dialect, numbered, next) {
+            return Err(Error::InvalidSpec(format!(
+                "Python can't mix numbered and automatic args: {}",
+                spec.text
+            )));
+        }
+
         let expected = (empty_args.max(highest_pos) + unique_names) as u8;
         Ok(Self {
             expected_args: expected,
             fmt_str: s,
             fmt_spec: spec,
+            dialect,
+            highlight: HIGHLIGHT.load(Ordering::Relaxed),
+            pad_lines: PAD_LINES.load(Ordering::Relaxed),
+            color: color::effective_level(),
         })
     }
 
@@ -81,67 +217,383 @@ impl Formatter {
         self.expected_args
     }
 
+    pub fn specs(&self) -> &[FormatSpec] {
+        &self.fmt_spec
+    }
+
+    /// Whether any spec takes its value from an arg, rather than from a builtin like `{now}` or an
+    /// expression that only uses numbers and builtins like `{=1+2}`. A conditional like
+    /// `{?name: on {name}}` only needs args when its arg is a builtin whose body needs them, since
+    /// without args it renders nothing otherwise.
+    pub fn needs_args(&self) -> bool {
+        let builtin = |name: &str| crate::builtin::BUILTINS.contains(&name);
+        self.fmt_spec.iter().any(|spec| {
+            match (&spec.conditional, &spec.expression, &spec.arg_name) {
+                (Some(body), _, Some(name)) if builtin(name) => {
+                    Self::parse(body, self.dialect).map_or(true, |inner| inner.needs_args())
+                }
+                (Some(_), _, _) => false,
+                (None, Some(expression), _) => !expression.args().into_iter().all(builtin),
+                (None, None, Some(name)) => !builtin(name),
+                (None, None, None) => true,
+            }
+        })
+    }
+
     pub fn generate<S: std::fmt::Display>(&self, args: &[S]) -> crate::Result<String> {
-        // let args = args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
-        let args: FormatArgs = args.iter().enumerate().collect();
-        let mut positional_count = 0usize;
+        crate::perf::measure(crate::perf::Phase::Generate, || {
+            self.resolve(args).map(|subs| self.assemble(subs))
+        })
+    }
+
+    /// Formats every set of args `iter` yields with this one parsed format string, lazily.
+    pub fn format_iter<'f, I>(&'f self, iter: I) -> impl Iterator<Item = crate::Result<String>> + 'f
+    where
+        I: IntoIterator<Item = FormatArgs>,
+        I::IntoIter: 'f,
+    {
+        iter.into_iter().map(move |args| {
+            crate::perf::measure(crate::perf::Phase::Generate, || {
+                self.resolve_args(&args).map(|subs| self.assemble(subs))
+            })
+        })
+    }
+
+    /// Like [`Formatter::format_iter`], but writes every result to `writer` as a line of its own
+    /// instead of collecting it, returning the number of lines written. Stops at the first set of
+    /// args that doesn't fit.
+    pub fn write_iter<W, I>(&self, writer: &mut W, iter: I) -> crate::Result<usize>
+    where
+        W: std::io::Write,
+        I: IntoIterator<Item = FormatArgs>,
+    {
+        let mut written = 0usize;
+        for args in iter {
+            let subs =
+                crate::perf::measure(crate::perf::Phase::Generate, || self.resolve_args(&args))?;
+            let mut last = 0usize;
+            for (sub, spec) in subs.iter().zip(&self.fmt_spec) {
+                writer.write_all(&self.fmt_str.as_bytes()[last..spec.fmt_pos])?;
+                writer.write_all(sub.prepared.as_bytes())?;
+                last = spec.fmt_pos;
+            }
+            writer.write_all(&self.fmt_str.as_bytes()[last..])?;
+            writer.write_all(b"\n")?;
+            written += 1;
+        }
+        Ok(written)
+    }
+
+    /// Defers rendering with `args` until the result is displayed, so it can be passed to `write!`
+    /// or a logging macro without building a `String` first.
+    pub fn display<'f, 'a, S: std::fmt::Display>(&'f self, args: &'a [S]) -> Formatted<'f, 'a, S> {
+        Formatted {
+            formatter: self,
+            args,
+        }
+    }
+
+    fn assemble(&self, subs: Vec<Substitution>) -> String {
         // Unused at the moment, since we iterate in the ranges in reverse, we no longer need to track character offset
         let mut offset = 0usize;
-        let mut mods = Vec::new();
+        let mods = subs
+            .into_iter()
+            .zip(&self.fmt_spec)
+            .map(|(sub, spec)| (sub.prepared, spec.fmt_pos))
+            .collect::<Vec<_>>();
+
+        let mut output = self.fmt_str.clone();
+        for (insert, pos) in mods.iter().rev() {
+            if !output.is_char_boundary(*pos) {
+                panic!("position {} is not a char boundary for output string {} (attempting to insert {})", pos, output, insert);
+            }
+
+            output.insert_str(*pos, insert);
+        }
+
+        output
+    }
+
+    /// Resolves every spec of the format string against `args`, in the order they appear, without
+    /// assembling the output.
+    pub fn substitutions<S: std::fmt::Display>(
+        &self,
+        args: &[S],
+    ) -> crate::Result<Vec<Substitution>> {
+        crate::perf::measure(crate::perf::Phase::Generate, || self.resolve(args))
+    }
+
+    fn resolve<S: std::fmt::Display>(&self, args: &[S]) -> crate::Result<Vec<Substitution>> {
+        // let args = args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        self.resolve_args(&args.iter().enumerate().collect())
+    }
+
+    fn resolve_args(&self, args: &FormatArgs) -> crate::Result<Vec<Substitution>> {
+        let mut positional_count = 0usize;
+        let mut subs = Vec::with_capacity(self.fmt_spec.len());
 
         for spec in &self.fmt_spec {
-            let insert = if let Some(num) = spec.arg_num {
+            if let Some(ref body) = spec.conditional {
+                subs.push(self.conditional(spec, body, args)?);
+                continue;
+            }
+            // A splat takes every positional arg that is left, so nothing is left after it
+            if let Some(ref separator) = spec.splat {
+                let joined = self.splat(args, positional_count).join(separator);
+                positional_count = args.len();
+                subs.push(Substitution {
+                    spec: spec.text.clone(),
+                    src_pos: spec.src_pos,
+                    arg: "*".to_string(),
+                    value: joined.clone(),
+                    filtered: joined.clone(),
+                    prepared: joined,
+                });
+                continue;
+            }
+            // `.*` takes its precision from the next positional arg, before the one it formats
+            let next_precision = if spec.precision_next {
+                positional_count += 1;
+                let key = (positional_count - 1).to_string();
+                Some(count_arg(
+                    args,
+                    &key,
+                    &spec.text,
+                    "a precision",
+                    MAX_PRECISION,
+                )?)
+            } else {
+                None
+            };
+            let (insert, arg) = if let Some(ref expression) = spec.expression {
+                (Self::evaluate(spec, expression, args)?, "=".to_string())
+            } else if let Some(num) = spec.arg_num {
                 match args.get(num) {
-                    Some(s) => s,
-                    None => {
-                        eprintln!("Unable to find numbered arg #{}", num);
-                        return Err(crate::Error::bad_arg_num(num, args.len()));
-                    }
+                    Some(s) => (s.clone(), format!("#{}", num)),
+                    None => return Err(crate::Error::bad_arg_num(num, args.len())),
                 }
             } else if let Some(ref name) = spec.arg_name {
-                match args.get_named(name) {
-                    Some(s) => s,
-                    None => {
-                        eprintln!("Unable to find named arg '{}'", name);
-                        return Err(crate::Error::bad_arg_name(name));
-                    }
+                match Self::named(args, name, spec.option.as_deref()) {
+                    Some(s) => (s, name.clone()),
+                    None => return Err(crate::Error::bad_arg_name(name)),
                 }
             } else {
                 let s = match args.get(positional_count) {
-                    Some(s) => s,
-                    None => {
-                        eprintln!("Positional arg requests have surpassed provided args");
-                        return Err(crate::Error::bad_arg_num(positional_count, args.len()));
-                    }
+                    Some(s) => s.clone(),
+                    None => return Err(crate::Error::bad_arg_num(positional_count, args.len())),
                 };
                 positional_count += 1;
-                s
+                (s, format!("#{}", positional_count - 1))
             };
+            let value = insert.clone();
+            let (insert, picked) = filter::apply_all(&spec.filters, insert, &spec.text)?;
 
-            let width = match spec.width {
-                Some(w) => w,
-                None => UnicodeWidthStr::width(insert.as_str()),
+            // Precision rounds numbers to that many decimal places, and cuts anything else down to
+            // at most that many cells before it is padded
+            let precision = match (spec.precision, &spec.precision_arg) {
+                (None, Some(key)) => Some(count_arg(
+                    args,
+                    key,
+                    &spec.text,
+                    "a precision",
+                    MAX_PRECISION,
+                )?),
+                (precision, _) => precision.or(next_precision),
+            };
+            let (shown, numeric) = match self.dialect {
+                Dialect::Default => {
+                    let shown = Self::shown(&insert, spec, precision)?;
+                    let numeric = match spec.ty {
+                        FormatType::Display => number::parse_float(&insert).is_some(),
+                        FormatType::Debug => false,
+                        _ => true,
+                    };
+                    (shown, numeric)
+                }
+                Dialect::Rust => dialect::rust_value(&insert, spec, precision)?,
+                Dialect::Python => dialect::python_value(&insert, spec, precision)?,
+            };
+            // Integers in other bases already have their sign, adding it again does nothing
+            let shown = if spec.plus {
+                number::with_plus(&shown)
+            } else {
+                shown
+            };
+            let lines = self.pad_lines && shown.contains('\n');
+            let width = match (spec.width, &spec.width_arg) {
+                (Some(w), _) => w,
+                (None, Some(key)) => count_arg(args, key, &spec.text, "a width", MAX_WIDTH)?,
+                (None, None) if spec.width_term => crate::term::width(),
+                (None, None) if lines => shown
+                    .split('\n')
+                    .map(crate::ansi::text_width)
+                    .max()
+                    .unwrap_or(0),
+                (None, None) => crate::ansi::text_width(shown.as_str()),
             };
-            let align = spec.align;
-            let prepared = Self::prepare_string(insert.as_str(), align, width);
+            // Like in rust, zero padding takes the place of the fill and alignment of numbers
+            let prepared = if lines {
+                shown
+                    .split('\n')
+                    .map(|line| Self::prepare_filled(line, spec.align, width, spec.fill))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            } else if self.dialect == Dialect::Python {
+                dialect::python_pad(&shown, spec, width, numeric)
+            } else if spec.zero && numeric {
+                number::zero_pad(&shown, width)
+            } else if self.dialect == Dialect::Rust && spec.ty == FormatType::Debug && !numeric {
+                // Rust's `Debug` for strings ignores the width
+                shown
+            } else {
+                let align = match self.dialect {
+                    Dialect::Rust if numeric && !spec.aligned => Alignment::Right,
+                    _ => spec.align,
+                };
+                Self::prepare_filled(shown.as_str(), align, width, spec.fill)
+            };
+            // Styles cover the padding too, and are left out entirely when color is disabled
+            let style = style::merge(spec.style, picked);
+            let prepared = style.paint(&prepared, self.color);
 
-            mods.push((prepared, spec.fmt_pos));
+            subs.push(Substitution {
+                spec: spec.text.clone(),
+                src_pos: spec.src_pos,
+                arg,
+                value,
+                filtered: insert,
+                prepared,
+            });
         }
 
-        let mut output = self.fmt_str.clone();
-        for (insert, pos) in mods.iter().rev() {
-            if !output.is_char_boundary(*pos) {
-                panic!("position {} is not a char boundary for output string {} (attempting to insert {})", pos, output, insert);
-            }
+        if self.highlight {
+            Self::highlight_subs(&mut subs, self.color);
+        }
+        Ok(subs)
+    }
 
-            output.insert_str(*pos, insert);
+    /// Underlines every substitution in `subs` that shows anything, giving each the next color of
+    /// [`HIGHLIGHTS`]. Their own styles are kept, so a spec like `{:red}` stays red but is
+    /// underlined all the same.
+    fn highlight_subs(subs: &mut [Substitution], level: ColorLevel) {
+        let shown = subs.iter_mut().filter(|sub| !sub.prepared.is_empty());
+        for (sub, fg) in shown.zip(HIGHLIGHTS.iter().cycle()) {
+            let marker = Style {
+                fg: Some(*fg),
+                underline: true,
+                ..Style::default()
+            };
+            sub.prepared = marker.paint(&sub.prepared, level);
         }
+    }
+
+    /// The unnamed args from position `from` on that no spec asks for by number.
+    fn splat<'a>(&self, args: &'a FormatArgs, from: usize) -> Vec<&'a str> {
+        let numbered = self
+            .fmt_spec
+            .iter()
+            .filter_map(|s| s.arg_num)
+            .collect::<Vec<_>>();
+        args.iter()
+            .filter(|a| a.pos() >= from && a.name().is_none() && !numbered.contains(&a.pos()))
+            .map(FormatArg::value)
+            .collect()
+    }
 
-        Ok(output)
+    /// Looks up the value of the named arg `name`, falling back to the builtin placeholders.
+    fn named(args: &FormatArgs, name: &str, option: Option<&str>) -> Option<String> {
+        let count = (name == crate::builtin::ARG_COUNT).then(|| args.len().to_string());
+        count
+            .or_else(|| args.get_named(name).cloned())
+            .or_else(|| crate::builtin::value(name, option))
+    }
+
+    /// Evaluates the expression of `spec`, with its args looked up by position or name.
+    fn evaluate(spec: &FormatSpec, expression: &Expr, args: &FormatArgs) -> Result<String> {
+        let mut arg = |key: &str| {
+            let value = match key.parse::<usize>() {
+                Ok(num) => args
+                    .get(num)
+                    .cloned()
+                    .ok_or_else(|| Error::bad_arg_num(num, args.len()))?,
+                Err(_) => Self::named(args, key, None).ok_or_else(|| Error::bad_arg_name(key))?,
+            };
+            number::parse_float(&value)
+                .ok_or_else(|| Error::bad_arg_value(&value, &spec.text, "a number"))
+        };
+        expression.eval(&mut arg).map(number::show)
+    }
+
+    /// Renders the body of a conditional spec with the same args if its arg has a value that isn't
+    /// blank, and nothing otherwise.
+    fn conditional(
+        &self,
+        spec: &FormatSpec,
+        body: &str,
+        args: &FormatArgs,
+    ) -> Result<Substitution> {
+        let name = spec.arg_name.clone().unwrap_or_default();
+        let value = args
+            .get_named(&name)
+            .cloned()
+            .or_else(|| crate::builtin::value(&name, None))
+            .unwrap_or_default();
+        let prepared = if value.trim().is_empty() {
+            String::new()
+        } else {
+            let mut inner = Self::parse(body, self.dialect)?;
+            inner.color = self.color;
+            let subs = inner.resolve_args(args)?;
+            inner.assemble(subs)
+        };
+
+        Ok(Substitution {
+            spec: spec.text.clone(),
+            src_pos: spec.src_pos,
+            arg: name,
+            filtered: value.clone(),
+            value,
+            prepared,
+        })
+    }
+
+    /// Renders `insert` with everything `spec` asks for except padding, treating it as a number
+    /// only when the spec needs one. A precision leaves values that aren't finite numbers alone,
+    /// whether they are written as one (like `1e400`) or as a word (like `nan` or `inf`).
+    fn shown(insert: &str, spec: &FormatSpec, precision: Option<usize>) -> Result<String> {
+        let shown = match (spec.ty, precision) {
+            (FormatType::Display, None) => insert.to_string(),
+            (FormatType::Display, Some(p)) => match number::parse_float(insert) {
+                Some(n) if n.is_finite() => number::fixed(n, p),
+                _ if insert.trim().parse::<f64>().is_ok_and(|n| !n.is_finite()) => {
+                    insert.to_string()
+                }
+                _ => crate::ansi::truncate(insert, p, ""),
+            },
+            (FormatType::Debug, _) => format!("{:?}", insert),
+            (ty @ (FormatType::LowerExp | FormatType::UpperExp), p) => {
+                match number::parse_float(insert) {
+                    Some(n) => number::exponent(n, ty == FormatType::UpperExp, p),
+                    None => return Err(Error::bad_arg_value(insert, &spec.text, "a number")),
+                }
+            }
+            (FormatType::Percent, p) => match number::parse_float(insert) {
+                Some(n) => number::percent(n, p),
+                None => return Err(Error::bad_arg_value(insert, &spec.text, "a number")),
+            },
+            (ty, _) => number::integer(insert, ty, spec.alternate, spec.plus)
+                .ok_or_else(|| Error::bad_arg_value(insert, &spec.text, "an integer"))?,
+        };
+        Ok(shown)
     }
 
     pub fn prepare_string(s: &str, align: Alignment, width: usize) -> String {
-        let str_size = UnicodeWidthStr::width(s);
+        Self::prepare_filled(s, align, width, ' ')
+    }
+
+    /// Like [`Formatter::prepare_string`], but pads with `fill` instead of spaces.
+    pub fn prepare_filled(s: &str, align: Alignment, width: usize, fill: char) -> String {
+        let str_size = crate::ansi::text_width(s);
         if str_size == width {
             return s.to_string();
         }
@@ -149,7 +601,7 @@ impl Formatter {
         let mut output = String::with_capacity(width);
 
         if width > str_size {
-            let pad_char = ' ';
+            let pad_char = fill;
             let pad_count = width - str_size;
             match align {
                 Alignment::Left => {
@@ -242,7 +694,6 @@ impl Formatter {
             panic!("\nInput string contains one of the left or right placeholders! \n\tInput string is '{}'. \n\t{}. \n\t{}.", s, l_msg, r_msg);
         }
 
-        let mut locs = format_regex().capture_locations();
         let mut pos = 0usize;
         let mut spec_num = 0usize;
         let mut specs = Vec::new();
@@ -255,19 +706,19 @@ impl Formatter {
         // are calculated. I specifically picked two characters (\u{1} and \u{2}) because they are the
         // same width as a single bracket so the calculations will be correct, and they do not show up
         // as anything so they are unlikely to be used.
-        let mut fmt_str = s
-            .replace("{{", LEFT_PLACEHOLDER)
-            .replace("}}", RIGHT_PLACEHOLDER);
-
-        while let Some(mat) = format_regex().captures_read_at(&mut locs, &fmt_str, pos) {
-            let (start, end) = locs
-                .get(0)
-                .expect("Unable to get group 0 on CaptureLocations");
+        let mut fmt_str = replace_escapes(s, '\u{1}', '\u{2}');
+
+        while let Some(range) = find_spec(&fmt_str, pos) {
+            let (start, end) = (range.start, range.end);
+            let spec_str = &fmt_str[range];
             spec_ranges.push(start..end);
             pos = end;
-            let spec = FormatSpec::new(start - removed, spec_num, mat.as_str())?;
+            let mut spec = FormatSpec::new(start - removed, spec_num, spec_str)?;
+            // Every escaped bracket before the spec was shortened to a single placeholder character
+            let escapes = fmt_str[..start].matches(['\u{1}', '\u{2}']).count();
+            spec.src_pos = start + escapes;
             spec_num += 1;
-            removed += mat.as_str().len();
+            removed += spec_str.len();
             specs.push(spec);
         }
 
@@ -290,10 +741,34 @@ impl Formatter {
     }
 }
 
+/// Looks up the arg `key` (a position or a name) that a spec takes a count like its width from.
+/// Looks up the arg holding a width or precision, which must be a count no larger than `max`.
+fn count_arg(args: &FormatArgs, key: &str, spec: &str, what: &str, max: usize) -> Result<usize> {
+    let value = match key.parse::<usize>() {
+        Ok(n) => args
+            .get(n)
+            .ok_or_else(|| Error::bad_arg_num(n, args.len()))?,
+        Err(_) => args
+            .get_named(key)
+            .ok_or_else(|| Error::bad_arg_name(key))?,
+    };
+    match value.trim().parse::<usize>() {
+        Ok(n) if n <= max => Ok(n),
+        Ok(_) => Err(Error::bad_arg_value(
+            value,
+            spec,
+            &format!("{} of at most {}", what, max),
+        )),
+        Err(_) => Err(Error::bad_arg_value(value, spec, what)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+    #[cfg(feature = "unicode")]
+    use unicode_width::UnicodeWidthStr;
     // Lets try , , , and .
     #[test]
     fn generate() {
@@ -305,6 +780,357 @@ mod tests {
         assert_eq!(output, "Lets try one, two, three, and one.");
     }
 
+    #[test]
+    fn filled() {
+        let output = Formatter::format("|{:*^10}|{:->4}|{:.<4}|", &["hi", "1", "2"]).unwrap();
+        assert_eq!(output, "|****hi****|---1|2...|");
+    }
+
+    #[test]
+    fn rounding() {
+        let output = Formatter::format("pi = {:.2}", &["3.14159"]).unwrap();
+        assert_eq!(output, "pi = 3.14");
+        let output = Formatter::format("[{:>6.1}] [{:.0}] [{:.3}]", &["-2.25", "7.5", "12"]);
+        assert_eq!(output.unwrap(), "[  -2.2] [8] [12.000]");
+        let output =
+            Formatter::format("{:.2} {:.2} {:.2} {:.2}", &["nan", "-inf", "1e400", "info"]);
+        assert_eq!(output.unwrap(), "nan -inf 1e400 in");
+    }
+
+    #[test]
+    fn bases() {
+        let output = Formatter::format("{:#x} {:#o} {:#b} {0:x}", &["42", "42", "42"]);
+        assert_eq!(output.unwrap(), "0x2a 0o52 0b101010 2a");
+        let output = Formatter::format("{0:x} {0:X} {0:b} {0:o}", &["255"]);
+        assert_eq!(output.unwrap(), "ff FF 11111111 377");
+        let output = Formatter::format("[{:>#8x}] [{:*<#6o}]", &["255", "8"]);
+        assert_eq!(output.unwrap(), "[    0xff] [0o10**]");
+        assert_eq!(
+            Formatter::format("{:x}", &["4.5"]).unwrap_err().to_string(),
+            "Invalid argument value: {:x} expects an integer, but was given '4.5'"
+        );
+    }
+
+    #[test]
+    fn scientific() {
+        let output = Formatter::format("{:.3e} {:E} {:+e}", &["123456.789", "0.5", "1500"]);
+        assert_eq!(output.unwrap(), "1.235e5 5E-1 +1.5e3");
+        assert!(Formatter::format("{:e}", &["many"]).is_err());
+    }
+
+    #[test]
+    fn width_args() {
+        crate::term::set_width(crate::term::DEFAULT_WIDTH);
+        let output = Formatter::format("[{name:{w}}]", &["name = tony", "w = 6"]);
+        assert_eq!(output.unwrap(), "[tony  ]");
+        let output = Formatter::format("[{0:>{1}}] {{x}}", &["ab", "4"]);
+        assert_eq!(output.unwrap(), "[  ab] {x}");
+        assert!(Formatter::format("{:{w}}", &["a", "w = wide"]).is_err());
+        assert!(Formatter::format("{:{w}}", &["a"]).is_err());
+        let output = Formatter::format("{:{w}}", &["a", "w = 18446744073709551615"]);
+        assert!(matches!(output, Err(Error::InvalidArgValue(_))));
+        let output = Formatter::format("{:>term}", &["ab"]).unwrap();
+        assert_eq!(output.len(), crate::term::DEFAULT_WIDTH);
+        assert!(output.ends_with(" ab"));
+    }
+
+    #[test]
+    fn precision_args() {
+        let output = Formatter::format("{:.{p}}", &["3.14159", "p = 3"]);
+        assert_eq!(output.unwrap(), "3.142");
+        let output = Formatter::format("[{0:>{1}.{2}}]", &["truncated", "6", "4"]);
+        assert_eq!(output.unwrap(), "[  trun]");
+        assert!(Formatter::format("{:.{p}}", &["1.5", "p = -1"]).is_err());
+        let output = Formatter::format("{:.{p}}", &["1.5", "p = 99999999999"]);
+        assert!(matches!(output, Err(Error::InvalidArgValue(_))));
+        let output = Formatter::format("{:.*}", &["70000", "1.5"]);
+        assert!(matches!(output, Err(Error::InvalidArgValue(_))));
+    }
+
+    #[test]
+    fn debug() {
+        let output = Formatter::format("{:?} {:?}", &["tab\there", "say \"hi\"\n\x1b[1m"]);
+        assert_eq!(output.unwrap(), r#""tab\there" "say \"hi\"\n\u{1b}[1m""#);
+        let output = Formatter::format("[{:>6?}]", &["a"]);
+        assert_eq!(output.unwrap(), "[   \"a\"]");
+    }
+
+    #[test]
+    fn conditionals() {
+        const STATUS: &str = "build{?branch: on {branch}}{?dirty: ({dirty} changed)}";
+        let output = Formatter::format(STATUS, &["branch = main", "dirty = 3"]);
+        assert_eq!(output.unwrap(), "build on main (3 changed)");
+        let output = Formatter::format(STATUS, &["branch = main", "dirty = "]);
+        assert_eq!(output.unwrap(), "build on main");
+        let output = Formatter::format(STATUS, &["x"]);
+        assert_eq!(output.unwrap(), "build");
+        let f = Formatter::new("{?name: on {name}}|").unwrap();
+        assert_eq!(f.generate::<&str>(&[]).unwrap(), "|");
+        let output = Formatter::format("{}{?n: and {n:>3}} {{x}}", &["a", "n = 7"]);
+        assert_eq!(output.unwrap(), "a and   7 {x}");
+        assert!(Formatter::new("{?a:{:q}}").is_err());
+    }
+
+    #[test]
+    fn splats() {
+        let output = Formatter::format("files: {*:, }", &["a.txt", "b.txt", "c.txt"]);
+        assert_eq!(output.unwrap(), "files: a.txt, b.txt, c.txt");
+        let output = Formatter::format("{} then {*}", &["a", "b", "c"]);
+        assert_eq!(output.unwrap(), "a then b c");
+        let output = Formatter::format("{0}: {*:|} ({name})", &["x", "y", "z", "name = n"]);
+        assert_eq!(output.unwrap(), "x: y|z (n)");
+        assert_eq!(Formatter::format("[{}{*}]", &["a"]).unwrap(), "[a]");
+        assert!(Formatter::format("{*} {}", &["a", "b"]).is_err());
+    }
+
+    #[test]
+    fn expressions() {
+        let output = Formatter::format(
+            "{=2*{0}+1} {= {price} * {qty} :.2} {}",
+            &["4", "price = 2.5", "qty = 3"],
+        );
+        assert_eq!(output.unwrap(), "9 7.50 4");
+        let output = Formatter::format("{=({0}-{1})/{1}:+.1%}|{=10/4:>5}", &["120", "100"]);
+        assert_eq!(output.unwrap(), "+20.0%|  2.5");
+        assert_eq!(Formatter::format("{={#}*2}", &["a", "b"]).unwrap(), "4");
+        assert!(Formatter::format("{={0}+1}", &["x"]).is_err());
+        assert!(Formatter::format("{={1}}", &["1"]).is_err());
+        assert!(Formatter::format("{=1/{0}}", &["0"]).is_err());
+    }
+
+    #[test]
+    fn needs_args() {
+        for fmt_str in [
+            "{=1+2}",
+            "{now:%Y} {=2*{#}:>3}",
+            "no specs",
+            "{?name: on {name}}|",
+            "{?user:{user}@{hostname}}",
+        ] {
+            assert!(
+                !Formatter::new(fmt_str).unwrap().needs_args(),
+                "{}",
+                fmt_str
+            );
+        }
+        for fmt_str in ["{}", "{now} {name}", "{=2*{0}}", "{=1+{x}}", "{?user: {0}}"] {
+            assert!(Formatter::new(fmt_str).unwrap().needs_args(), "{}", fmt_str);
+        }
+    }
+
+    #[test]
+    fn percentages() {
+        let output = Formatter::format(
+            "{:%}|{:.1%}|{:>7.2%}|{:+.0%}",
+            &["0.5", "0.42", "0.125", "1"],
+        );
+        assert_eq!(output.unwrap(), "50%|42.0%| 12.50%|+100%");
+        assert!(Formatter::format("{:%}", &["half"]).is_err());
+        assert!(Formatter::with_dialect("{:%}", Dialect::Rust).is_err());
+    }
+
+    #[test]
+    fn filters() {
+        let output = Formatter::format(
+            "{name:upper} {1:title} {:snake:>12}|",
+            &["FirstName", "ada lovelace", "name = ada"],
+        );
+        assert_eq!(output.unwrap(), "ADA Ada Lovelace   first_name|");
+        assert_eq!(
+            Formatter::format("{user:camel}", &["user = x-y"]).unwrap(),
+            "xY"
+        );
+    }
+
+    #[test]
+    fn styles() {
+        let mut f = Formatter::new("{:red:>4}|{name:bright_green}{?name:({name:blue})}").unwrap();
+        f.color = ColorLevel::Basic;
+        let output = f.generate(&["x", "name = y"]).unwrap();
+        assert_eq!(
+            output,
+            "\x1b[31m   x\x1b[0m|\x1b[92my\x1b[0m(\x1b[34my\x1b[0m)"
+        );
+        f.color = ColorLevel::None;
+        assert_eq!(f.generate(&["x", "name = y"]).unwrap(), "   x|y(y)");
+    }
+
+    #[test]
+    fn padded_lines() {
+        let mut f = Formatter::new("[{:^5}] [{}] [{:>2}]").unwrap();
+        let args = ["ab\nc", "one\nthree", "x\nlong"];
+        f.pad_lines = true;
+        assert_eq!(
+            f.generate(&args).unwrap(),
+            "[ ab  \n  c  ] [one  \nthree] [ x\nng]"
+        );
+    }
+
+    #[test]
+    fn highlighting() {
+        let f = Formatter::new("{} and {?x:no}{} and {}").unwrap();
+        let mut subs = f.substitutions(&["a", "b", "c"]).unwrap();
+        Formatter::highlight_subs(&mut subs, ColorLevel::Basic);
+        let prepared = subs.iter().map(|s| s.prepared.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            prepared,
+            vec![
+                "\x1b[4;96ma\x1b[0m",
+                "",
+                "\x1b[4;95mb\x1b[0m",
+                "\x1b[4;93mc\x1b[0m"
+            ]
+        );
+        Formatter::highlight_subs(&mut subs, ColorLevel::None);
+        assert_eq!(subs[0].prepared, "\x1b[4;96ma\x1b[0m");
+    }
+
+    #[test]
+    fn arg_count() {
+        let output = Formatter::format("processing {#} items: {*}", &["a", "b", "c"]);
+        assert_eq!(output.unwrap(), "processing 3 items: a b c");
+        let output = Formatter::format("[{#:>3}] {}", &["a", "n = 1"]);
+        assert_eq!(output.unwrap(), "[  2] a");
+        assert_eq!(Formatter::format("{#}", &[]).unwrap(), "0");
+    }
+
+    #[test]
+    fn zero_padding() {
+        let output = Formatter::format(
+            "{:05}|{:+06.1}|{:#010x}|{:<05}",
+            &["42", "-2.25", "255", "7"],
+        );
+        assert_eq!(output.unwrap(), "00042|-002.2|0x000000ff|00007");
+        assert_eq!(Formatter::format("[{:05}]", &["ab"]).unwrap(), "[ab   ]");
+    }
+
+    /// Renders the literals with `--rust-fmt` and checks that `format!` renders them the same
+    macro_rules! assert_rust_fmt {
+        ($fmt:literal $(, $arg:expr)*) => {
+            let args = [$(stringify!($arg).trim_matches('"')),*];
+            let f = Formatter::with_dialect($fmt, Dialect::Rust).unwrap();
+            assert_eq!(f.generate(&args).unwrap(), format!($fmt $(, $arg)*), "{}", $fmt);
+        };
+    }
+
+    #[test]
+    fn rust_parity() {
+        assert_rust_fmt!("[{:5}|{:<5}|{:^5}|{:>5}]", 42, 42, 42, "ab");
+        assert_rust_fmt!("[{:5}|{:*^7}|{:.3}|{:>6.2}]", "ab", 1.5, "abcdef", "xyz");
+        assert_rust_fmt!("[{:05}|{:+05}|{:<05}|{:^+06}|{:05}]", 42, 42, -7, 3, "ab");
+        assert_rust_fmt!("[{:#06x}|{:#010b}|{:08.3}|{:+}]", 42, 5, -1.5, "s");
+        assert_rust_fmt!("[{:x}|{:#X}|{:o}|{:#b}]", 255, 255, 8, 5);
+        assert_rust_fmt!("[{:.2}|{:.0}|{:.3}|{}|{}]", 1.23456, 2.5, 12, 1.0, 1e3);
+        assert_rust_fmt!("[{:?}|{:?}|{:?}|{:>8?}|{:.1?}]", 1.0, 42, "say", "a", 0.25);
+        assert_rust_fmt!(
+            "[{:e}|{:E}|{:.2e}|{:+e}|{:e}]",
+            1500,
+            0.00042,
+            123456.789,
+            1.5,
+            7
+        );
+        assert_rust_fmt!("[{:1$}] [{:.*}] [{0:>1$}]", "ab", 5, 2.0);
+        assert_rust_fmt!("[{:+.1}|{:+}|{:+?}|{:+08.2e}]", 0.25, 0, -3, 1500.0);
+
+        let f = Formatter::with_dialect("[{:>w$.p$}]", Dialect::Rust).unwrap();
+        let output = f.generate(&["1.23456", "w = 7", "p = 2"]).unwrap();
+        assert_eq!(output, format!("[{:>w$.p$}]", 1.23456, w = 7, p = 2));
+        let f = Formatter::with_dialect("{:x}", Dialect::Rust).unwrap();
+        assert!(f.generate(&["1.5"]).is_err());
+        assert!(Formatter::with_dialect("{:.70000}", Dialect::Rust).is_err());
+        let f = Formatter::with_dialect("{:.*}", Dialect::Rust).unwrap();
+        assert!(f.generate(&["70000", "1.5"]).is_err());
+    }
+
+    fn python(fmt_str: &str, args: &[&str]) -> crate::Result<String> {
+        Formatter::with_dialect(fmt_str, Dialect::Python)?.generate(args)
+    }
+
+    #[test]
+    fn python_parity() {
+        // Expected output is what python 3 renders for the same format string and values
+        let output = python(
+            "[{0!r:>10}] [{1:>{width}}] [{2:,}]",
+            &["abc", "tony", "1234567", "width = 6"],
+        );
+        assert_eq!(output.unwrap(), "[     'abc'] [  tony] [1,234,567]");
+        let output = python(
+            "[{:,.2f}|{:_}|{:_x}|{:#X}|{:#o}]",
+            &["1234567.891", "1000000", "3735928559", "255", "8"],
+        );
+        assert_eq!(
+            output.unwrap(),
+            "[1,234,567.89|1_000_000|dead_beef|0XFF|0o10]"
+        );
+        let output = python(
+            "[{:=+8.2f}|{:08.3f}|{: d}|{:*^9,}|{:<05}]",
+            &["3.14159", "-1.5", "42", "12345", "42"],
+        );
+        assert_eq!(output.unwrap(), "[+   3.14|-001.500| 42|*12,345**|42000]");
+        let output = python(
+            "[{}|{}|{}|{:.3}|{:.3}|{:.3}]",
+            &["1.0", "1e16", "1e-05", "1.23456", "123.0", "1.0"],
+        );
+        assert_eq!(output.unwrap(), "[1.0|1e+16|1e-05|1.23|1.23e+02|1.0]");
+        let output = python(
+            "[{:e}|{:.2E}|{:g}|{:g}|{:.3g}|{:%}|{:.1%}]",
+            &[
+                "1500", "0.00042", "1234.5", "1e-05", "123456", "0.25", "0.125",
+            ],
+        );
+        assert_eq!(
+            output.unwrap(),
+            "[1.500000e+03|4.20E-04|1234.5|1e-05|1.23e+05|25.000000%|12.5%]"
+        );
+        let output = python(
+            "[{!r}|{!a}|{!r}|{!s:>4}|{:c}|{:5}|{:.2}|{:05}]",
+            &["it's", "café", "1.5", "x", "65", "ab", "abc", "ab"],
+        );
+        assert_eq!(
+            output.unwrap(),
+            r#"["it's"|'caf\xe9'|1.5|   x|A|ab   |ab|ab000]"#
+        );
+        let output = python(
+            "[{:=#10x}|{:+}|{:>6}|{:^7.1f}]",
+            &["255", "-0.0", "7", "2.25"],
+        );
+        assert_eq!(output.unwrap(), "[0x      ff|-0.0|     7|  2.2  ]");
+
+        assert!(python("{:.2}", &["5"]).is_err());
+        assert!(python("{:+}", &["text"]).is_err());
+        assert!(python("{0} {}", &["a"]).is_err());
+        assert!(python("{:?}", &["a"]).is_err());
+        assert!(python("{:.70000f}", &["1.5"]).is_err());
+        assert!(python("{:.{p}e}", &["1.5", "p = 70000"]).is_err());
+        assert!(Formatter::new("{:,}").is_err());
+        assert!(Formatter::new("{0!r}").is_err());
+    }
+
+    #[test]
+    fn signs() {
+        let output = Formatter::format(
+            "{:+} {:+} {:+.1} {:+} {:>+4}",
+            &["5", "-5", "0.25", "+7", "x"],
+        );
+        assert_eq!(output.unwrap(), "+5 -5 +0.2 +7    x");
+    }
+
+    #[test]
+    fn precision() {
+        let output = Formatter::format(
+            "[{:.5}] [{:>8.3}] [{:.9}]",
+            &["truncate me", "abcdef", "short"],
+        );
+        assert_eq!(output.unwrap(), "[trunc] [     abc] [short]");
+        #[cfg(feature = "unicode")]
+        assert_eq!(Formatter::format("{:.3}", &["读文读"]).unwrap(), "读");
+        assert_eq!(
+            Formatter::format("{:.2}", &["\x1b[1mbold\x1b[0m"]).unwrap(),
+            "\x1b[1mbo\x1b[0m\x1b[0m"
+        );
+    }
+
     #[test]
     fn format() {
         const INPUT: &str = "Lets try {0}, {1}, {2}, and {}.";
@@ -326,6 +1152,65 @@ mod tests {
         assert_eq!(output, "Let the motherfucking beat drop.");
     }
 
+    #[test]
+    fn display() {
+        let f = Formatter::new("{{x}} {} {name:>4}!").unwrap();
+        let args = ["a", "name = bob"];
+        assert_eq!(f.display(&args).to_string(), f.generate(&args).unwrap());
+        assert_eq!(format!("<{}>", f.display(&args)), "<{x} a  bob!>");
+
+        let mut out = String::new();
+        assert!(std::fmt::write(&mut out, format_args!("{}", f.display(&["a"]))).is_err());
+    }
+
+    #[test]
+    fn batches() {
+        let f = Formatter::new("{name}: {:>3}").unwrap();
+        let rows = [["7", "name = a"], ["42", "name = b"]]
+            .into_iter()
+            .map(|row| row.into_iter().enumerate().collect::<FormatArgs>())
+            .collect::<Vec<_>>();
+        let lines = f
+            .format_iter(rows.clone())
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(lines, vec!["a:   7", "b:  42"]);
+
+        let mut out = Vec::new();
+        assert_eq!(f.write_iter(&mut out, rows).unwrap(), 2);
+        assert_eq!(String::from_utf8(out).unwrap(), "a:   7\nb:  42\n");
+
+        let bad = vec![["7"].into_iter().enumerate().collect::<FormatArgs>()];
+        assert!(f.format_iter(bad).next().unwrap().is_err());
+    }
+
+    #[test]
+    fn substitutions() {
+        let f = Formatter::new("{{x}} {name:>4} {} {0:upper:^5}").unwrap();
+        let subs = f.substitutions(&["a", "name = bob"]).unwrap();
+        let table = subs
+            .iter()
+            .map(|s| {
+                (
+                    s.spec.as_str(),
+                    s.src_pos,
+                    s.arg.as_str(),
+                    s.value.as_str(),
+                    s.filtered.as_str(),
+                    s.prepared.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            table,
+            vec![
+                ("{name:>4}", 6, "name", "bob", "bob", " bob"),
+                ("{}", 16, "#0", "a", "a", "a"),
+                ("{0:upper:^5}", 19, "#0", "a", "A", "  A  "),
+            ]
+        );
+    }
+
     #[test]
     fn multi1() {
         // cargo run -- "lets {test} some {} up {}. hell {:^8}" "fuck" "❤️🧡❤️" "FUCKING YES BRO AMIRITE" "test = bro"
@@ -355,6 +1240,31 @@ mod tests {
         let _ = Formatter::new(format!("Here is my {} very bad string", "\u{1}").as_str());
     }
 
+    #[test]
+    fn decoded_placeholders() {
+        use crate::encode::unicode_unescape;
+
+        for decoded in [unicode_unescape("{} \\u{1} {}"), unicode_unescape("\\u{2}")] {
+            assert!(check_placeholders(&decoded).is_err(), "{:?}", decoded);
+        }
+        assert!(check_placeholders(&unicode_unescape("{{ \\u{1F600} {} }}")).is_ok());
+    }
+
+    #[test]
+    fn echo_placeholders() {
+        use crate::encode::echo_unescape;
+
+        for decoded in [
+            echo_unescape("{} \\x01"),
+            echo_unescape("\\x2 {}"),
+            echo_unescape("\\0001"),
+            echo_unescape("\\02{}"),
+        ] {
+            assert!(check_placeholders(&decoded).is_err(), "{:?}", decoded);
+        }
+        assert!(check_placeholders(&echo_unescape("{}\\x03\\t\\\\x01")).is_ok());
+    }
+
     #[test]
     fn weirdo1() {
         const INPUT: &str = "Thats {} too many {4} bro.";
@@ -401,6 +1311,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "unicode")]
     fn prepare_string() {
         let string = "0123456789";
         let left20 = Formatter::prepare_string(string, Alignment::Left, 20);
@@ -5,18 +5,96 @@
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
 mod arg;
+pub mod builtin;
+pub mod dialect;
 mod error;
+mod expr;
+mod filter;
 mod formatter;
+mod hash;
+pub mod number;
 mod spec;
+pub mod style;
 
 pub use arg::{FormatArg, FormatArgs};
+pub use dialect::Dialect;
 pub use error::{Error, Result};
-pub use formatter::Formatter;
-pub use spec::{Alignment, FormatSpec};
+pub use filter::Filter;
+pub use formatter::{check_placeholders, highlight, pad_lines, Formatted, Formatter, Substitution};
+pub use spec::{set_fill, Alignment, FormatSpec, FormatType, MAX_PRECISION, MAX_WIDTH};
 
+use std::ops::Range;
+
+#[cfg(feature = "regex")]
 use once_cell::sync::OnceCell;
+#[cfg(feature = "regex")]
 use regex::Regex;
 
+/// Finds the next spec in `s` at or after byte `from`: an opening bracket up to the nearest closing
+/// bracket on the same line. Escaped brackets must already have been replaced. After a colon (or
+/// an `=` starting an expression), brackets nest, so args used in the spec itself (like `{:{w}}`)
+/// are part of it.
+///
+/// This does the same as matching [`spec_regex_brackets_only`] used to, but without having to
+/// compile a regex on every run, which was most of the startup time.
+pub(crate) fn find_spec(s: &str, from: usize) -> Option<Range<usize>> {
+    let mut start = from;
+    'open: while let Some(open) = s[start..].find('{').map(|i| start + i) {
+        start = open + 1;
+        let mut depth = 1usize;
+        // Expressions like `{=2*{0}}` have nested brackets from the start
+        let mut colon = s[open + 1..].starts_with('=');
+        for (i, c) in s[open + 1..].char_indices() {
+            match c {
+                ':' => colon = true,
+                '{' if colon => depth += 1,
+                '}' if depth == 1 => return Some(open..open + 1 + i + 1),
+                '}' => depth -= 1,
+                '\n' => continue 'open,
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+/// Replaces escaped brackets (`{{` and `}}`) outside of specs with `left` and `right`, so they
+/// can't be mistaken for the brackets of a spec. Brackets inside of a spec are left alone, so the
+/// end of `{:{w}}` isn't taken for an escape.
+pub(crate) fn replace_escapes(s: &str, left: char, right: char) -> String {
+    let mut output = String::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    // How deeply nested in a spec the current character is, and whether that spec had a colon yet
+    let mut depth = 0usize;
+    let mut colon = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if depth == 0 && chars.peek() == Some(&'{') => {
+                chars.next();
+                output.push(left);
+                continue;
+            }
+            '}' if depth == 0 && chars.peek() == Some(&'}') => {
+                chars.next();
+                output.push(right);
+                continue;
+            }
+            '{' if depth == 0 => {
+                depth = 1;
+                colon = chars.peek() == Some(&'=');
+            }
+            '{' if colon => depth += 1,
+            ':' => colon = true,
+            '}' if depth > 0 => depth -= 1,
+            '\n' => depth = 0,
+            _ => {}
+        }
+        output.push(c);
+    }
+    output
+}
+
+#[cfg(feature = "regex")]
 pub fn spec_regex() -> &'static Regex {
     static REGEX: OnceCell<Regex> = OnceCell::new();
     REGEX.get_or_init(|| {
@@ -62,6 +140,7 @@ pub fn spec_regex() -> &'static Regex {
     })
 }
 
+#[cfg(feature = "regex")]
 pub fn spec_regex_simple() -> &'static Regex {
     static REGEX: OnceCell<Regex> = OnceCell::new();
     REGEX.get_or_init(|| {
@@ -69,6 +148,7 @@ pub fn spec_regex_simple() -> &'static Regex {
     })
 }
 
+#[cfg(feature = "regex")]
 pub fn spec_regex_brackets_only() -> &'static Regex {
     static REGEX: OnceCell<Regex> = OnceCell::new();
     // Match anything between brackets but as few as possible. Previously this was:
@@ -77,3 +157,54 @@ pub fn spec_regex_brackets_only() -> &'static Regex {
     // and closings in between
     REGEX.get_or_init(|| Regex::new(r"\{.{0,}?\}").expect("Failed to compile regex"))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn spec_scanning() {
+        let cases = [
+            ("a {} b", 0, Some(2..4)),
+            ("a {} b {name:>4}", 4, Some(7..16)),
+            ("{ {x}", 0, Some(0..5)),
+            ("{\n{x}", 0, Some(2..5)),
+            ("{ never closed", 0, None),
+            ("{:x}}", 0, Some(0..4)),
+            ("} {", 0, None),
+            ("", 0, None),
+        ];
+        for (s, from, expected) in cases {
+            assert_eq!(find_spec(s, from), expected, "scanning {:?}", s);
+            #[cfg(feature = "regex")]
+            assert_eq!(
+                spec_regex_brackets_only()
+                    .find_at(s, from)
+                    .map(|m| m.range()),
+                expected,
+                "regex on {:?}",
+                s
+            );
+        }
+
+        // Nested brackets are beyond the regex
+        let nested = [
+            ("a {:>{w}} b", 0, Some(2..9)),
+            ("{x:{0}.{p}}", 0, Some(0..11)),
+            ("{:{w}\n}", 0, Some(2..5)),
+        ];
+        for (s, from, expected) in nested {
+            assert_eq!(find_spec(s, from), expected, "scanning {:?}", s);
+        }
+    }
+
+    #[test]
+    fn escapes() {
+        let escape = |s| replace_escapes(s, '<', '>');
+        assert_eq!(escape("{{x}} {}"), "<x> {}");
+        assert_eq!(escape("{name:{w}}}}"), "{name:{w}}>");
+        assert_eq!(escape("{a}}}"), "{a}>");
+        assert_eq!(escape("{:x}\n{{"), "{:x}\n<");
+    }
+}
@@ -0,0 +1,420 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Args are text, but the ones that look like numbers are formatted like numbers when a spec asks
+//! for something only numbers have (like a precision).
+
+use super::FormatType;
+
+/// Parses `s` as a float if it is written like a decimal number: an optional sign, digits with at
+/// most one decimal point, and an optional exponent. Words rust would also accept (like `inf` or
+/// `nan`) are left alone, since they are much more likely to be text.
+pub fn parse_float(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let unsigned = s.strip_prefix(['+', '-']).unwrap_or(s);
+    let mantissa = match unsigned.find(['e', 'E']) {
+        Some(e) => &unsigned[..e],
+        None => unsigned,
+    };
+    if !mantissa.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if !unsigned
+        .chars()
+        .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+    {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Shows a computed number, like the result of an expression or a sum: integers without a decimal
+/// point, and anything else with at most 12 significant digits, so `0.1+0.2` shows as `0.3`. Results too large for a float show
+/// as `inf` or `-inf`, and those that aren't a number (like `0*2^2000`) as `NaN`.
+pub fn show(n: f64) -> String {
+    if !n.is_finite() {
+        return n.to_string();
+    }
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        return format!("{}", n as i64);
+    }
+    let digits = n.abs().log10().floor() as i32 + 1;
+    let decimals = (12 - digits).clamp(0, 15) as usize;
+    let shown = format!("{:.*}", decimals, n);
+    match shown.contains('.') {
+        true => shown
+            .trim_end_matches('0')
+            .trim_end_matches('.')
+            .to_string(),
+        false => shown,
+    }
+}
+
+/// Formats `n` rounded to `precision` decimal places.
+pub fn fixed(n: f64, precision: usize) -> String {
+    format!("{:.*}", precision, n)
+}
+
+/// Formats `n` in scientific notation the way rust does, with `precision` decimal places in the
+/// mantissa if given and as many as it takes otherwise.
+pub fn exponent(n: f64, upper: bool, precision: Option<usize>) -> String {
+    match (upper, precision) {
+        (false, Some(p)) => format!("{:.*e}", p, n),
+        (false, None) => format!("{:e}", n),
+        (true, Some(p)) => format!("{:.*E}", p, n),
+        (true, None) => format!("{:E}", n),
+    }
+}
+
+/// Formats `n` multiplied by 100 with a percent sign, with `precision` decimal places if given and
+/// as many as it takes (up to 6) otherwise.
+pub fn percent(n: f64, precision: Option<usize>) -> String {
+    let shown = match precision {
+        Some(p) => fixed(n * 100.0, p),
+        None => {
+            let shown = fixed(n * 100.0, 6);
+            shown
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        }
+    };
+    format!("{}%", shown)
+}
+
+/// Puts a `+` in front of `s` if it is a number (or a percentage) without a sign, leaving anything
+/// else alone.
+pub fn with_plus(s: &str) -> String {
+    let trimmed = s.trim_start();
+    let number = trimmed.strip_suffix('%').unwrap_or(trimmed);
+    if parse_float(number).is_some() && !trimmed.starts_with(['+', '-']) {
+        format!("+{}", trimmed)
+    } else {
+        s.to_string()
+    }
+}
+
+/// Pads the number `s` with zeros up to `width` cells, putting them after its sign and base prefix
+/// like rust's `0` flag does.
+pub fn zero_pad(s: &str, width: usize) -> String {
+    pad_after_sign(s, width, '0')
+}
+
+/// Pads the number `s` with `fill` up to `width` cells, putting the padding after its sign and
+/// base prefix.
+pub fn pad_after_sign(s: &str, width: usize, fill: char) -> String {
+    let len = s.chars().count();
+    if len >= width {
+        return s.to_string();
+    }
+    let sign = usize::from(s.starts_with(['+', '-', ' ']));
+    let prefix = match s[sign..].get(..2) {
+        Some("0x" | "0X" | "0o" | "0b") => 2,
+        _ => 0,
+    };
+    let (head, digits) = s.split_at(sign + prefix);
+    let padding = fill.to_string().repeat(width - len);
+    format!("{}{}{}", head, padding, digits)
+}
+
+/// Puts `sep` between every group of `every` digits of the integer `digits`, counting from the
+/// right.
+pub fn group(digits: &str, sep: char, every: usize) -> String {
+    let len = digits.chars().count();
+    let mut output = String::with_capacity(digits.len() + len / every);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (len - i).is_multiple_of(every) {
+            output.push(sep);
+        }
+        output.push(c);
+    }
+    output
+}
+
+/// Rewrites the exponent of rust's scientific notation the way python writes it, with a sign and
+/// at least two digits (`1.5e3` becomes `1.5e+03`).
+fn python_exponent_of(s: &str) -> String {
+    match s.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => {
+            let e = if s.contains('E') { 'E' } else { 'e' };
+            let (sign, digits) = match exp.strip_prefix('-') {
+                Some(digits) => ('-', digits),
+                None => ('+', exp),
+            };
+            format!("{}{}{}{:0>2}", mantissa, e, sign, digits)
+        }
+        None => s.to_string(),
+    }
+}
+
+/// Python's `repr` of a float: the shortest text that reads back as the same number.
+pub fn python_repr(n: f64) -> String {
+    python_exponent_of(&format!("{:?}", n))
+}
+
+/// Python's `e` and `E`, scientific notation with `precision` decimals in the mantissa.
+pub fn python_exponent(n: f64, upper: bool, precision: usize) -> String {
+    python_exponent_of(&exponent(n, upper, Some(precision)))
+}
+
+/// Python's `g` and `G`: `precision` significant digits, in fixed notation unless the exponent is
+/// below -4 or at least `precision`, without trailing zeros unless `alternate` is set. With
+/// `at_least_one_decimal`, it behaves like a float spec without a type instead, which keeps one
+/// decimal in fixed notation and switches to scientific notation one digit sooner.
+pub fn python_general(
+    n: f64,
+    upper: bool,
+    precision: usize,
+    alternate: bool,
+    at_least_one_decimal: bool,
+) -> String {
+    let p = precision.max(1);
+    let scientific = format!("{:.*e}", p - 1, n);
+    let exp = scientific
+        .split_once('e')
+        .and_then(|(_, e)| e.parse::<i64>().ok())
+        .unwrap_or(0);
+    let limit = if at_least_one_decimal { p - 1 } else { p } as i64;
+    let trim = |s: &str| -> String {
+        if alternate || !s.contains('.') {
+            s.to_string()
+        } else {
+            s.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+    };
+
+    if (-4..limit).contains(&exp) {
+        let fixed = trim(&format!("{:.*}", (p as i64 - 1 - exp).max(0) as usize, n));
+        if at_least_one_decimal && !fixed.contains('.') {
+            format!("{}.0", fixed)
+        } else {
+            fixed
+        }
+    } else {
+        let (mantissa, exp) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+        let e = if upper { 'E' } else { 'e' };
+        python_exponent_of(&format!("{}{}{}", trim(mantissa), e, exp))
+    }
+}
+
+/// Parses `s` as an integer, written in decimal or with a `0x`, `0o` or `0b` prefix for other
+/// bases, after an optional sign.
+pub fn parse_int(s: &str) -> Option<i128> {
+    let s = s.trim();
+    let (negative, unsigned) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let (radix, digits) = match unsigned.get(..2) {
+        Some("0x" | "0X") => (16, &unsigned[2..]),
+        Some("0o" | "0O") => (8, &unsigned[2..]),
+        Some("0b" | "0B") => (2, &unsigned[2..]),
+        _ => (10, unsigned),
+    };
+    // from_str_radix would accept another sign after the prefix
+    if digits.starts_with(['+', '-']) {
+        return None;
+    }
+    let n = i128::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -n } else { n })
+}
+
+/// Formats `s` as an integer in the base `ty` asks for, with a prefix showing the base when
+/// `alternate` is set and an explicit `+` when `plus` is. Negative numbers get a `-` in front of
+/// their magnitude rather than being shown in two's complement, since there is no type to say how
+/// many bits they have. Returns `None` if `s` isn't an integer.
+pub fn integer(s: &str, ty: FormatType, alternate: bool, plus: bool) -> Option<String> {
+    let n = parse_int(s)?;
+    let abs = n.unsigned_abs();
+    let (digits, prefix) = match ty {
+        FormatType::LowerHex => (format!("{:x}", abs), "0x"),
+        FormatType::UpperHex => (format!("{:X}", abs), "0x"),
+        FormatType::Octal => (format!("{:o}", abs), "0o"),
+        FormatType::Binary => (format!("{:b}", abs), "0b"),
+        _ => (abs.to_string(), ""),
+    };
+    let sign = match (n < 0, plus) {
+        (true, _) => "-",
+        (false, true) => "+",
+        (false, false) => "",
+    };
+    let prefix = if alternate { prefix } else { "" };
+    Some(format!("{}{}{}", sign, prefix, digits))
+}
+
+/// Formats `n` bytes in the largest unit it is at least one of, with `decimals` decimal places,
+/// like `1.5 MB`. `binary` units are powers of 1024 (`KiB`, `MiB`, ..) instead of 1000.
+pub fn bytes(n: f64, binary: bool, decimals: usize) -> String {
+    const DECIMAL: [&str; 7] = ["B", "kB", "MB", "GB", "TB", "PB", "EB"];
+    const BINARY: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let (base, units) = if binary {
+        (1024.0, BINARY)
+    } else {
+        (1000.0, DECIMAL)
+    };
+    let mut scaled = n;
+    let mut unit = 0;
+    // Rounding can carry a value up to the base, like 999.96 kB showing as 1000.0 kB
+    while unit + 1 < units.len()
+        && fixed(scaled.abs(), decimals).parse::<f64>().unwrap_or(0.0) >= base
+    {
+        scaled /= base;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{} B", n),
+        _ => format!("{} {}", fixed(scaled, decimals), units[unit]),
+    }
+}
+
+/// Formats `secs` seconds, rounded to whole seconds, as days, hours, minutes and seconds like
+/// `1d 2h 3m 4s`, leaving out the parts that are zero. A `clock` shows them like `1:02:03:04`
+/// instead, with the days only if there are any.
+pub fn duration(secs: f64, clock: bool) -> String {
+    let sign = if secs < 0.0 { "-" } else { "" };
+    let total = secs.abs().round() as u64;
+    let (days, hours, minutes, seconds) = (
+        total / 86_400,
+        total % 86_400 / 3600,
+        total % 3600 / 60,
+        total % 60,
+    );
+    if clock {
+        return match days {
+            0 => format!("{}{}:{:02}:{:02}", sign, hours, minutes, seconds),
+            _ => format!(
+                "{}{}:{:02}:{:02}:{:02}",
+                sign, days, hours, minutes, seconds
+            ),
+        };
+    }
+    let parts = [(days, 'd'), (hours, 'h'), (minutes, 'm'), (seconds, 's')]
+        .iter()
+        .filter(|(n, _)| *n > 0)
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect::<Vec<_>>();
+    match parts.is_empty() {
+        true => "0s".to_string(),
+        false => format!("{}{}", sign, parts.join(" ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn byte_sizes() {
+        assert_eq!(bytes(1_536_000.0, false, 1), "1.5 MB");
+        assert_eq!(bytes(1_536_000.0, true, 2), "1.46 MiB");
+        assert_eq!(bytes(999.0, false, 1), "999 B");
+        assert_eq!(bytes(1000.0, false, 1), "1.0 kB");
+        assert_eq!(bytes(1000.0, true, 1), "1000 B");
+        assert_eq!(bytes(999_960.0, false, 1), "1.0 MB");
+        assert_eq!(bytes(-2048.0, true, 0), "-2 KiB");
+        assert_eq!(bytes(0.0, false, 1), "0 B");
+        assert_eq!(bytes(1e21, false, 1), "1000.0 EB");
+    }
+
+    #[test]
+    fn percents() {
+        assert_eq!(percent(0.42, Some(1)), "42.0%");
+        assert_eq!(percent(0.42, None), "42%");
+        assert_eq!(percent(0.07, None), "7%");
+        assert_eq!(percent(0.125, None), "12.5%");
+        assert_eq!(percent(1.0 / 3.0, None), "33.333333%");
+        assert_eq!(percent(-0.5, Some(0)), "-50%");
+        assert_eq!(percent(12.0, None), "1200%");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(duration(93_784.0, false), "1d 2h 3m 4s");
+        assert_eq!(duration(93_784.0, true), "1:02:03:04");
+        assert_eq!(duration(3_604.0, false), "1h 4s");
+        assert_eq!(duration(3_604.0, true), "1:00:04");
+        assert_eq!(duration(59.6, false), "1m");
+        assert_eq!(duration(0.2, false), "0s");
+        assert_eq!(duration(0.0, true), "0:00:00");
+        assert_eq!(duration(-90.0, false), "-1m 30s");
+        assert_eq!(duration(-90.0, true), "-0:01:30");
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(parse_float("1.25"), Some(1.25));
+        assert_eq!(parse_float(" -2 "), Some(-2.0));
+        assert_eq!(parse_float("+1.5e3"), Some(1500.0));
+        assert_eq!(parse_float(".5"), Some(0.5));
+        assert_eq!(parse_float("inf"), None);
+        assert_eq!(parse_float("nan"), None);
+        assert_eq!(parse_float("e5"), None);
+        assert_eq!(parse_float("1.2.3"), None);
+        assert_eq!(parse_float("12px"), None);
+        assert_eq!(parse_float(""), None);
+
+        assert_eq!(fixed(1.23456, 2), "1.23");
+        assert_eq!(fixed(2.675, 2), "2.67");
+        assert_eq!(fixed(0.125, 2), "0.12");
+        assert_eq!(fixed(9.995, 1), "10.0");
+        assert_eq!(fixed(-1.5, 0), "-2");
+    }
+
+    #[test]
+    fn exponents() {
+        assert_eq!(exponent(123456.789, false, Some(3)), "1.235e5");
+        assert_eq!(exponent(123456.789, true, None), "1.23456789E5");
+        assert_eq!(exponent(0.00042, false, None), "4.2e-4");
+        assert_eq!(exponent(-1500.0, false, Some(0)), "-2e3");
+        assert_eq!(with_plus(&exponent(1500.0, false, None)), "+1.5e3");
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(
+            integer("42", FormatType::LowerHex, true, false).unwrap(),
+            "0x2a"
+        );
+        assert_eq!(
+            integer("42", FormatType::Octal, true, false).unwrap(),
+            "0o52"
+        );
+        assert_eq!(
+            integer("42", FormatType::Binary, true, false).unwrap(),
+            "0b101010"
+        );
+        assert_eq!(
+            integer("255", FormatType::LowerHex, false, false).unwrap(),
+            "ff"
+        );
+        assert_eq!(
+            integer("-42", FormatType::LowerHex, true, false).unwrap(),
+            "-0x2a"
+        );
+        assert_eq!(
+            integer("42", FormatType::LowerHex, true, true).unwrap(),
+            "+0x2a"
+        );
+        assert_eq!(integer("4.2", FormatType::LowerHex, true, false), None);
+        assert_eq!(integer("x", FormatType::Binary, false, false), None);
+        assert_eq!(
+            integer("255", FormatType::UpperHex, true, false).unwrap(),
+            "0xFF"
+        );
+        assert_eq!(
+            integer("0xff", FormatType::Binary, false, false).unwrap(),
+            "11111111"
+        );
+        assert_eq!(
+            integer("-0b101", FormatType::Display, false, false).unwrap(),
+            "-5"
+        );
+        assert_eq!(parse_int("0o17"), Some(15));
+        assert_eq!(parse_int("0x-1"), None);
+        assert_eq!(parse_int("--1"), None);
+    }
+}
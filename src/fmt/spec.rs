@@ -4,9 +4,14 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use super::{expr::Expr, style};
+use crate::{ansi::Style, Filter};
+
 use once_cell::sync::OnceCell;
+#[cfg(feature = "regex")]
 use regex::Regex;
 
+#[cfg(feature = "regex")]
 fn arg_name_regex() -> &'static Regex {
     static REGEX: OnceCell<Regex> = OnceCell::new();
     REGEX.get_or_init(|| {
@@ -14,6 +19,33 @@ fn arg_name_regex() -> &'static Regex {
     })
 }
 
+fn is_arg_name(input: &str) -> bool {
+    #[cfg(feature = "regex")]
+    return arg_name_regex().is_match(input);
+    // The regex above isn't anchored, so it matches anything containing a letter
+    #[cfg(not(feature = "regex"))]
+    return input.chars().any(|c| c.is_ascii_alphabetic());
+}
+
+/// The largest precision a spec may give, the same limit as rust's own `format!`, which is what
+/// numbers end up being formatted with.
+pub const MAX_PRECISION: usize = u16::MAX as usize;
+
+/// The widest a spec may pad its arg to, the same limit rust puts on widths.
+pub const MAX_WIDTH: usize = u16::MAX as usize;
+
+static FILL: OnceCell<char> = OnceCell::new();
+
+/// Makes `fill` the fill character of specs that don't give one, in place of a space. Only the
+/// first call has any effect.
+pub fn set_fill(fill: char) {
+    let _ = FILL.set(fill);
+}
+
+fn default_fill() -> char {
+    FILL.get().copied().unwrap_or(' ')
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Alignment {
     Left,
@@ -21,71 +53,374 @@ pub enum Alignment {
     Right,
 }
 
+/// How a spec renders its arg, the letter at the end of a spec like `{:x}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FormatType {
+    /// No letter, the arg as it is
+    Display,
+    /// `x`, an integer in lowercase hexadecimal
+    LowerHex,
+    /// `X`, an integer in uppercase hexadecimal
+    UpperHex,
+    /// `o`, an integer in octal
+    Octal,
+    /// `b`, an integer in binary
+    Binary,
+    /// `e`, a number in scientific notation like `1.5e3`
+    LowerExp,
+    /// `E`, a number in scientific notation like `1.5E3`
+    UpperExp,
+    /// `?`, the arg quoted with anything unprintable escaped, like rust's `Debug` for strings
+    Debug,
+    /// `d`, an integer in decimal (python only)
+    Decimal,
+    /// `f`, a number with a fixed number of decimals (python only)
+    Fixed,
+    /// `F`, like `f` but with `INF` and `NAN` in uppercase (python only)
+    UpperFixed,
+    /// `g`, a number in fixed or scientific notation, whichever is shorter (python only)
+    General,
+    /// `G`, like `g` but with an uppercase `E` (python only)
+    UpperGeneral,
+    /// `%`, a number multiplied by 100 with a percent sign (not in rust)
+    Percent,
+    /// `c`, the character with the integer as its code point (python only)
+    Char,
+    /// `s`, a string as it is (python only)
+    Str,
+}
+
+impl FormatType {
+    fn parse(c: char) -> Option<Self> {
+        match c {
+            'x' => Some(Self::LowerHex),
+            'X' => Some(Self::UpperHex),
+            'o' => Some(Self::Octal),
+            'b' => Some(Self::Binary),
+            'e' => Some(Self::LowerExp),
+            'E' => Some(Self::UpperExp),
+            '?' => Some(Self::Debug),
+            'd' => Some(Self::Decimal),
+            'f' => Some(Self::Fixed),
+            'F' => Some(Self::UpperFixed),
+            'g' => Some(Self::General),
+            'G' => Some(Self::UpperGeneral),
+            '%' => Some(Self::Percent),
+            'c' => Some(Self::Char),
+            's' => Some(Self::Str),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct FormatSpec {
     pub fmt_pos: usize,
     pub spec_num: usize,
     pub arg_num: Option<usize>,
     pub arg_name: Option<String>,
+    /// The `r`, `s` or `a` in python's `{0!r}`, converting the arg before it is formatted
+    pub conversion: Option<char>,
+    /// The body of a conditional like `{?name:body}`, a format string of its own that is only
+    /// rendered when the arg has a value
+    pub conditional: Option<String>,
+    /// The separator of a splat like `{*:, }`, which joins every positional arg no other spec uses
+    pub splat: Option<String>,
+    /// What follows the colon of a builtin that takes an option instead of a spec, like the time
+    /// format of `{now:%H:%M}`
+    pub option: Option<String>,
+    /// The filters applied to the arg before it is formatted, like the `upper` of `{name:upper}`
+    pub filters: Vec<Filter>,
+    /// The arithmetic of an expression spec like `{=2*{0}+1}`, whose result is formatted instead of
+    /// an arg
+    pub expression: Option<Expr>,
+    /// The colors the formatted arg is shown in, like the `red` of `{msg:red}`
+    pub style: Style,
     pub align: Alignment,
+    /// Whether the alignment was given, rather than being the default
+    pub aligned: bool,
+    /// Python's `=` alignment, padding numbers after their sign
+    pub after_sign: bool,
+    /// The character used to pad the arg to its width
+    pub fill: char,
+    /// Whether numbers that aren't negative get an explicit `+`
+    pub plus: bool,
+    /// Python's ` ` sign, numbers that aren't negative get a space where the sign would be
+    pub space: bool,
+    /// `#`, whether integers get a prefix showing their base
+    pub alternate: bool,
+    /// `0`, whether numbers are padded with zeros after their sign instead of being aligned
+    pub zero: bool,
     pub width: Option<usize>,
+    /// The arg (a position or a name) holding the width, for specs like `{:{w}}`
+    pub width_arg: Option<String>,
+    /// `term`, the width is the terminal's, looked up when the spec is rendered
+    pub width_term: bool,
+    /// Python's `,` or `_`, separating groups of digits
+    pub grouping: Option<char>,
+    /// The most cells the arg may take up, anything past that is cut off
+    pub precision: Option<usize>,
+    /// The arg (a position or a name) holding the precision, for specs like `{:.{p}}`
+    pub precision_arg: Option<String>,
+    /// `.*`, whether the precision is taken from the next positional arg
+    pub precision_next: bool,
+    pub ty: FormatType,
+    /// The spec as it was written
+    pub text: String,
+    /// Byte offset of the spec in the original format string, set by the [`crate::Formatter`]
+    pub src_pos: usize,
 }
 
 mod detail {
-    pub type LeftParse = (Option<String>, Option<usize>);
-    pub type RightParse = (super::Alignment, Option<usize>);
+    pub type LeftParse = (Option<String>, Option<usize>, Option<char>);
+    /// Everything after the colon of a spec
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct RightParse {
+        pub align: Option<super::Alignment>,
+        pub after_sign: bool,
+        pub fill: char,
+        pub plus: bool,
+        pub space: bool,
+        pub alternate: bool,
+        pub zero: bool,
+        pub width: Option<usize>,
+        pub width_arg: Option<String>,
+        pub width_term: bool,
+        pub grouping: Option<char>,
+        pub precision: Option<usize>,
+        pub precision_arg: Option<String>,
+        pub precision_next: bool,
+        pub ty: super::FormatType,
+    }
+
+    impl Default for RightParse {
+        fn default() -> Self {
+            Self {
+                align: None,
+                after_sign: false,
+                fill: ' ',
+                plus: false,
+                space: false,
+                alternate: false,
+                zero: false,
+                width: None,
+                width_arg: None,
+                width_term: false,
+                grouping: None,
+                precision: None,
+                precision_arg: None,
+                precision_next: false,
+                ty: super::FormatType::Display,
+            }
+        }
+    }
+
     pub type FullParse = (LeftParse, RightParse);
 }
 
 impl FormatSpec {
     pub(crate) fn new(fmt_start: usize, spec_no: usize, spec_str: &str) -> crate::Result<Self> {
         if spec_str == "{}" {
-            return Ok(Self {
-                fmt_pos: fmt_start,
-                spec_num: spec_no,
-                arg_name: None,
-                arg_num: None,
-                align: Alignment::Left,
-                width: None,
+            let right = detail::RightParse::default();
+            return Ok(Self::from_parts(
+                fmt_start,
+                spec_no,
+                spec_str,
+                (None, None, None),
+                right,
+            ));
+        }
+
+        // Expressions have args of their own in brackets, like `{=2*{0}+1}`, and may be followed
+        // by a spec for their result after a colon outside of those brackets
+        if let Some(inner) = spec_str
+            .strip_prefix("{=")
+            .and_then(|s| s.strip_suffix('}'))
+        {
+            let mut depth = 0usize;
+            let colon = inner.char_indices().find_map(|(i, c)| {
+                match c {
+                    '{' => depth += 1,
+                    '}' => depth = depth.saturating_sub(1),
+                    ':' if depth == 0 => return Some(i),
+                    _ => {}
+                }
+                None
             });
+            let (expression, rest) = match colon {
+                Some(i) => (&inner[..i], format!("{{:{}}}", &inner[i + 1..])),
+                None => (inner, "{}".to_string()),
+            };
+            let expression = Expr::parse(expression).map_err(|err| {
+                crate::Error::InvalidSpec(format!(
+                    "Unable to parse expression in spec {}: {}",
+                    spec_str, err
+                ))
+            })?;
+            let mut spec = Self::new(fmt_start, spec_no, &rest)?;
+            spec.expression = Some(expression);
+            spec.text = spec_str.to_string();
+            return Ok(spec);
         }
 
-        if spec_str.contains("{{") || spec_str.contains("}}") {
+        // Brackets after the colon belong to args nested in the spec, like `{:{w}}`
+        let arg_part = spec_str.split(':').next().unwrap_or(spec_str);
+        if arg_part.contains("{{") || arg_part.contains("}}") {
             return Err(crate::Error::bad_spec(spec_str));
         }
 
-        if !spec_str.starts_with('{') || !spec_str.ends_with('}') {
+        if spec_str.len() < 2 || !spec_str.starts_with('{') || !spec_str.ends_with('}') {
             return Err(crate::Error::bad_spec(spec_str));
         }
 
-        let inner = spec_str.trim_start_matches('{').trim_end_matches('}');
+        let inner = &spec_str[1..spec_str.len() - 1];
+        if let Some(splat) = inner.strip_prefix('*') {
+            let separator = match splat.strip_prefix(':') {
+                Some(separator) => separator,
+                None if splat.is_empty() => " ",
+                None => {
+                    return Err(crate::Error::InvalidSpec(format!(
+                        "Unable to parse splat spec: {}",
+                        spec_str
+                    )))
+                }
+            };
+            let mut spec = Self::new(fmt_start, spec_no, "{}")?;
+            spec.splat = Some(separator.to_string());
+            spec.text = spec_str.to_string();
+            return Ok(spec);
+        }
+        if let Some((name, option)) = inner.split_once(':') {
+            if crate::builtin::takes_option(name, option) {
+                let mut spec = Self::new(fmt_start, spec_no, "{}")?;
+                spec.arg_name = Some(name.to_string());
+                spec.option = Some(option.to_string());
+                spec.text = spec_str.to_string();
+                return Ok(spec);
+            }
+        }
+        if let Some(conditional) = inner.strip_prefix('?') {
+            return match conditional.split_once(':') {
+                Some((name, body)) if is_arg_name(name) => {
+                    let mut spec = Self::new(fmt_start, spec_no, "{}")?;
+                    spec.arg_name = Some(name.to_string());
+                    spec.conditional = Some(body.to_string());
+                    spec.text = spec_str.to_string();
+                    Ok(spec)
+                }
+                _ => Err(crate::Error::InvalidSpec(format!(
+                    "Unable to parse conditional spec: {}",
+                    spec_str
+                ))),
+            };
+        }
+        // Filters and styles can be mixed in any order before the rest of the spec
+        if let Some((arg, mut rest)) = inner.split_once(':') {
+            let (mut filters, mut styled) = (Vec::new(), None::<Style>);
+            loop {
+                let (more, after) = Filter::parse_chain(spec_str, rest)?;
+                filters.extend(more);
+                rest = after;
+                let (item, after) = rest.split_once(':').unwrap_or((rest, ""));
+                match style::parse(item) {
+                    Some(style) => styled = Some(style::merge(styled.unwrap_or_default(), style)),
+                    None => break,
+                }
+                rest = after;
+            }
+            if !filters.is_empty() || styled.is_some() {
+                let plain = match rest {
+                    "" => format!("{{{}}}", arg),
+                    _ => format!("{{{}:{}}}", arg, rest),
+                };
+                let mut spec = Self::new(fmt_start, spec_no, &plain)?;
+                spec.filters = filters;
+                spec.style = styled.unwrap_or_default();
+                spec.text = spec_str.to_string();
+                return Ok(spec);
+            }
+        }
         if inner.is_empty() {
-            return Ok(Self {
-                fmt_pos: fmt_start,
-                spec_num: spec_no,
-                arg_name: None,
-                arg_num: None,
-                align: Alignment::Left,
-                width: None,
-            });
+            let right = detail::RightParse::default();
+            return Ok(Self::from_parts(
+                fmt_start,
+                spec_no,
+                spec_str,
+                (None, None, None),
+                right,
+            ));
         }
 
-        let ((name, num), (align, width)) = Self::parse_spec(spec_str, inner)?;
-        Ok(Self {
+        let (left, right) = Self::parse_spec(spec_str, inner)?;
+        Ok(Self::from_parts(fmt_start, spec_no, spec_str, left, right))
+    }
+
+    /// Builds the spec written as `spec_str` out of what was parsed from either side of its colon.
+    fn from_parts(
+        fmt_start: usize,
+        spec_no: usize,
+        spec_str: &str,
+        (name, num, conversion): detail::LeftParse,
+        right: detail::RightParse,
+    ) -> Self {
+        Self {
             fmt_pos: fmt_start,
             spec_num: spec_no,
             arg_name: name,
             arg_num: num,
-            align,
-            width,
-        })
+            conversion,
+            conditional: None,
+            splat: None,
+            option: None,
+            filters: Vec::new(),
+            expression: None,
+            style: Style::default(),
+            align: right.align.unwrap_or(Alignment::Left),
+            aligned: right.align.is_some(),
+            after_sign: right.after_sign,
+            fill: right.fill,
+            plus: right.plus,
+            space: right.space,
+            alternate: right.alternate,
+            zero: right.zero,
+            width: right.width,
+            width_arg: right.width_arg,
+            width_term: right.width_term,
+            grouping: right.grouping,
+            precision: right.precision,
+            precision_arg: right.precision_arg,
+            precision_next: right.precision_next,
+            ty: right.ty,
+            text: spec_str.to_string(),
+            src_pos: 0,
+        }
     }
 
     pub fn is_empty(&self) -> bool {
         self.arg_num.is_none()
             && self.arg_name.is_none()
+            && self.conversion.is_none()
+            && self.conditional.is_none()
+            && self.splat.is_none()
+            && self.option.is_none()
+            && self.filters.is_empty()
+            && self.expression.is_none()
+            && self.style.is_plain()
             && self.align == Alignment::Left
+            && !self.after_sign
+            && self.fill == ' '
+            && !self.plus
+            && !self.space
+            && !self.alternate
+            && !self.zero
+            && self.ty == FormatType::Display
             && self.width.is_none()
+            && self.width_arg.is_none()
+            && !self.width_term
+            && self.grouping.is_none()
+            && self.precision.is_none()
+            && self.precision_arg.is_none()
+            && !self.precision_next
     }
 
     fn parse_spec(entire_spec: &str, inner: &str) -> crate::Result<detail::FullParse> {
@@ -97,53 +432,171 @@ impl FormatSpec {
             Ok((left_side, right_parsed))
         } else {
             let parsed = Self::parse_spec_left(entire_spec, inner)?;
-            Ok((parsed, (Alignment::Left, None)))
+            Ok((parsed, detail::RightParse::default()))
         }
     }
 
     fn parse_spec_left(entire: &str, input: &str) -> crate::Result<detail::LeftParse> {
+        let (input, conversion) = match input.split_once('!') {
+            Some((arg, c @ ("r" | "s" | "a"))) => (arg, c.chars().next()),
+            Some(_) => {
+                return Err(crate::Error::InvalidSpec(format!(
+                    "Unable to parse conversion in spec: {}",
+                    entire
+                )))
+            }
+            None => (input, None),
+        };
         if input.is_empty() {
-            Ok((None, None))
+            Ok((None, None, conversion))
         } else if let Ok(num) = input.parse::<usize>() {
-            Ok((None, Some(num)))
-        } else if arg_name_regex().is_match(input) {
-            Ok((Some(input.to_string()), None))
+            Ok((None, Some(num), conversion))
+        } else if is_arg_name(input) || input == crate::builtin::ARG_COUNT {
+            Ok((Some(input.to_string()), None, conversion))
         } else {
-            eprintln!("Unable to parse left side of colon in spec: {}", entire);
-            Err(crate::Error::bad_spec(entire))
+            Err(crate::Error::InvalidSpec(format!(
+                "Unable to parse left side of colon in spec: {}",
+                entire
+            )))
         }
     }
 
     fn parse_spec_right(entire: &str, input: &str) -> crate::Result<detail::RightParse> {
+        fn alignment(c: char) -> Option<Alignment> {
+            match c {
+                '<' => Some(Alignment::Left),
+                '>' => Some(Alignment::Right),
+                '^' => Some(Alignment::Center),
+                // Python's `=` right aligns too, but puts the padding after the sign
+                '=' => Some(Alignment::Right),
+                _ => None,
+            }
+        }
+
         let mut right = input;
-        let align = if right.starts_with(['<', '>', '^']) {
-            let a = match right.chars().next().unwrap() {
-                '<' => Alignment::Left,
-                '>' => Alignment::Right,
-                '^' => Alignment::Center,
-                _ => unreachable!(),
-            };
+        let mut chars = right.chars();
+        // Like rust, any character directly followed by an alignment is the fill character
+        let (align, fill, after_sign) = match (chars.next(), chars.next()) {
+            (Some(fill), Some(c)) if alignment(c).is_some() => {
+                right = &right[fill.len_utf8() + 1..];
+                (alignment(c), fill, c == '=')
+            }
+            (Some(c), _) if alignment(c).is_some() => {
+                right = &right[1..];
+                (alignment(c), default_fill(), c == '=')
+            }
+            _ => (None, default_fill(), false),
+        };
+
+        // `-` is accepted like rust does, but it doesn't do anything there either
+        let plus = right.starts_with('+');
+        let space = right.starts_with(' ');
+        if right.starts_with(['+', '-', ' ']) {
             right = &right[1..];
-            a
-        } else {
-            // TODO: Should this be None? Should align be Alignment instead of Option<Alignment>?
-            Alignment::Left
+        }
+        let alternate = right.starts_with('#');
+        if alternate {
+            right = &right[1..];
+        }
+        // A lone `0` is a width of zero rather than the flag, and `0$` takes the width from arg 0
+        let zero = right.starts_with('0') && right.len() > 1 && !right[1..].starts_with('$');
+        if zero {
+            right = &right[1..];
+        }
+
+        let ty = match right.chars().last().and_then(FormatType::parse) {
+            Some(ty) => {
+                right = &right[..right.len() - 1];
+                ty
+            }
+            None => FormatType::Display,
+        };
+
+        /// The arg in `{arg}` (or `arg$`, like rust), when a count is taken from another arg
+        fn nested_arg(entire: &str, count: &str) -> crate::Result<Option<String>> {
+            let arg = count
+                .strip_prefix('{')
+                .and_then(|c| c.strip_suffix('}'))
+                .or_else(|| count.strip_suffix('$'));
+            match arg {
+                Some(arg) if arg.parse::<usize>().is_ok() || is_arg_name(arg) => {
+                    Ok(Some(arg.to_string()))
+                }
+                Some(_) => Err(crate::Error::InvalidSpec(format!(
+                    "Unable to parse argument in spec: {}",
+                    entire
+                ))),
+                None => Ok(None),
+            }
+        }
+
+        let mut precision_arg = None;
+        let mut precision_next = false;
+        let precision = match right.split_once('.') {
+            Some((w, "*")) => {
+                right = w;
+                precision_next = true;
+                None
+            }
+            Some((w, p)) => {
+                right = w;
+                precision_arg = nested_arg(entire, p)?;
+                match p.parse::<usize>() {
+                    Ok(p) if p > MAX_PRECISION => return Err(crate::Error::bad_spec(entire)),
+                    Ok(p) => Some(p),
+                    Err(_) if precision_arg.is_some() => None,
+                    Err(_) => {
+                        return Err(crate::Error::InvalidSpec(format!(
+                            "Unable to parse precision in spec: {}",
+                            entire
+                        )))
+                    }
+                }
+            }
+            None => None,
         };
 
-        let width = if right.is_empty() {
+        let grouping = right.chars().last().filter(|c| matches!(c, ',' | '_'));
+        if grouping.is_some() {
+            right = &right[..right.len() - 1];
+        }
+
+        let width_arg = nested_arg(entire, right)?;
+        let width_term = right == "term";
+        let width = if right.is_empty() || width_arg.is_some() || width_term {
             None
         } else if let Ok(n) = right.parse::<usize>() {
             if n == 0 {
-                eprintln!("Format spec is zero width: {}", entire);
                 return Err(crate::Error::zero_width(entire));
             }
+            if n > MAX_WIDTH {
+                return Err(crate::Error::bad_spec(entire));
+            }
             Some(n)
         } else {
-            eprintln!("Unable to parse right side of colon in spec: {}", entire);
-            return Err(crate::Error::bad_spec(entire));
+            return Err(crate::Error::InvalidSpec(format!(
+                "Unable to parse right side of colon in spec: {}",
+                entire
+            )));
         };
 
-        Ok((align, width))
+        Ok(detail::RightParse {
+            align,
+            after_sign,
+            fill,
+            plus,
+            space,
+            alternate,
+            zero,
+            width,
+            width_arg,
+            width_term,
+            grouping,
+            precision,
+            precision_arg,
+            precision_next,
+            ty,
+        })
     }
 }
 
@@ -180,6 +633,9 @@ mod tests {
 
         let spec = FormatSpec::new(0, 0, "{1:0}");
         assert!(spec.is_err());
+
+        let spec = FormatSpec::new(0, 0, "{:18446744073709551615}");
+        assert!(spec.is_err());
     }
 
     #[test]
@@ -278,4 +734,273 @@ mod tests {
         let spec = FormatSpec::new(0, 0, "{name:>0}");
         assert!(spec.is_err());
     }
+
+    #[test]
+    fn fill_chars() {
+        let spec = FormatSpec::new(0, 0, "{:*^10}").expect("error parsing {:*^10}");
+        assert!(!spec.is_empty());
+        assert_eq!(spec.fill, '*');
+        assert_eq!(spec.align, Alignment::Center);
+        assert_eq!(spec.width, Some(10));
+
+        let spec = FormatSpec::new(0, 0, "{name:>>3}").expect("error parsing {name:>>3}");
+        assert_eq!(spec.fill, '>');
+        assert_eq!(spec.align, Alignment::Right);
+
+        let spec = FormatSpec::new(0, 0, "{::<4}").expect("error parsing {::<4}");
+        assert_eq!(spec.fill, ':');
+        assert_eq!(spec.align, Alignment::Left);
+
+        let spec = FormatSpec::new(0, 0, "{:·<4}").expect("error parsing {:·<4}");
+        assert_eq!(spec.fill, '·');
+
+        let spec = FormatSpec::new(0, 0, "{:>5}").expect("error parsing {:>5}");
+        assert_eq!(spec.fill, ' ');
+
+        assert!(FormatSpec::new(0, 0, "{:*10}").is_err());
+    }
+
+    #[test]
+    fn types() {
+        let spec = FormatSpec::new(0, 0, "{:#x}").expect("error parsing {:#x}");
+        assert!(spec.alternate);
+        assert_eq!(spec.ty, FormatType::LowerHex);
+        assert!(!spec.is_empty());
+
+        let spec = FormatSpec::new(0, 0, "{0:>+#10b}").expect("error parsing {0:>+#10b}");
+        assert!(spec.plus && spec.alternate);
+        assert_eq!(spec.width, Some(10));
+        assert_eq!(spec.ty, FormatType::Binary);
+
+        let spec = FormatSpec::new(0, 0, "{:X}").expect("error parsing {:X}");
+        assert_eq!(spec.ty, FormatType::UpperHex);
+
+        let spec = FormatSpec::new(0, 0, "{:.3e}").expect("error parsing {:.3e}");
+        assert_eq!(spec.ty, FormatType::LowerExp);
+        assert_eq!(spec.precision, Some(3));
+
+        let spec = FormatSpec::new(0, 0, "{name:>12?}").expect("error parsing {name:>12?}");
+        assert_eq!(spec.ty, FormatType::Debug);
+        assert_eq!(spec.width, Some(12));
+
+        let spec = FormatSpec::new(0, 0, "{:o}").expect("error parsing {:o}");
+        assert!(!spec.alternate);
+        assert_eq!(spec.ty, FormatType::Octal);
+
+        assert!(FormatSpec::new(0, 0, "{:#q}").is_err());
+    }
+
+    #[test]
+    fn rust_flags() {
+        let spec = FormatSpec::new(0, 0, "{:+#010x}").expect("error parsing {:+#010x}");
+        assert!(spec.plus && spec.alternate && spec.zero);
+        assert_eq!(spec.width, Some(10));
+        assert!(!spec.aligned);
+
+        let spec = FormatSpec::new(0, 0, "{:0<5}").expect("error parsing {:0<5}");
+        assert!(!spec.zero && spec.aligned);
+        assert_eq!(spec.fill, '0');
+
+        let spec = FormatSpec::new(0, 0, "{:1$.w$}").expect("error parsing {:1$.w$}");
+        assert_eq!(spec.width_arg, Some("1".to_string()));
+        assert_eq!(spec.precision_arg, Some("w".to_string()));
+
+        let spec = FormatSpec::new(0, 0, "{:0$}").expect("error parsing {:0$}");
+        assert!(!spec.zero);
+        assert_eq!(spec.width_arg, Some("0".to_string()));
+
+        let spec = FormatSpec::new(0, 0, "{:>8.*}").expect("error parsing {:>8.*}");
+        assert!(spec.precision_next);
+        assert_eq!(spec.precision, None);
+        assert!(FormatSpec::new(0, 0, "{:0}").is_err());
+    }
+
+    #[test]
+    fn conditionals() {
+        let spec = FormatSpec::new(0, 0, "{?branch: on {branch}}")
+            .expect("error parsing {?branch: on {branch}}");
+        assert_eq!(spec.arg_name, Some("branch".to_string()));
+        assert_eq!(spec.conditional, Some(" on {branch}".to_string()));
+        assert!(!spec.is_empty());
+
+        assert!(FormatSpec::new(0, 0, "{?branch}").is_err());
+        assert!(FormatSpec::new(0, 0, "{?:text}").is_err());
+    }
+
+    #[test]
+    fn arg_count() {
+        let spec = FormatSpec::new(0, 0, "{#}").expect("error parsing {#}");
+        assert_eq!(spec.arg_name, Some("#".to_string()));
+        let spec = FormatSpec::new(0, 0, "{#:>3}").expect("error parsing {#:>3}");
+        assert_eq!(spec.width, Some(3));
+        assert!(FormatSpec::new(0, 0, "{##}").is_err());
+    }
+
+    #[test]
+    fn builtin_options() {
+        let spec = FormatSpec::new(0, 0, "{now:%H:%M:%S}").expect("error parsing {now:%H:%M:%S}");
+        assert_eq!(spec.arg_name, Some("now".to_string()));
+        assert_eq!(spec.option, Some("%H:%M:%S".to_string()));
+        let spec = FormatSpec::new(0, 0, "{now:>30}").expect("error parsing {now:>30}");
+        assert_eq!(spec.option, None);
+        assert_eq!(spec.width, Some(30));
+    }
+
+    #[test]
+    fn filters() {
+        let spec = FormatSpec::new(0, 0, "{name:upper}").expect("error parsing {name:upper}");
+        assert_eq!(spec.arg_name, Some("name".to_string()));
+        assert_eq!(spec.filters, vec![Filter::Upper]);
+        assert_eq!(spec.text, "{name:upper}");
+        assert!(!spec.is_empty());
+        let spec = FormatSpec::new(0, 0, "{0:snake:lower:>10}").expect("error parsing filters");
+        assert_eq!(spec.arg_num, Some(0));
+        assert_eq!(spec.filters, vec![Filter::Snake, Filter::Lower]);
+        assert_eq!((spec.align, spec.width), (Alignment::Right, Some(10)));
+        let spec = FormatSpec::new(0, 0, "{:title}").expect("error parsing {:title}");
+        assert_eq!(spec.filters, vec![Filter::Title]);
+        assert!(FormatSpec::new(0, 0, "{:upper:nope}").is_err());
+    }
+
+    #[test]
+    fn styles() {
+        let red = Some(crate::color::Color::Basic(1));
+        let spec = FormatSpec::new(0, 0, "{msg:red}").expect("error parsing {msg:red}");
+        assert_eq!(spec.arg_name.as_deref(), Some("msg"));
+        assert_eq!(spec.style.fg, red);
+        assert!(!spec.is_empty());
+        let spec = FormatSpec::new(0, 0, "{0:upper:color=red:>10}").expect("error parsing styles");
+        assert_eq!(spec.filters, vec![Filter::Upper]);
+        assert_eq!(spec.style.fg, red);
+        assert_eq!((spec.align, spec.width), (Alignment::Right, Some(10)));
+        assert!(FormatSpec::new(0, 0, "{:>10}").unwrap().style.is_plain());
+        assert!(FormatSpec::new(0, 0, "{:color=crimson}").is_err());
+        let spec = FormatSpec::new(0, 0, "{title:bold.underline:red:^9}").unwrap();
+        assert!(spec.style.bold && spec.style.underline);
+        assert_eq!(spec.style.fg, red);
+        assert_eq!(spec.width, Some(9));
+    }
+
+    #[test]
+    fn expressions() {
+        let spec = FormatSpec::new(0, 0, "{=2*{0}+1}").expect("error parsing {=2*{0}+1}");
+        assert!(spec.expression.is_some());
+        assert_eq!((spec.arg_num, spec.arg_name.as_deref()), (None, None));
+        assert!(!spec.is_empty());
+        let spec = FormatSpec::new(0, 0, "{= {a} / {b} :>8.2}").expect("error parsing expression");
+        assert_eq!((spec.width, spec.precision), (Some(8), Some(2)));
+        assert_eq!(spec.text, "{= {a} / {b} :>8.2}");
+        assert!(FormatSpec::new(0, 0, "{=1+}").is_err());
+        assert!(FormatSpec::new(0, 0, "{=1:q}").is_err());
+    }
+
+    #[test]
+    fn splats() {
+        let spec = FormatSpec::new(0, 0, "{*}").expect("error parsing {*}");
+        assert_eq!(spec.splat, Some(" ".to_string()));
+        assert!(!spec.is_empty());
+        let spec = FormatSpec::new(0, 0, "{*:, }").expect("error parsing {*:, }");
+        assert_eq!(spec.splat, Some(", ".to_string()));
+        assert_eq!(spec.arg_num, None);
+        assert!(FormatSpec::new(0, 0, "{*x}").is_err());
+    }
+
+    #[test]
+    fn python_parts() {
+        let spec = FormatSpec::new(0, 0, "{0!r:>10}").expect("error parsing {0!r:>10}");
+        assert_eq!(spec.arg_num, Some(0));
+        assert_eq!(spec.conversion, Some('r'));
+        assert_eq!(spec.width, Some(10));
+
+        let spec = FormatSpec::new(0, 0, "{!a}").expect("error parsing {!a}");
+        assert_eq!(spec.conversion, Some('a'));
+        assert!(!spec.is_empty());
+
+        let spec = FormatSpec::new(0, 0, "{:*= 12_.2f}").expect("error parsing {:*= 12_.2f}");
+        assert!(spec.after_sign && spec.space);
+        assert_eq!(spec.fill, '*');
+        assert_eq!(spec.width, Some(12));
+        assert_eq!(spec.grouping, Some('_'));
+        assert_eq!(spec.precision, Some(2));
+        assert_eq!(spec.ty, FormatType::Fixed);
+
+        let spec = FormatSpec::new(0, 0, "{:,}").expect("error parsing {:,}");
+        assert_eq!(spec.grouping, Some(','));
+        assert_eq!(spec.width, None);
+
+        assert!(FormatSpec::new(0, 0, "{0!x}").is_err());
+    }
+
+    #[test]
+    fn width_args() {
+        let spec = FormatSpec::new(0, 0, "{name:{w}}").expect("error parsing {name:{w}}");
+        assert_eq!(spec.arg_name, Some("name".to_string()));
+        assert_eq!(spec.width, None);
+        assert_eq!(spec.width_arg, Some("w".to_string()));
+        assert!(!spec.is_empty());
+
+        let spec = FormatSpec::new(0, 0, "{:*>{1}.2}").expect("error parsing {:*>{1}.2}");
+        assert_eq!(spec.fill, '*');
+        assert_eq!(spec.width_arg, Some("1".to_string()));
+        assert_eq!(spec.precision, Some(2));
+
+        let spec = FormatSpec::new(0, 0, "{:-^term}").expect("error parsing {:-^term}");
+        assert!(spec.width_term);
+        assert_eq!((spec.fill, spec.width), ('-', None));
+        assert!(!spec.is_empty());
+        assert!(FormatSpec::new(0, 0, "{:terminal}").is_err());
+
+        assert!(FormatSpec::new(0, 0, "{:{}}").is_err());
+        assert!(FormatSpec::new(0, 0, "{:{-}}").is_err());
+        assert!(FormatSpec::new(0, 0, "{a}}").is_err());
+    }
+
+    #[test]
+    fn precision_args() {
+        let spec = FormatSpec::new(0, 0, "{:.{p}}").expect("error parsing {:.{p}}");
+        assert_eq!(spec.precision, None);
+        assert_eq!(spec.precision_arg, Some("p".to_string()));
+        assert!(!spec.is_empty());
+
+        let spec = FormatSpec::new(0, 0, "{x:{0}.{1}e}").expect("error parsing {x:{0}.{1}e}");
+        assert_eq!(spec.width_arg, Some("0".to_string()));
+        assert_eq!(spec.precision_arg, Some("1".to_string()));
+        assert_eq!(spec.ty, FormatType::LowerExp);
+
+        assert!(FormatSpec::new(0, 0, "{:.{}}").is_err());
+        assert!(FormatSpec::new(0, 0, "{:.{p}x}").is_ok());
+    }
+
+    #[test]
+    fn precisions() {
+        let spec = FormatSpec::new(0, 0, "{:.5}").expect("error parsing {:.5}");
+        assert!(!spec.is_empty());
+        assert_eq!(spec.width, None);
+        assert_eq!(spec.precision, Some(5));
+
+        let spec = FormatSpec::new(0, 0, "{name:->10.3}").expect("error parsing {name:->10.3}");
+        assert_eq!(spec.fill, '-');
+        assert_eq!(spec.align, Alignment::Right);
+        assert_eq!(spec.width, Some(10));
+        assert_eq!(spec.precision, Some(3));
+
+        let spec = FormatSpec::new(0, 0, "{:.0}").expect("error parsing {:.0}");
+        assert_eq!(spec.precision, Some(0));
+
+        assert!(FormatSpec::new(0, 0, "{:.}").is_err());
+        assert!(FormatSpec::new(0, 0, "{:.65535}").is_ok());
+        assert!(FormatSpec::new(0, 0, "{:.65536}").is_err());
+        assert!(FormatSpec::new(0, 0, "{:.70000e}").is_err());
+
+        let spec = FormatSpec::new(0, 0, "{:>+8.2}").expect("error parsing {:>+8.2}");
+        assert!(spec.plus);
+        assert_eq!(spec.width, Some(8));
+        assert_eq!(spec.precision, Some(2));
+        let spec = FormatSpec::new(0, 0, "{:+}").expect("error parsing {:+}");
+        assert!(spec.plus && !spec.is_empty());
+        let spec = FormatSpec::new(0, 0, "{:-5}").expect("error parsing {:-5}");
+        assert!(!spec.plus);
+        assert_eq!(spec.width, Some(5));
+        assert!(FormatSpec::new(0, 0, "{:5.x}").is_err());
+    }
 }
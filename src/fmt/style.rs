@@ -0,0 +1,140 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! The styles of specs like `{msg:red}`, `{err:color=red}`, `{title:bold.underline}` or
+//! `{warn:black.on_yellow}`, which color the formatted arg and change how its text looks. Colors
+//! are anything [`Color::parse`] understands, and themes from the config file can be used like
+//! colors.
+
+use std::collections::HashMap;
+
+use once_cell::sync::OnceCell;
+
+use crate::{ansi::Style, color::Color};
+
+static THEMES: OnceCell<HashMap<String, Style>> = OnceCell::new();
+
+/// Makes `themes` the named styles specs can use. Only the first call has any effect.
+pub fn set_themes(themes: HashMap<String, Style>) {
+    let _ = THEMES.set(themes);
+}
+
+fn theme(name: &str) -> Option<Style> {
+    THEMES.get()?.get(name).copied()
+}
+
+/// Parses a style, keywords and colors separated by dots like `bold.red`, or returns `None` if
+/// `s` isn't one so it can be tried as something else.
+pub fn parse(s: &str) -> Option<Style> {
+    let mut style = Style::default();
+    for word in s.split('.') {
+        if let Some(name) = word.strip_prefix("on_") {
+            style.bg = Some(Color::parse(name)?);
+            continue;
+        }
+        match word {
+            "bold" => style.bold = true,
+            "dim" => style.dim = true,
+            "italic" => style.italic = true,
+            "underline" => style.underline = true,
+            "inverse" => style.inverse = true,
+            "strike" => style.strike = true,
+            _ => match Color::parse(word.strip_prefix("color=").unwrap_or(word)) {
+                Some(color) => style.fg = Some(color),
+                None => style = merge(style, theme(word)?),
+            },
+        }
+    }
+    Some(style)
+}
+
+/// Adds the style `over` to `base`, its colors taking the place of those in `base`.
+pub fn merge(base: Style, over: Style) -> Style {
+    Style {
+        fg: over.fg.or(base.fg),
+        bg: over.bg.or(base.bg),
+        bold: base.bold || over.bold,
+        dim: base.dim || over.dim,
+        italic: base.italic || over.italic,
+        underline: base.underline || over.underline,
+        inverse: base.inverse || over.inverse,
+        strike: base.strike || over.strike,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::ColorLevel;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    fn fg(s: &str) -> Option<Color> {
+        parse(s).and_then(|style| style.fg)
+    }
+
+    #[test]
+    fn colors() {
+        assert_eq!(fg("red"), Some(Color::Basic(1)));
+        assert_eq!(fg("color=red"), Some(Color::Basic(1)));
+        assert_eq!(fg("bright_cyan"), Some(Color::Basic(14)));
+        assert_eq!(fg("grey"), Some(Color::Basic(8)));
+        assert_eq!(parse("color=black"), parse("black"));
+        assert_eq!(parse("crimson"), None);
+        assert_eq!(parse("color="), None);
+        assert_eq!(parse(">10"), None);
+        assert_eq!(parse(""), None);
+    }
+
+    #[test]
+    fn extended_colors() {
+        assert_eq!(fg("#ff8800"), Some(Color::Rgb(255, 136, 0)));
+        assert_eq!(fg("color=#FF8800"), Some(Color::Rgb(255, 136, 0)));
+        assert_eq!(fg("color256=208"), Some(Color::Ansi256(208)));
+        assert_eq!(
+            parse("on_#000080").and_then(|s| s.bg),
+            Some(Color::Rgb(0, 0, 128))
+        );
+        assert_eq!(
+            parse("on_color256=17").and_then(|s| s.bg),
+            Some(Color::Ansi256(17))
+        );
+        assert_eq!(parse("#ff880"), None);
+        assert_eq!(parse("#gg8800"), None);
+        assert_eq!(parse("#x"), None);
+        assert_eq!(parse("color256=256"), None);
+        assert_eq!(parse("color256="), None);
+
+        let style = parse("#ff8800.on_color256=17").unwrap();
+        assert_eq!(style.sgr(ColorLevel::TrueColor), "38;2;255;136;0;48;5;17");
+        assert_eq!(style.sgr(ColorLevel::Ansi256), "38;5;208;48;5;17");
+        assert_eq!(style.sgr(ColorLevel::Basic), "33;40");
+    }
+
+    #[test]
+    fn keywords() {
+        let style = parse("bold.underline").unwrap();
+        assert!(style.bold && style.underline && !style.italic);
+        assert_eq!(style.fg, None);
+        let style = parse("italic.color=blue.dim").unwrap();
+        assert!(style.italic && style.dim);
+        assert_eq!(style.fg, Some(Color::Basic(4)));
+        assert_eq!(parse("bold.nope"), None);
+        assert_eq!(parse("bold."), None);
+
+        let style = parse("black.on_yellow").unwrap();
+        assert_eq!(style.fg, Some(Color::Basic(0)));
+        assert_eq!(style.bg, Some(Color::Basic(3)));
+        assert_eq!(parse("on_bright_blue").unwrap().bg, Some(Color::Basic(12)));
+        assert_eq!(parse("on_"), None);
+        assert_eq!(parse("on_nope"), None);
+
+        let style = merge(parse("red.bold").unwrap(), parse("strike.blue").unwrap());
+        assert!(style.bold && style.strike);
+        assert_eq!(style.fg, Some(Color::Basic(4)));
+        let style = merge(parse("on_red").unwrap(), parse("white").unwrap());
+        assert_eq!(style.bg, Some(Color::Basic(1)));
+    }
+}
@@ -1,4 +1,44 @@
-use ansirs::*;
+#[cfg(feature = "color")]
+use ansirs::{Ansi, Colors};
+#[cfg(not(feature = "color"))]
+use plain::{Ansi, Colors};
+
+/// `ansirs` always writes 24-bit colors, so what it writes is downgraded to what the terminal
+/// supports, or stripped entirely when color is off.
+#[cfg(feature = "color")]
+fn style_text<S: std::fmt::Display>(text: S, style: Ansi) -> String {
+    crate::ansi::downgrade(
+        &ansirs::style_text(text.to_string(), style),
+        crate::color::effective_level(),
+    )
+}
+
+#[cfg(not(feature = "color"))]
+fn style_text<S: std::fmt::Display>(text: S, _: Ansi) -> String {
+    text.to_string()
+}
+
+/// Stand-ins for the bits of `ansirs` used here, so the help text needs no changes when built
+/// without `color`.
+#[cfg(not(feature = "color"))]
+mod plain {
+    pub enum Colors {
+        Yellow,
+        LawnGreen,
+        GoldenRod,
+        Purple,
+        White,
+        Gray,
+    }
+
+    pub struct Ansi;
+
+    impl Ansi {
+        pub fn from_fg(_: Colors) -> Self {
+            Self
+        }
+    }
+}
 
 pub fn print_usage(bin: &str) -> crate::Result<()> {
     fn header(text: &str) {
@@ -122,16 +162,46 @@ pub fn print_usage_long(bin: &str) -> crate::Result<()> {
         true,
         false,
     );
+    term(
+        this_bin,
+        &[
+            "snapshot",
+            "--record|--verify",
+            "<FILE>",
+            "[FLAGS]",
+            "<FMT_STRING>",
+            "[<ARGS>]",
+        ],
+        true,
+        false,
+    );
+    term(
+        this_bin,
+        &[
+            "table",
+            "[--csv|--tsv]",
+            "[--headers]",
+            "[--align SPECS]",
+            "[--border STYLE]",
+            "[FLAGS]",
+        ],
+        true,
+        false,
+    );
     println!();
     // Argument description
     header("Arguments");
     item_and_desc(
         "FMT_STRING",
-        "A string containing text and any number of FMT_SPECs (format specifiers, see below)",
+        "A string containing text and any number of FMT_SPECs (format specifiers, see below), or - to read it from stdin",
+    );
+    item_and_desc(
+        "@ALIAS",
+        "In place of FMT_STRING, the format string named ALIAS in the [aliases] of the config file",
     );
     item_and_desc(
         "ARGS",
-        "A list of strings to be inserted into the FMT_STRING",
+        "A list of strings to be inserted into the FMT_STRING, @FILE for the lines of FILE (@@ for a leading @)",
     );
     println!();
     // Flag description
@@ -141,6 +211,282 @@ pub fn print_usage_long(bin: &str) -> crate::Result<()> {
         "-D, --debug",
         "Print debug information while parsing the FMT_STRING and ARGS",
     );
+    item_and_desc(
+        "-e",
+        "Interpret backslash escapes in FMT_STRING like echo -e (\\n, \\t, \\r, \\0NNN, \\xHH, ..)",
+    );
+    item_and_desc("-n", "Do not print a newline after the output");
+    item_and_desc(
+        "--stdin-args",
+        "Add every line of stdin to the ARGS, like ls | fmt --stdin-args \"{#} entries\"",
+    );
+    item_and_desc(
+        "--args-file FILE",
+        "Add every line of FILE to the ARGS, like @FILE",
+    );
+    item_and_desc(
+        "-F, --template FILE",
+        "Read the FMT_STRING from FILE (- for stdin), every arg after the flags is then one of the ARGS",
+    );
+    item_and_desc(
+        "--clamp",
+        "Truncate output lines that are wider than the terminal, marking them with \"…\"",
+    );
+    item_and_desc(
+        "--wrap[=N]",
+        "Wrap output at word boundaries to fit the terminal, or N columns if given",
+    );
+    item_and_desc(
+        "--hard-wrap",
+        "Like --wrap, but also break up words that are too long to fit on any line",
+    );
+    item_and_desc(
+        "--justify",
+        "Like --wrap, but also space words out so lines are flush with both margins",
+    );
+    item_and_desc(
+        "--hang N",
+        "Indent lines continued by --wrap by N columns, so they hang under the first",
+    );
+    item_and_desc(
+        "--block-align A",
+        "Align the whole output as one block to the left, center, or right of the terminal",
+    );
+    item_and_desc(
+        "--center",
+        "Center every output line on its own within the terminal, for banners and headers",
+    );
+    item_and_desc(
+        "--progress N TOTAL",
+        "Follow the output with a progress bar of N out of TOTAL, redrawn in place on terminals",
+    );
+    item_and_desc(
+        "--typewriter[=MS]",
+        "Type the output out on terminals one character at a time, MS (default 40) apart",
+    );
+    item_and_desc(
+        "--title[=also]",
+        "Make the output the title of the terminal instead of printing it, or print it as well",
+    );
+    item_and_desc(
+        "--no-config",
+        "Ignore the config file (~/.config/term-println/config.toml, or TERM_PRINTLN_CONFIG)",
+    );
+    item_and_desc(
+        "--ascii",
+        "Draw boxes, rules, bars and spinners with ASCII only, the default in non-UTF-8 locales",
+    );
+    item_and_desc(
+        "--qr",
+        "Draw the output as a QR code, to scan it off the terminal",
+    );
+    item_and_desc(
+        "--at ROW,COL",
+        "Draw the output starting at ROW and COL of the terminal, counting from 1",
+    );
+    item_and_desc("--clear", "Clear the terminal before drawing the output");
+    item_and_desc(
+        "--clear-line",
+        "Clear the line the output is drawn on first",
+    );
+    item_and_desc(
+        "--bell",
+        "Ring the terminal bell once the output is printed",
+    );
+    item_and_desc(
+        "--notify",
+        "Also send the output as a desktop notification, on terminals that support OSC 9 or 777",
+    );
+    item_and_desc(
+        "--every DURATION",
+        "Render FMT_STRING again every DURATION (like 2s or 500ms) on a cleared screen, like watch",
+    );
+    item_and_desc(
+        "--overwrite",
+        "Draw the output over the current line instead of ending it, for status lines in loops",
+    );
+    item_and_desc(
+        "--countdown SECS",
+        "Render FMT_STRING every second, with {remaining} counting down from SECS to 0",
+    );
+    item_and_desc(
+        "--spinner",
+        "Run the command after -- in ARGS with a spinner next to the output until it is done",
+    );
+    item_and_desc(
+        "--hr[=PATTERN]",
+        "Print a rule across the terminal out of PATTERN (default ─), in place of FMT_STRING",
+    );
+    item_and_desc(
+        "--box[=STYLE]",
+        "Draw a frame around the output, with single (default), double, rounded or ascii lines",
+    );
+    item_and_desc(
+        "--width N",
+        "Lay output out for a terminal N columns wide, instead of the detected width",
+    );
+    item_and_desc(
+        "--each",
+        "Render FMT_STRING once for every one of the ARGS, one per line (a record mode)",
+    );
+    item_and_desc(
+        "--chunk N",
+        "Like --each, but every N of the ARGS fill FMT_STRING together",
+    );
+    item_and_desc(
+        "--zip N",
+        "Like --chunk, but the ARGS are N equally long lists and each record takes the next of every list",
+    );
+    item_and_desc(
+        "--stream",
+        "Like --each, but render FMT_STRING for every line read from stdin as it arrives",
+    );
+    item_and_desc(
+        "--columns[=N]",
+        "In record modes, lay the results out in N columns, or as many as fit the terminal",
+    );
+    item_and_desc(
+        "--sort[=KEY]",
+        "In record modes, sort the results, or sort the ARGS by a field name or position first",
+    );
+    item_and_desc(
+        "--unique[=KEY]",
+        "In record modes, drop duplicate results (or ARGS with a duplicate field), {dupcount} counts them",
+    );
+    item_and_desc(
+        "--header FMT",
+        "In record modes, render FMT once before all of the records",
+    );
+    item_and_desc(
+        "--footer FMT",
+        "In record modes, render FMT after all of the records, with {count} and the {sum_X}, {min_X} and {max_X} of numeric fields",
+    );
+    item_and_desc(
+        "--separator FMT",
+        "In record modes, render FMT between records, a single character fills the line",
+    );
+    item_and_desc(
+        "--stats",
+        "In record modes, report records processed, failures and throughput on stderr",
+    );
+    item_and_desc(
+        "-o FILE",
+        "Write the output to FILE (also --output-file) instead of stdout, without styling",
+    );
+    item_and_desc(
+        "--tee[=LIST]",
+        "Also write to stdout, or to the comma separated streams (stdout, stderr) in LIST",
+    );
+    item_and_desc(
+        "--printf",
+        "Use printf(1) conversions (%s, %d, %5.2f, %x, %e and more, with flags) instead of specs",
+    );
+    item_and_desc(
+        "--rust-fmt",
+        "Render args like format! would, as integers, floats or strings depending on how they look",
+    );
+    item_and_desc(
+        "--python",
+        "Render args like python's str.format would, with conversions ({0!r}), grouping ({:,}) and its types",
+    );
+    item_and_desc(
+        "--printf-compat-check",
+        "Compare --printf with the system printf on a set of shared cases, failing on any difference",
+    );
+    item_and_desc(
+        "--record FILE",
+        "With snapshot, save the output to FILE instead of printing it, styling included",
+    );
+    item_and_desc(
+        "--verify FILE",
+        "With snapshot, compare the output with FILE and show a diff if they differ",
+    );
+    item_and_desc(
+        "--csv, --tsv",
+        "With table, read stdin as comma or tab separated rows (detected from the first line)",
+    );
+    item_and_desc(
+        "--headers",
+        "With table, show the first row as the column headers",
+    );
+    item_and_desc(
+        "--align SPECS",
+        "With table, align each column left (< or l), center (^ or c) or right (> or r)",
+    );
+    item_and_desc(
+        "--border STYLE",
+        "With table, draw single, double, rounded or ascii lines around and between the cells",
+    );
+    item_and_desc(
+        "--deterministic",
+        "Pin {now} to the unix epoch, seed {rand} and {uuid} with 0, and fix the system info, for snapshot tests",
+    );
+    item_and_desc("--utc", "Show {now} in UTC instead of the local time");
+    item_and_desc(
+        "--seed N",
+        "Seed {rand} and {uuid} with N so they produce the same values on every run",
+    );
+    item_and_desc(
+        "--stats-perf",
+        "Report parse and render times, allocations and bytes written on stderr when done",
+    );
+    item_and_desc(
+        "--bold, --dim, ..",
+        "Show the whole output bold or dim, or with --italic and --underline, italic or underlined",
+    );
+    item_and_desc(
+        "--color WHEN",
+        "Color the output always, never, or on terminals (auto), overriding the environment",
+    );
+    item_and_desc(
+        "--highlight",
+        "Underline every substituted ARG in a color of its own, to see where the output came from",
+    );
+    item_and_desc(
+        "--pad-lines",
+        "Pad every line of a multi-line ARG to the width of its spec, keeping it rectangular",
+    );
+    item_and_desc(
+        "--rainbow",
+        "Color the output one character at a time with the colors of a rainbow",
+    );
+    item_and_desc(
+        "--gradient A B",
+        "Color every line of the output with a gradient from color A to color B (red, #ff8800, ..)",
+    );
+    item_and_desc(
+        "--plain",
+        "Remove all escape sequences and control characters from the output, no matter where from",
+    );
+    item_and_desc(
+        "--copy",
+        "Also put the output on the system clipboard, with pbcopy, wl-copy, xclip, xsel or clip.exe, or else through the terminal (OSC 52)",
+    );
+    item_and_desc(
+        "--export",
+        "Print NAME='value' lines for the named placeholders (or named ARGS) to eval in a shell",
+    );
+    item_and_desc(
+        "--output F",
+        "Write html (styling as spans) or an svg image, or in record modes and table csv or tsv rows",
+    );
+    item_and_desc(
+        "--dry-run",
+        "Instead of the output, show the argument each spec consumes and what it is replaced with",
+    );
+    println!();
+    // Environment variables
+    header("Environment");
+    item_and_desc(
+        "NO_COLOR",
+        "Disable colored output when set to any non-empty value",
+    );
+    item_and_desc("CLICOLOR", "Disable colored output when set to 0");
+    item_and_desc(
+        "CLICOLOR_FORCE",
+        "Force colored output when set to anything but 0, overriding the above",
+    );
+    item_and_desc("TERM", "Colored output is disabled when TERM is \"dumb\"");
     println!();
     // Format specifier details
     header("Format specifiers");
@@ -156,6 +502,10 @@ pub fn print_usage_long(bin: &str) -> crate::Result<()> {
         "{name}",
         "Named specifier, corresponding to ARGS in the form of \"name = value\"",
     );
+    item_and_desc(
+        "\\u{1F600}",
+        "Unicode escape, replaced with the character of that hexadecimal code point before parsing",
+    );
     item_and_desc(
         "{:5}, {:10}, {:n}",
         "Width specifier, dictates how much space the ARG will occupy",
@@ -164,6 +514,165 @@ pub fn print_usage_long(bin: &str) -> crate::Result<()> {
         "{:<}, {:^}, {:>}",
         "Alignment specifier, aligns ARG to the left, center, or right (useless without width)",
     );
+    item_and_desc(
+        "{now}, {now:%H:%M}",
+        "The current local time (TZ is honoured), as RFC 3339 or with a strftime format (%Y, %m, %d, %H, %M, %S, %Z, ..)",
+    );
+    item_and_desc("{#}", "The number of ARGs given, named ones included");
+    item_and_desc(
+        "{rand}, {rand:1..=6}",
+        "A random 32 bit number, or one in a range (1..7 excludes 7, 1..=7 includes it)",
+    );
+    item_and_desc("{uuid}", "A random version 4 UUID");
+    item_and_desc("{remaining}", "The seconds left of --countdown");
+    item_and_desc(
+        "{hostname}, {user}, {cwd}, {pid}",
+        "The machine name, user, working directory and process id, unless an ARG has that name",
+    );
+    item_and_desc(
+        "{*}, {*:, }",
+        "Splat, every positional ARG no other spec uses, joined by spaces or by what follows the colon",
+    );
+    item_and_desc(
+        "{?name:text}",
+        "Conditional, renders text (which may use specs of its own) only if the ARG name is given and not blank",
+    );
+    item_and_desc(
+        "{=2*{0}+1}, {= {a} / {b} :.2}",
+        "Expression, the result of arithmetic (+ - * / % ^ and parentheses) on numbers and ARGs",
+    );
+    item_and_desc(
+        "{name:upper}, {:snake:>10}",
+        "Filters, change the ARG before it is formatted and can be chained with colons",
+    );
+    item_and_desc(
+        "{:upper}, {:lower}, {:title}",
+        "Filters that uppercase, lowercase, or capitalize every word",
+    );
+    item_and_desc(
+        "{:snake}, {:camel}",
+        "Filters that split the ARG into words and join them like snake_case or camelCase",
+    );
+    item_and_desc(
+        "{:trim}, {:reverse}, {:repeat(n)}",
+        "Filters that strip surrounding whitespace, reverse, or repeat the ARG n times",
+    );
+    item_and_desc(
+        "{:slice(start, end)}",
+        "Filter keeping the characters from start up to end, negative positions count from the end",
+    );
+    item_and_desc(
+        "{:replace(old, new)}",
+        "Filter replacing every old with new, use \\, for a comma",
+    );
+    item_and_desc(
+        "{:bytes}, {:bytes(binary, 2)}",
+        "Filter showing a number of bytes like 1.5 MB, or 1.46 MiB with binary units and 2 decimals",
+    );
+    item_and_desc(
+        "{:duration}, {:duration(clock)}",
+        "Filter showing a number of seconds like 1d 2h 3m 4s, or like 1:02:03:04",
+    );
+    item_and_desc(
+        "{:b64}, {:b64d}",
+        "Filters encoding the ARG as base64, or decoding it from base64",
+    );
+    item_and_desc(
+        "{:urlencode}, {:urldecode}",
+        "Filters percent encoding the ARG so it is safe in a URL, or decoding it",
+    );
+    item_and_desc(
+        "{:json}",
+        "Filter escaping quotes, backslashes and control characters so the ARG fits in a JSON string",
+    );
+    item_and_desc(
+        "{:sh}",
+        "Filter quoting the ARG so a POSIX shell takes it as a single word, with nothing expanded",
+    );
+    item_and_desc(
+        "{:html}",
+        "Filter escaping <, >, &, and quotes so the ARG fits in HTML text or attributes",
+    );
+    item_and_desc(
+        "{:sha256}, {:md5}",
+        "Filters replacing the ARG with its SHA-256 or MD5 digest in hexadecimal",
+    );
+    item_and_desc(
+        "{:match(re)}, {:capture(re, n)}",
+        "Filters keeping the first match of the regex re, or its capture group n (1 if not given)",
+    );
+    item_and_desc(
+        "{msg:red}, {:color=red}",
+        "Style, shows ARG in a color (black, red, .., white, bright_red, ..), dropped when output isn't a terminal",
+    );
+    item_and_desc(
+        "{title:bold.underline}",
+        "Style keywords (bold, dim, italic, underline, inverse, strike), combined with each other and colors by dots",
+    );
+    item_and_desc(
+        "{warn:black.on_yellow}",
+        "Background colors, any color prefixed with on_",
+    );
+    item_and_desc(
+        "{:#ff8800}, {:color256=208}",
+        "Hex and 256 palette colors, downgraded to the closest color the terminal supports",
+    );
+    item_and_desc(
+        "{:color_if(<0,red,green)}",
+        "Filter styling ARG with the first style if the comparison (<, <=, >, >=, ==, !=) holds, else the second",
+    );
+    item_and_desc(
+        "{:term}, {:-^term}",
+        "Terminal width, pads ARG to the width of the terminal, for full width separators and columns",
+    );
+    item_and_desc(
+        "{:{w}}, {:.{p}}",
+        "Width or precision from another ARG, by name or position, so scripts can compute them",
+    );
+    item_and_desc(
+        "{:.5}, {:>8.3}",
+        "Precision specifier, rounds numbers to that many decimals and cuts other ARGs off at that many cells",
+    );
+    item_and_desc(
+        "{:+}, {:>+8.2}",
+        "Sign specifier, numbers that aren't negative get an explicit +",
+    );
+    item_and_desc(
+        "{:x}, {:X}, {:b}",
+        "Type specifier, shows integer ARGs in hexadecimal (x or X), octal (o) or binary (b)",
+    );
+    item_and_desc(
+        "{:e}, {:.3E}",
+        "Scientific notation, shows number ARGs like 1.235e5 (or 1.235E5)",
+    );
+    item_and_desc(
+        "{:%}, {:.1%}",
+        "Percentage, shows number ARGs multiplied by 100 with a percent sign, like 42.0%",
+    );
+    item_and_desc(
+        "{:?}, {:>10?}",
+        "Debug form, quotes ARG and escapes newlines, tabs and other control characters",
+    );
+    item_and_desc(
+        "{:#x}, {:#b}",
+        "Alternate form, prefixes integers shown in another base with 0x, 0o or 0b",
+    );
+    item_and_desc(
+        "{:*^}, {:->}",
+        "Fill character, pads ARG with the character before the alignment instead of spaces",
+    );
+    item_and_desc(
+        "{:05}, {:#010x}",
+        "Zero padding, pads numbers with zeros after their sign and prefix, ignoring alignment",
+    );
+    item_and_desc(
+        "{:1$}, {:.*}",
+        "Rust's counts, width or precision from the ARG at that position, or from the next ARG",
+    );
+    item_and_desc(
+        "{0!r}, {:,}, {:.2f}",
+        "With --python, conversions, digit grouping and python's d, f, g, %, c and s types",
+    );
     println!();
 
     // Usages Examples
@@ -0,0 +1,151 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::ansi;
+
+/// Space left between two columns
+pub const COLUMN_GAP: usize = 2;
+
+/// Lays `items` out in columns like `ls` does, filling each column top to bottom before moving on
+/// to the next. Every column is as wide as its own widest item, so the columns are balanced rather
+/// than all as wide as the widest item overall. When `count` is `None` as many columns as fit into
+/// `width` are used.
+pub fn columns<S: AsRef<str>>(items: &[S], count: Option<usize>, width: usize) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+
+    let widths = items
+        .iter()
+        .map(|i| ansi::width(i.as_ref()))
+        .collect::<Vec<_>>();
+    let rows = match count {
+        Some(count) => items.len().div_ceil(count.clamp(1, items.len())),
+        None => fitted_rows(&widths, width),
+    };
+    let col_widths = column_widths(&widths, rows);
+
+    let mut output = Vec::with_capacity(rows);
+    for row in 0..rows {
+        let mut line = String::new();
+        let cells =
+            (0..col_widths.len()).filter_map(|col| Some((col, items.get(col * rows + row)?)));
+        let mut cells = cells.peekable();
+        while let Some((col, cell)) = cells.next() {
+            let cell = cell.as_ref();
+            line.push_str(cell);
+            if cells.peek().is_some() {
+                let pad = col_widths[col] - ansi::width(cell) + COLUMN_GAP;
+                line.push_str(&" ".repeat(pad));
+            }
+        }
+        output.push(line);
+    }
+
+    output.join("\n")
+}
+
+/// The width of every column when items of `widths` fill `rows` rows, column by column.
+fn column_widths(widths: &[usize], rows: usize) -> Vec<usize> {
+    widths
+        .chunks(rows)
+        .map(|col| col.iter().copied().max().unwrap_or(0))
+        .collect()
+}
+
+/// The fewest rows, and so the most columns, that items of `widths` fit into `width` cells with.
+/// Laying everything out as wide as the widest item always fits, so nothing past that is tried.
+fn fitted_rows(widths: &[usize], width: usize) -> usize {
+    let widest = widths.iter().copied().max().unwrap_or(0);
+    let uniform = ((width + COLUMN_GAP) / (widest + COLUMN_GAP)).clamp(1, widths.len());
+    let most = widths.len().div_ceil(uniform);
+    let narrowest = widths.iter().copied().min().unwrap_or(0);
+    let fewest = widths
+        .len()
+        .div_ceil(((width + COLUMN_GAP) / (narrowest + COLUMN_GAP)).max(1));
+
+    (fewest..most)
+        .find(|&rows| {
+            let col_widths = column_widths(widths, rows);
+            col_widths.iter().sum::<usize>() + COLUMN_GAP * (col_widths.len() - 1) <= width
+        })
+        .unwrap_or(most)
+}
+
+/// Lays `rows` out as a table with left aligned columns, each as wide as its widest cell.
+pub fn table<S: AsRef<str>>(rows: &[Vec<S>]) -> String {
+    let mut widths = Vec::new();
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            let w = ansi::width(cell.as_ref());
+            match widths.get_mut(i) {
+                Some(widest) if *widest < w => *widest = w,
+                Some(_) => {}
+                None => widths.push(w),
+            }
+        }
+    }
+
+    rows.iter()
+        .map(|row| {
+            let mut line = String::new();
+            for (i, cell) in row.iter().enumerate() {
+                let cell = cell.as_ref();
+                line.push_str(cell);
+                if i + 1 < row.len() {
+                    line.push_str(&" ".repeat(widths[i] - ansi::width(cell) + COLUMN_GAP));
+                }
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn column_major() {
+        let items = ["a", "bb", "c", "dddd", "e"];
+        assert_eq!(columns(&items, Some(2), 80), "a   dddd\nbb  e\nc");
+        assert_eq!(columns(&items, Some(5), 80), "a  bb  c  dddd  e");
+        assert_eq!(columns(&items, Some(4), 80), "a   c     e\nbb  dddd");
+        assert_eq!(columns(&items, Some(1), 80), "a\nbb\nc\ndddd\ne");
+    }
+
+    #[test]
+    fn auto_count() {
+        let items = ["one", "two", "three", "four", "five", "six"];
+        // Only the middle column needs to be 5 wide, so 3 fit in 18 cells
+        assert_eq!(
+            columns(&items, None, 18),
+            "one  three  five\ntwo  four   six"
+        );
+        assert_eq!(
+            columns(&items, None, 15),
+            "one    four\ntwo    five\nthree  six"
+        );
+        assert_eq!(columns(&items, None, 3), items.join("\n"));
+        assert_eq!(columns::<&str>(&[], None, 80), "");
+    }
+
+    #[test]
+    fn tables() {
+        let rows = vec![
+            vec!["SPEC", "ARG"],
+            vec!["{name:>10}", "name"],
+            vec!["{}", "#0"],
+        ];
+        assert_eq!(
+            table(&rows),
+            "SPEC        ARG\n{name:>10}  name\n{}          #0"
+        );
+        assert_eq!(table::<&str>(&[]), "");
+    }
+}
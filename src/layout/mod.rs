@@ -0,0 +1,200 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+mod columns;
+mod wrap;
+
+pub use columns::{columns, table, COLUMN_GAP};
+pub use wrap::{wrap, WrapOptions};
+
+use crate::{ansi, Alignment};
+
+/// Appended to lines that were cut short by [`clamp`]
+pub const TRUNCATION_MARKER: &str = "…";
+
+/// Truncates every line of `text` that would occupy more than `width` cells.
+pub fn clamp(text: &str, width: usize) -> String {
+    text.split('\n')
+        .map(|line| ansi::truncate(line, width, TRUNCATION_MARKER))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Turns a separator into a rule spanning `width` cells when it is a single visible character
+/// (like `-` or `─`), keeping any styling around it. Anything else is returned unchanged.
+pub fn rule(separator: &str, width: usize) -> String {
+    let visible = ansi::strip(separator);
+    if ansi::grapheme_indices(&visible).count() != 1 || ansi::width(&visible) == 0 {
+        return separator.to_string();
+    }
+
+    let fill = visible.repeat(width / ansi::width(&visible));
+    separator.replacen(visible.as_str(), &fill, 1)
+}
+
+/// A horizontal rule `width` cells wide, repeating `pattern` (like `-` or `=-`) for as long as it
+/// fits. Escape sequences in the pattern are left out.
+pub fn hr(pattern: &str, width: usize) -> String {
+    let pattern = ansi::strip(pattern);
+    let graphemes = ansi::grapheme_indices(&pattern)
+        .map(|(_, g)| (g, ansi::width(g)))
+        .filter(|&(_, w)| w > 0)
+        .collect::<Vec<_>>();
+
+    let mut rule = String::new();
+    let mut filled = 0usize;
+    for &(grapheme, w) in graphemes.iter().cycle() {
+        if filled + w > width {
+            break;
+        }
+        rule.push_str(grapheme);
+        filled += w;
+    }
+    rule
+}
+
+/// The fewest cells the bar of [`progress`] is given, even when that overflows the width
+pub const MIN_PROGRESS_BAR: usize = 10;
+
+/// A progress bar of `current` out of `total` with its percentage, after `label` if it isn't
+/// empty, filling `width` cells altogether. With `ascii` the bar is drawn with `#` and `-`.
+pub fn progress(label: &str, current: usize, total: usize, width: usize, ascii: bool) -> String {
+    let current = current.min(total);
+    let label = match label.is_empty() {
+        true => String::new(),
+        false => format!("{} ", label),
+    };
+    let percent = format!("{:>3}%", current * 100 / total);
+    // The brackets and the space before the percentage take up three more cells
+    let cells = width
+        .saturating_sub(ansi::width(&label) + percent.len() + 3)
+        .max(MIN_PROGRESS_BAR);
+    let filled = cells * current / total;
+    let (done, left) = match ascii {
+        true => ("#", "-"),
+        false => ("█", "░"),
+    };
+    format!(
+        "{}[{}{}] {}",
+        label,
+        done.repeat(filled),
+        left.repeat(cells - filled),
+        percent
+    )
+}
+
+/// Aligns `text` as a single block within `width` cells: every line is shifted by the same amount,
+/// based on the widest line, so the lines keep their positions relative to each other.
+pub fn block_align(text: &str, align: Alignment, width: usize) -> String {
+    let widest = text.split('\n').map(ansi::width).max().unwrap_or(0);
+    let shift = match align {
+        Alignment::Left => 0,
+        Alignment::Center => width.saturating_sub(widest) / 2,
+        Alignment::Right => width.saturating_sub(widest),
+    };
+    if shift == 0 {
+        return text.to_string();
+    }
+
+    let pad = " ".repeat(shift);
+    text.split('\n')
+        .map(|line| {
+            if line.is_empty() {
+                String::new()
+            } else {
+                format!("{}{}", pad, line)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Centers every line of `text` within `width` cells on its own, unlike [`block_align`]. Lines
+/// that are as wide as `width` or wider are left as they are.
+pub fn center(text: &str, width: usize) -> String {
+    text.split('\n')
+        .map(|line| {
+            let shift = width.saturating_sub(ansi::width(line)) / 2;
+            match line.is_empty() {
+                true => String::new(),
+                false => format!("{}{}", " ".repeat(shift), line),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn clamp_lines() {
+        let text = "short\na much longer line of text\n\nend";
+        assert_eq!(clamp(text, 10), "short\na much lo…\n\nend");
+        assert_eq!(clamp(text, 100), text);
+    }
+
+    #[test]
+    fn rules() {
+        assert_eq!(rule("-", 5), "-----");
+        #[cfg(feature = "unicode")]
+        assert_eq!(rule("读", 5), "读读");
+        assert_eq!(rule("\x1b[2m─\x1b[0m", 3), "\x1b[2m───\x1b[0m");
+        assert_eq!(rule("--", 5), "--");
+        assert_eq!(rule("", 5), "");
+    }
+
+    #[test]
+    fn horizontal_rules() {
+        assert_eq!(hr("-", 5), "-----");
+        assert_eq!(hr("=-", 5), "=-=-=");
+        assert_eq!(hr("\x1b[2m─\x1b[0m", 3), "───");
+        #[cfg(feature = "unicode")]
+        assert_eq!(hr("读", 5), "读读");
+        assert_eq!(hr("", 5), "");
+        assert_eq!(hr("-", 0), "");
+    }
+
+    #[test]
+    fn progress_bars() {
+        assert_eq!(progress("", 5, 10, 17, false), "[█████░░░░░]  50%");
+        assert_eq!(progress("copy", 1, 3, 22, false), "copy [███░░░░░░░]  33%");
+        assert_eq!(progress("", 12, 10, 17, false), "[██████████] 100%");
+        assert_eq!(progress("", 0, 4, 21, false), "[░░░░░░░░░░░░░░]   0%");
+        assert_eq!(progress("", 5, 10, 17, true), "[#####-----]  50%");
+        assert_eq!(
+            progress("too long for it", 1, 2, 10, false),
+            "too long for it [█████░░░░░]  50%"
+        );
+    }
+
+    #[test]
+    fn aligned_blocks() {
+        let text = "total: 10\nok: 9\n\nfailed: 1";
+        assert_eq!(
+            block_align(text, Alignment::Right, 12),
+            "   total: 10\n   ok: 9\n\n   failed: 1"
+        );
+        assert_eq!(
+            block_align(text, Alignment::Center, 14),
+            "  total: 10\n  ok: 9\n\n  failed: 1"
+        );
+        assert_eq!(block_align(text, Alignment::Left, 40), text);
+        assert_eq!(block_align(text, Alignment::Right, 5), text);
+    }
+
+    #[test]
+    fn centered_lines() {
+        let text = "== Install ==\n\n\x1b[1mok\x1b[0m\na line that is too long";
+        assert_eq!(
+            center(text, 19),
+            "   == Install ==\n\n        \x1b[1mok\x1b[0m\na line that is too long"
+        );
+        assert_eq!(center("odd", 6), " odd");
+    }
+}
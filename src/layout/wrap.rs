@@ -0,0 +1,278 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::ansi::{self, Segment};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WrapOptions {
+    /// The maximum number of cells per line
+    pub width: usize,
+    /// How far continuation lines are indented
+    pub hang: usize,
+    /// Whether words that can't fit on a line of their own are broken up
+    pub hard: bool,
+    /// Whether every line but the last of a paragraph is stretched to the full width
+    pub justify: bool,
+}
+
+impl WrapOptions {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            hang: 0,
+            hard: false,
+            justify: false,
+        }
+    }
+
+    pub fn hang(self, hang: usize) -> Self {
+        Self { hang, ..self }
+    }
+
+    pub fn hard(self, hard: bool) -> Self {
+        Self { hard, ..self }
+    }
+
+    pub fn justify(self, justify: bool) -> Self {
+        Self { justify, ..self }
+    }
+}
+
+/// Wraps every line of `text` at word boundaries. Words longer than the available width are left
+/// on a line of their own, unless [`WrapOptions::hard`] is set, in which case they are broken up
+/// exactly at the width limit (without splitting grapheme clusters or escape sequences).
+pub fn wrap(text: &str, opts: WrapOptions) -> String {
+    text.split('\n')
+        .flat_map(|line| wrap_line(line, opts))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn wrap_line(line: &str, opts: WrapOptions) -> Vec<String> {
+    let hang = opts.hang.min(opts.width.saturating_sub(1));
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut current_width = 0usize;
+    let mut pending_space = "";
+
+    let available = |lines: &Vec<String>| {
+        if lines.is_empty() {
+            opts.width
+        } else {
+            opts.width - hang
+        }
+    };
+
+    for token in words(line) {
+        let mut word = token.trim_end_matches(' ');
+        let space = &token[word.len()..];
+        let mut word_width = ansi::width(word);
+
+        if current_width > 0 && current_width + pending_space.len() + word_width > available(&lines)
+        {
+            lines.push(std::mem::take(&mut current));
+            current_width = 0;
+        } else {
+            current.push_str(pending_space);
+            current_width += pending_space.len();
+        }
+
+        if opts.hard {
+            while current_width + word_width > available(&lines) {
+                let room = available(&lines).saturating_sub(current_width);
+                if room == 0 && current_width == 0 {
+                    break;
+                }
+                let (head, tail) = ansi::split_at_width(word, room);
+                current.push_str(head);
+                lines.push(std::mem::take(&mut current));
+                current_width = 0;
+                word = tail;
+                word_width = ansi::width(word);
+            }
+        }
+
+        current.push_str(word);
+        current_width += word_width;
+        pending_space = space;
+    }
+    lines.push(current);
+
+    if opts.justify {
+        let last = lines.len() - 1;
+        for (i, line) in lines.iter_mut().enumerate().take(last) {
+            let width = if i == 0 {
+                opts.width
+            } else {
+                opts.width - hang
+            };
+            *line = justify(line, width);
+        }
+    }
+    let indent = " ".repeat(hang);
+    for line in lines.iter_mut().skip(1) {
+        line.insert_str(0, &indent);
+    }
+    lines
+}
+
+/// Stretches `line` to `width` cells by widening the spaces between its words, the leftmost ones
+/// first when they can't all grow by the same amount. Indentation is left alone.
+fn justify(line: &str, width: usize) -> String {
+    let words = words(line);
+    let indented = usize::from(words.first().is_some_and(|w| w.trim().is_empty()));
+    let gaps = words.len().saturating_sub(indented + 1);
+    let extra = width.saturating_sub(ansi::width(line));
+    if gaps == 0 || extra == 0 {
+        return line.to_string();
+    }
+
+    let mut justified = String::with_capacity(line.len() + extra);
+    for (i, word) in words.iter().enumerate() {
+        justified.push_str(word);
+        if i >= indented && i < words.len() - 1 {
+            let gap = i - indented;
+            justified.push_str(&" ".repeat(extra / gaps + usize::from(gap < extra % gaps)));
+        }
+    }
+    justified
+}
+
+/// Splits `line` into words, each carrying the spaces that follow it. Escape sequences stick to
+/// the word they precede, and spaces inside of them are not treated as word boundaries.
+fn words(line: &str) -> Vec<&str> {
+    let mut words = Vec::new();
+    let mut start = 0usize;
+    let mut pos = 0usize;
+    let mut in_space = false;
+
+    for seg in ansi::segments(line) {
+        match seg {
+            Segment::Escape(e) => {
+                if in_space {
+                    words.push(&line[start..pos]);
+                    start = pos;
+                    in_space = false;
+                }
+                pos += e.len();
+            }
+            Segment::Text(t) => {
+                for (i, c) in t.char_indices() {
+                    if c == ' ' {
+                        in_space = true;
+                    } else if in_space {
+                        words.push(&line[start..pos + i]);
+                        start = pos + i;
+                        in_space = false;
+                    }
+                }
+                pos += t.len();
+            }
+        }
+    }
+
+    if start < line.len() {
+        words.push(&line[start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn word_splitting() {
+        assert_eq!(words("a bb  ccc"), vec!["a ", "bb  ", "ccc"]);
+        assert_eq!(words("  indented"), vec!["  ", "indented"]);
+        assert_eq!(
+            words("a \x1b[31mred\x1b[0m b"),
+            vec!["a ", "\x1b[31mred\x1b[0m ", "b"]
+        );
+        assert!(words("").is_empty());
+    }
+
+    #[test]
+    fn soft_wrap() {
+        let text = "the quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            wrap(text, WrapOptions::new(10)),
+            "the quick\nbrown fox\njumps over\nthe lazy\ndog"
+        );
+        assert_eq!(wrap(text, WrapOptions::new(100)), text);
+        assert_eq!(
+            wrap("a verylongword b", WrapOptions::new(5)),
+            "a\nverylongword\nb"
+        );
+        assert_eq!(
+            wrap("first line\n  second line", WrapOptions::new(8)),
+            "first\nline\n  second\nline"
+        );
+    }
+
+    #[test]
+    fn hanging_indent() {
+        let text = "usage: fmt with some extra words";
+        assert_eq!(
+            wrap(text, WrapOptions::new(12).hang(7)),
+            "usage: fmt\n       with\n       some\n       extra\n       words"
+        );
+    }
+
+    #[test]
+    fn hard_wrap() {
+        let opts = WrapOptions::new(4).hard(true);
+        assert_eq!(wrap("abcdefghij", opts), "abcd\nefgh\nij");
+        assert_eq!(wrap("ab cdefghij k", opts), "ab\ncdef\nghij\nk");
+        #[cfg(feature = "unicode")]
+        assert_eq!(wrap("读文读文读", opts), "读文\n读文\n读");
+        assert_eq!(
+            wrap("\x1b[31mabcdef\x1b[0m", opts),
+            "\x1b[31mabcd\nef\x1b[0m"
+        );
+        assert_eq!(
+            wrap("label abcdefgh", WrapOptions::new(6).hang(2).hard(true)),
+            "label\n  abcd\n  efgh"
+        );
+    }
+
+    #[test]
+    fn justified_wrap() {
+        let text = "the quick brown fox jumps over the lazy dog";
+        assert_eq!(
+            wrap(text, WrapOptions::new(10).justify(true)),
+            "the  quick\nbrown  fox\njumps over\nthe   lazy\ndog"
+        );
+        assert_eq!(
+            wrap("  a b c d e", WrapOptions::new(8).justify(true)),
+            "  a  b c\nd e"
+        );
+        assert_eq!(
+            wrap(
+                "a \x1b[1mbold\x1b[0m b c",
+                WrapOptions::new(9).justify(true)
+            ),
+            "a  \x1b[1mbold\x1b[0m b\nc"
+        );
+        assert_eq!(
+            wrap(
+                "usage: fmt with args",
+                WrapOptions::new(11).hang(2).justify(true)
+            ),
+            "usage:  fmt\n  with args"
+        );
+    }
+
+    #[test]
+    fn styled_wrap() {
+        let text = "\x1b[31mred words\x1b[0m plain";
+        assert_eq!(
+            wrap(text, WrapOptions::new(9)),
+            "\x1b[31mred words\x1b[0m\nplain"
+        );
+    }
+}
@@ -1,58 +1,675 @@
 #![feature(round_char_boundary)]
 #![allow(dead_code, unused)]
 
+mod ansi;
+mod cli;
+mod clipboard;
+mod color;
+mod config;
+mod decor;
+mod encode;
 mod fmt;
 mod help;
+mod layout;
+mod output;
+mod perf;
+mod printf;
+mod qr;
+mod records;
+mod sink;
+mod snapshot;
+mod spinner;
+mod table;
+mod term;
 
-use std::{env, sync::atomic::AtomicBool};
+use std::{
+    env,
+    io::{IsTerminal, Write},
+    sync::atomic::AtomicBool,
+};
 
 pub use fmt::*;
 
 static PRINT_DEBUG: AtomicBool = AtomicBool::new(false);
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(err) = run() {
+        error(&err);
+        std::process::exit(1);
+    }
+}
+
+/// Tells the user about an error on stderr.
+fn error(err: &Error) {
+    let level = term::Capabilities::stderr().color;
+    let label = color::paint_at("error", color::Color::Basic(9), level);
+    eprintln!("{}: {}", label, err);
+}
+
+/// Tells the user about something that went wrong without failing the run.
+fn warn(message: &str) {
+    let level = term::Capabilities::stderr().color;
+    let label = color::paint_at("warning", color::Color::Basic(11), level);
+    eprintln!("{}: {}", label, message);
+}
+
+fn run() -> Result<()> {
     let bin = env::args().next().expect("Unable to get env::args[0]");
-    let mut all_args = env::args().skip(1).collect::<Vec<_>>();
-    match all_args.len() {
-        0 => help::print_usage(&bin),
-        1 => {
-            if &all_args[0] == "--help" {
-                help::print_usage_long(&bin)
-            } else if &all_args[0] == "-h" {
-                help::print_usage(&bin)
-            } else {
-                print_string(&all_args[0])
-            }
+    let ansi = term::enable_ansi();
+    let mut opts = cli::Options::parse(env::args().skip(1))?;
+    // An unfinished progress bar is drawn over by the next one on a terminal, so it ends no line
+    if matches!(opts.progress, Some((current, total)) if current < total)
+        && std::io::stdout().is_terminal()
+    {
+        opts.no_newline = true;
+    }
+    // Args from stdin go after any given on the command line
+    if opts.stdin_args {
+        let input = std::io::read_to_string(std::io::stdin())?;
+        opts.args.extend(input.lines().map(String::from));
+    }
+    let config = match opts.no_config {
+        true => config::Config::default(),
+        false => config::Config::load()?,
+    };
+    // Flags come first, then the environment, then the config file. Consoles that can't interpret
+    // escape sequences get plain output, unless --color insists.
+    match (opts.color, config.color) {
+        (Some(choice), _) if ansi || choice == color::ColorChoice::Always => {
+            color::set_choice(choice)
+        }
+        _ if !ansi => color::set_choice(color::ColorChoice::Never),
+        (None, Some(choice)) if color::ColorChoice::from_env() == color::ColorChoice::Auto => {
+            color::set_choice(choice)
         }
-        _ => {
-            if &all_args[0] == "--debug" || &all_args[0] == "-d" || &all_args[0] == "-D" {
-                PRINT_DEBUG.store(true, std::sync::atomic::Ordering::Relaxed);
-                all_args.remove(0);
+        _ => {}
+    }
+    if let Some(fill) = config.fill {
+        fmt::set_fill(fill);
+    }
+    // A QR code wouldn't survive being wrapped
+    if opts.wrap.is_none() && !opts.qr {
+        opts.wrap = config.wrap.map(cli::Width::Fixed);
+    }
+    if let Some(ref fmt_str) = opts.fmt_str {
+        opts.fmt_str = Some(config.resolve(fmt_str)?.into_owned());
+    }
+    fmt::style::set_themes(config.themes);
+    // Snapshots and html or svg output keep the styling, so it is produced even when piped. Errors
+    // go to stderr, which is styled only if it is a terminal all the same.
+    if opts.snapshot.is_some() || opts.output.is_styled() {
+        term::set_capabilities(term::Capabilities::new(true));
+    }
+    if opts.ascii || !term::unicode_locale() {
+        opts.ascii_fallback();
+    }
+    if let Some(width) = opts.width {
+        term::set_width(width);
+    }
+    if opts.debug {
+        PRINT_DEBUG.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    if opts.stats_perf {
+        perf::enable();
+    }
+    if let Some(seed) = opts.seed {
+        builtin::seed(seed);
+    }
+    if opts.deterministic {
+        builtin::deterministic();
+    }
+    if opts.utc {
+        builtin::utc();
+    }
+    dialect::set(opts.dialect);
+    if opts.highlight {
+        fmt::highlight();
+    }
+    if opts.pad_lines {
+        fmt::pad_lines();
+    }
+
+    let result = run_with(&bin, &opts);
+    if opts.stats_perf {
+        eprintln!("{}", perf::report());
+    }
+    result
+}
+
+fn run_with(bin: &str, opts: &cli::Options) -> Result<()> {
+    match opts.help {
+        Some(cli::Help::Long) => return help::print_usage_long(bin),
+        Some(cli::Help::Short) => return help::print_usage(bin),
+        None => {}
+    }
+
+    if opts.printf_compat_check {
+        println!("{}", printf::compat_check()?);
+        return Ok(());
+    }
+
+    if let Some(ref table) = opts.table {
+        let input = std::io::read_to_string(std::io::stdin())?;
+        let output = match opts.output.is_tabular() {
+            true => table.convert(&input, opts.output),
+            false => table.render(&input, color::effective_level()),
+        };
+        let mut printer = Printer::new(opts)?;
+        printer.print(&output)?;
+        return printer.finish();
+    }
+    if let Some(ref pattern) = opts.hr {
+        let mut printer = Printer::new(opts)?;
+        printer.print(&layout::hr(pattern, term::width()))?;
+        return printer.finish();
+    }
+
+    // Escapes like \u{1F600} are decoded before anything else, so their brackets aren't specs
+    let fmt_str = match opts.fmt_str {
+        Some(ref s) => encode::unicode_unescape(s),
+        None if opts.progress.is_some() => String::new(),
+        None => return help::print_usage(bin),
+    };
+    let fmt_str = match opts.escapes {
+        true => encode::echo_unescape(&fmt_str),
+        false => fmt_str,
+    };
+    fmt::check_placeholders(&fmt_str)?;
+    let fmt_str = fmt_str.as_str();
+
+    if opts.dry_run {
+        return dry_run(fmt_str, &opts.args);
+    }
+    if opts.export {
+        let mut printer = Printer::new(opts)?;
+        printer.print(&export(fmt_str, &opts.args)?)?;
+        return printer.finish();
+    }
+
+    if let Some(secs) = opts.countdown {
+        return countdown(opts, fmt_str, secs);
+    }
+    if let Some(interval) = opts.every {
+        return watch(opts, fmt_str, interval);
+    }
+
+    let output = match opts.records {
+        Some(mode) => return run_records(opts, mode, fmt_str),
+        // Every line gets a newline of its own when printed, so a trailing one would be doubled
+        None if opts.printf => {
+            let output = printf::format(fmt_str, &opts.args)?;
+            output.strip_suffix('\n').unwrap_or(&output).to_string()
+        }
+        None if opts.args.is_empty() => without_args(fmt_str)?,
+        None => format(fmt_str, &opts.args)?,
+    };
+    if opts.spinner {
+        return run_spinner(opts, &output);
+    }
+    match opts.title {
+        Some(cli::Title::Only) => return term::set_title(&output),
+        Some(cli::Title::Also) => term::set_title(&output)?,
+        None => {}
+    }
+    let output = match opts.qr {
+        true => qr::render(&output)?,
+        false => output,
+    };
+    let output = match opts.progress {
+        // The last column is left alone, since some terminals move on to the next line once it is
+        // written to, and the carriage return takes the cursor back to redraw the line in place
+        Some((current, total)) => {
+            let width = term::width().saturating_sub(1);
+            let bar = layout::progress(&output, current, total, width, opts.ascii);
+            match std::io::stdout().is_terminal() {
+                true => format!("\r{}", bar),
+                false => bar,
             }
-            format(&bin, &all_args)
+        }
+        None => output,
+    };
+
+    let mut printer = Printer::new(opts)?;
+    printer.print(&output)?;
+    printer.finish()
+}
+
+/// Renders the format string once a second for `secs` seconds and once more at the end, with
+/// `{remaining}` counting down to zero. On a terminal every render is drawn over the last one.
+fn countdown(opts: &cli::Options, fmt_str: &str, secs: u64) -> Result<()> {
+    let start = std::time::Instant::now();
+    let mut printer = Printer::new(opts)?;
+    for remaining in (0..=secs).rev() {
+        builtin::set_remaining(remaining);
+        let output = match opts.args.is_empty() {
+            true => without_args(fmt_str)?,
+            false => format(fmt_str, &opts.args)?,
+        };
+        printer.print(&output)?;
+        if remaining > 0 {
+            // Waiting for the next full second from the start keeps the renders from drifting
+            let due = start + std::time::Duration::from_secs(secs - remaining + 1);
+            std::thread::sleep(due.saturating_duration_since(std::time::Instant::now()));
         }
     }
+    printer.finish()
 }
 
-fn format<S: std::fmt::Display>(bin: &str, all_args: &[S]) -> Result<()> {
-    let input_len = all_args.len();
-    if input_len == 0 {
-        return help::print_usage(bin);
-    } else if input_len == 1 {
-        return print_string(&all_args[0]);
+/// Renders the format string every `interval` until interrupted, like watch(1), each time on a
+/// cleared terminal and with builtins like `{now}` read again.
+fn watch(opts: &cli::Options, fmt_str: &str, interval: std::time::Duration) -> Result<()> {
+    let mut printer = Printer::new(opts)?;
+    loop {
+        builtin::tick();
+        let output = match opts.args.is_empty() {
+            true => without_args(fmt_str)?,
+            false => format(fmt_str, &opts.args)?,
+        };
+        term::clear_screen()?;
+        printer.print(&output)?;
+        std::thread::sleep(interval);
     }
+}
 
-    let f = fmt::Formatter::new(&all_args[0].to_string())?;
+/// Runs the command of `--spinner` with a spinner in front of `message`, then prints `message`
+/// marked with whether the command succeeded. When it fails, everything it wrote is shown and its
+/// exit code becomes ours.
+fn run_spinner(opts: &cli::Options, message: &str) -> Result<()> {
+    let frames = match opts.ascii {
+        true => &spinner::ASCII_FRAMES[..],
+        false => &spinner::FRAMES[..],
+    };
+    let frames = std::io::stdout().is_terminal().then_some(frames);
+    let result = spinner::spin(message, &opts.command, frames)?;
+    let (ok, failed) = match opts.ascii {
+        true => ("+", "x"),
+        false => ("✔", "✖"),
+    };
+    let mark = match result.status.success() {
+        true => color::paint(ok, color::Color::Basic(10)),
+        false => color::paint(failed, color::Color::Basic(9)),
+    };
+
+    let mut printer = Printer::new(opts)?;
+    printer.print(&format!("{} {}", mark, message))?;
+    printer.finish()?;
+    if !result.status.success() {
+        let mut stderr = std::io::stderr();
+        stderr.write_all(&result.stdout)?;
+        stderr.write_all(&result.stderr)?;
+        std::process::exit(result.status.code().unwrap_or(1));
+    }
+    Ok(())
+}
+
+fn format<S: std::fmt::Display>(fmt_str: &str, args: &[S]) -> Result<String> {
+    let f = fmt::Formatter::new(fmt_str)?;
     if PRINT_DEBUG.load(std::sync::atomic::Ordering::Relaxed) {
         println!("Formatter: {:#?}", f);
     }
-    let output = f.generate(&all_args[1..])?;
-    println!("{}", output);
+    f.generate(args)
+}
 
-    Ok(())
+/// Without any args the format string is printed as is, unless it has specs and none of them need
+/// an arg, like the builtin `{now}` or the expression `{=1+2}`. One that doesn't parse is printed as
+/// is too, the parser keeps why to its error rather than printing it.
+fn without_args(fmt_str: &str) -> Result<String> {
+    match fmt::Formatter::new(fmt_str) {
+        Ok(f) if !f.specs().is_empty() && !f.needs_args() => f.generate::<&str>(&[]),
+        _ => Ok(fmt_str.to_string()),
+    }
 }
 
-fn print_string<S: std::fmt::Display>(s: S) -> Result<()> {
-    println!("{}", s);
+/// `--dry-run`: shows what every spec of `fmt_str` would be replaced with instead of the output.
+fn dry_run(fmt_str: &str, args: &[String]) -> Result<()> {
+    let f = fmt::Formatter::new(fmt_str)?;
+    let header = ["SPEC", "AT", "ARG", "VALUE", "PREPARED"].map(String::from);
+    let mut rows = vec![header.to_vec()];
+    for sub in f.substitutions(args)? {
+        rows.push(vec![
+            sub.spec,
+            sub.src_pos.to_string(),
+            sub.arg,
+            format!("{:?}", sub.value),
+            format!("{:?}", sub.prepared),
+        ]);
+    }
+
+    println!("{}", layout::table(&rows));
     Ok(())
 }
+
+/// `--export`: renders `NAME='value'` lines for the named placeholders of `fmt_str`, or for every
+/// named arg if it has none, so the output can be `eval`ed by a shell.
+fn export(fmt_str: &str, args: &[String]) -> Result<String> {
+    let f = fmt::Formatter::new(fmt_str)?;
+    let args: FormatArgs = args.iter().enumerate().collect();
+    let mut names = f
+        .specs()
+        .iter()
+        .filter_map(|s| s.arg_name.as_deref())
+        .collect::<Vec<_>>();
+    if names.is_empty() {
+        names = args.iter().filter_map(FormatArg::name).collect();
+    }
+
+    let mut lines = Vec::with_capacity(names.len());
+    for (i, name) in names.iter().enumerate() {
+        if names[..i].contains(name) {
+            continue;
+        }
+        if !encode::is_sh_name(name) {
+            return Err(Error::Other(format!(
+                "Unable to export '{}', it is not a valid shell variable name",
+                name
+            )));
+        }
+        let value = args
+            .get_named(name)
+            .ok_or_else(|| Error::bad_arg_name(name))?;
+        lines.push(format!("{}={}", name, encode::sh_quote(value)));
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Runs a record mode, reporting its [`records::Stats`] on stderr afterwards when `--stats` is set,
+/// whether or not the run succeeded.
+fn run_records(opts: &cli::Options, mode: cli::RecordMode, fmt_str: &str) -> Result<()> {
+    let mut stats = records::Stats::new();
+    let mut printer = Printer::new(opts)?;
+    let result =
+        emit_records(opts, mode, fmt_str, &mut printer, &mut stats).and_then(|_| printer.finish());
+    if opts.stats {
+        eprintln!("{}", stats);
+    }
+    result
+}
+
+/// Renders `fmt_str` once per record, between the `--header` and `--footer` if there are any, and
+/// with the `--separator` in between records. Records are printed as soon as they are rendered
+/// unless an option needs to see all of them first, so `--stream` keeps up with input that arrives
+/// slowly or never ends.
+fn emit_records(
+    opts: &cli::Options,
+    mode: cli::RecordMode,
+    fmt_str: &str,
+    printer: &mut Printer,
+    stats: &mut records::Stats,
+) -> Result<()> {
+    let input: Box<dyn Iterator<Item = Result<records::Record>>> = match mode {
+        cli::RecordMode::Each => Box::new(records::each(&opts.args).into_iter().map(Ok)),
+        cli::RecordMode::Stream => Box::new(records::stream(std::io::stdin().lock())),
+        cli::RecordMode::Chunk(n) => Box::new(records::chunk(&opts.args, n).into_iter().map(Ok)),
+        cli::RecordMode::Zip(n) => Box::new(records::zip(&opts.args, n)?.into_iter().map(Ok)),
+    };
+    for fmt in [&opts.header, &opts.footer, &opts.separator] {
+        fmt.as_deref().map_or(Ok(()), fmt::check_placeholders)?;
+    }
+    let header = match opts.header {
+        Some(ref header) => Some(format(header, &[] as &[String])?),
+        None => None,
+    };
+    let footer = |totals: &records::Totals, count: usize| -> Result<Option<String>> {
+        match opts.footer {
+            Some(ref footer) => Ok(Some(format(footer, &totals.args(count))?)),
+            None => Ok(None),
+        }
+    };
+    let mut totals = records::Totals::default();
+    let separator = match opts.separator {
+        Some(ref sep) => Some(records::separator(sep)?),
+        None => None,
+    };
+
+    let buffered = opts.sort.is_some()
+        || opts.unique.is_some()
+        || opts.columns.is_some()
+        || opts.block_align.is_some();
+    if buffered {
+        let mut read = Vec::new();
+        for record in input {
+            stats.processed += 1;
+            read.extend(skip_failed(stats, record));
+        }
+        read.iter().for_each(|record| totals.add(record));
+        let (body, count) = render_records(opts, fmt_str, read, separator.as_deref(), stats)?;
+        let body = (count > 0).then_some(body);
+        let parts = [header, body, footer(&totals, count)?];
+        let output = parts.into_iter().flatten().collect::<Vec<_>>();
+        if !output.is_empty() {
+            printer.print(&output.join("\n"))?;
+        }
+        return failures(stats);
+    }
+
+    if let Some(header) = header {
+        printer.print(&header)?;
+    }
+    let f = fmt::Formatter::new(fmt_str)?;
+    let mut count = 0usize;
+    for record in input {
+        stats.processed += 1;
+        let Some(record) = skip_failed(stats, record) else {
+            continue;
+        };
+        totals.add(&record);
+        let Some(output) = skip_failed(stats, opts.output.render(&f, &record.fields)) else {
+            continue;
+        };
+        if let (Some(sep), true) = (&separator, count > 0) {
+            printer.print(sep)?;
+        }
+        printer.print(&output)?;
+        count += 1;
+    }
+    if let Some(footer) = footer(&totals, count)? {
+        printer.print(&footer)?;
+    }
+    failures(stats)
+}
+
+/// Reports a record that failed to be read or rendered and counts it, so the run can go on with
+/// the records after it.
+fn skip_failed<T>(stats: &mut records::Stats, result: Result<T>) -> Option<T> {
+    match stats.tally(result) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            error(&err);
+            None
+        }
+    }
+}
+
+/// Fails a record mode that skipped any records, once all of the others are output.
+fn failures(stats: &records::Stats) -> Result<()> {
+    match stats.failed {
+        0 => Ok(()),
+        failed => Err(Error::Other(format!(
+            "{} of {} records failed",
+            failed, stats.processed
+        ))),
+    }
+}
+
+/// Renders every record at once, for the options that need to see all of them. Returns the output
+/// along with the number of records it contains, records that fail to render being left out.
+fn render_records(
+    opts: &cli::Options,
+    fmt_str: &str,
+    mut input: Vec<records::Record>,
+    separator: Option<&str>,
+    stats: &mut records::Stats,
+) -> Result<(String, usize)> {
+    if let Some(records::RecordKey::Field(ref key)) = opts.sort {
+        records::sort_records(&mut input, key);
+    }
+
+    let mut rendered = match opts.unique {
+        Some(ref key) => records::render_unique(fmt_str, &input, key, opts.output)?,
+        None => records::render(fmt_str, &input, opts.output)?,
+    }
+    .into_iter()
+    .filter_map(|output| skip_failed(stats, output))
+    .collect::<Vec<_>>();
+    if let Some(records::RecordKey::Rendered) = opts.sort {
+        records::sort_rendered(&mut rendered);
+    }
+
+    let output = match opts.columns {
+        Some(cli::Columns::Auto) => layout::columns(&rendered, None, term::width()),
+        Some(cli::Columns::Count(n)) => layout::columns(&rendered, Some(n), term::width()),
+        None => match separator {
+            Some(sep) => rendered.join(&format!("\n{}\n", sep)),
+            None => rendered.join("\n"),
+        },
+    };
+    Ok((output, rendered.len()))
+}
+
+/// Lays rendered output out the way the options ask for and writes it to every output target,
+/// keeping a plain copy of everything printed for `--copy` and `--notify`.
+struct Printer<'a> {
+    opts: &'a cli::Options,
+    sinks: Vec<sink::Sink>,
+    copied: Option<Vec<String>>,
+    /// Output kept for `fmt snapshot` instead of being written anywhere
+    captured: Option<Vec<String>>,
+    /// Output held back until the end, for formats that need all of it at once
+    held: Option<Vec<String>>,
+}
+
+impl<'a> Printer<'a> {
+    fn new(opts: &'a cli::Options) -> Result<Self> {
+        let targets = match opts.snapshot {
+            Some(_) => &[][..],
+            None => &opts.targets[..],
+        };
+        let end = match () {
+            _ if opts.countdown.is_some() || opts.overwrite => sink::LineEnd::Overwrite,
+            _ if opts.no_newline => sink::LineEnd::Held,
+            _ => sink::LineEnd::Newline,
+        };
+        let mut sinks = targets
+            .iter()
+            .map(|target| {
+                let sink = sink::Sink::open(target, color::choice(), end)?;
+                let delay = opts.typewriter.map(std::time::Duration::from_millis);
+                Ok(sink.typewriter(delay).screen(opts.screen))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut captured = opts.snapshot.as_ref().map(|_| Vec::new());
+        if opts.output == output::OutputFormat::Html {
+            if let Some(ref mut captured) = captured {
+                captured.push(output::HTML_OPEN.to_string());
+            }
+            for sink in &mut sinks {
+                sink.write_line(output::HTML_OPEN)?;
+            }
+        }
+        Ok(Self {
+            opts,
+            sinks,
+            copied: (opts.copy || opts.notify).then(Vec::new),
+            captured,
+            held: (opts.output == output::OutputFormat::Svg).then(Vec::new),
+        })
+    }
+
+    fn print(&mut self, output: &str) -> Result<()> {
+        let opts = self.opts;
+        let mut output = if opts.plain {
+            ansi::sanitize(output)
+        } else {
+            opts.style.paint(output, color::effective_level())
+        };
+        if let Some(width) = opts.wrap {
+            let wrap_opts = layout::WrapOptions::new(width.resolve())
+                .hang(opts.hang)
+                .hard(opts.hard_wrap)
+                .justify(opts.justify);
+            output = layout::wrap(&output, wrap_opts);
+        }
+        if opts.clamp {
+            output = layout::clamp(&output, term::width());
+        }
+        if let Some(style) = opts.frame {
+            output = decor::frame(&output, style);
+        }
+        if let Some(align) = opts.block_align {
+            output = layout::block_align(&output, align, term::width());
+        }
+        if opts.center {
+            output = layout::center(&output, term::width());
+        }
+        // Colors go on last, one grapheme at a time, so they follow the final layout
+        match opts.colorize {
+            Some(cli::Colorize::Rainbow) => {
+                output = color::rainbow(&output, color::effective_level())
+            }
+            Some(cli::Colorize::Gradient(from, to)) => {
+                output = color::gradient(&output, from, to, color::effective_level())
+            }
+            None => {}
+        }
+
+        if let Some(ref mut copied) = self.copied {
+            copied.push(ansi::strip(&output));
+        }
+        if let Some(ref mut held) = self.held {
+            held.push(output);
+            return Ok(());
+        }
+        if opts.output == output::OutputFormat::Html {
+            output = output::html(&output);
+        }
+        if let Some(ref mut captured) = self.captured {
+            captured.push(output);
+            return Ok(());
+        }
+        for sink in &mut self.sinks {
+            sink.write_line(&output)?;
+        }
+        Ok(())
+    }
+
+    /// Closes whatever the output format opened, and puts everything that was printed on the
+    /// clipboard if `--copy` was given. Alerts asked for with `--notify` or `--bell` go last.
+    fn finish(mut self) -> Result<()> {
+        let closing = match self.opts.output {
+            output::OutputFormat::Html => Some(output::HTML_CLOSE.to_string()),
+            output::OutputFormat::Svg => self.held.take().map(|held| output::svg(&held.join("\n"))),
+            _ => None,
+        };
+        if let Some(closing) = closing {
+            if let Some(ref mut captured) = self.captured {
+                captured.push(closing.clone());
+            }
+            for sink in &mut self.sinks {
+                sink.write_line(&closing)?;
+            }
+        }
+        // With --overwrite the line is left open, for the next run to draw over
+        if !self.opts.overwrite {
+            for sink in &mut self.sinks {
+                sink.end_line()?;
+            }
+        }
+        if let (Some(snapshot), Some(captured)) = (&self.opts.snapshot, &self.captured) {
+            snapshot.run(&captured.join("\n"))?;
+        }
+        let printed = self.copied.map(|copied| copied.join("\n"));
+        if let (true, Some(printed)) = (self.opts.notify, &printed) {
+            term::send(&term::notify_sequence(printed))?;
+        }
+        if self.opts.bell {
+            term::send(term::BELL)?;
+        }
+        // Not being able to copy shouldn't fail output that was printed just fine
+        if let (true, Some(printed)) = (self.opts.copy, printed) {
+            if !clipboard::copy(&printed) {
+                warn("Unable to copy the output, there is no clipboard tool or terminal to copy it with");
+            }
+        }
+        Ok(())
+    }
+}
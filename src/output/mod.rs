@@ -0,0 +1,280 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use crate::{
+    ansi::{self, Style},
+    encode, Formatter,
+};
+
+/// Opens an HTML document fragment made by [`html`]
+pub const HTML_OPEN: &str = "<pre class=\"term-println\">";
+/// Closes what [`HTML_OPEN`] opened
+pub const HTML_CLOSE: &str = "</pre>";
+
+// Sizes of the SVG "screenshot", in pixels
+const SVG_FONT_SIZE: f64 = 14.0;
+const SVG_CELL_WIDTH: f64 = SVG_FONT_SIZE * 0.6;
+const SVG_LINE_HEIGHT: f64 = SVG_FONT_SIZE * 1.4;
+const SVG_PADDING: f64 = 16.0;
+const SVG_TITLE_BAR: f64 = 28.0;
+const SVG_BACKGROUND: &str = "#1e1e1e";
+const SVG_FOREGROUND: &str = "#d4d4d4";
+
+/// The shape `--output` gives to the rendered output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum OutputFormat {
+    /// The format string with every spec substituted, as usual
+    #[default]
+    Text,
+    /// One CSV row per record, made of the values the specs substitute
+    Csv,
+    /// Like [`OutputFormat::Csv`], but separated by tabs
+    Tsv,
+    /// HTML with styling turned into `<span>`s, see [`html`]
+    Html,
+    /// An SVG image of the output as a terminal would show it, see [`svg`]
+    Svg,
+}
+
+impl OutputFormat {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim() {
+            "text" => Some(Self::Text),
+            "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            "html" => Some(Self::Html),
+            "svg" => Some(Self::Svg),
+            _ => None,
+        }
+    }
+
+    /// Whether this format produces one row per record, which only makes sense in record modes.
+    pub fn is_tabular(&self) -> bool {
+        matches!(self, Self::Csv | Self::Tsv)
+    }
+
+    /// Whether the format shows styling in a form of its own, so output is styled no matter
+    /// where it is written.
+    pub fn is_styled(&self) -> bool {
+        matches!(self, Self::Html | Self::Svg)
+    }
+
+    /// Renders one record in this format.
+    pub fn render<S: std::fmt::Display>(&self, f: &Formatter, args: &[S]) -> crate::Result<String> {
+        match self.is_tabular() {
+            true => {
+                let subs = f.substitutions(args)?;
+                Ok(self.row(subs.iter().map(|sub| sub.filtered.as_str())))
+            }
+            false => f.generate(args),
+        }
+    }
+
+    /// Joins `fields` into a single row, quoted and separated the way this format wants them. Only
+    /// meaningful for the formats that are [tabular](OutputFormat::is_tabular).
+    pub fn row<'a, I: IntoIterator<Item = &'a str>>(&self, fields: I) -> String {
+        let (sep, quote): (&str, fn(&str) -> String) = match self {
+            Self::Tsv => ("\t", encode::tsv_field),
+            _ => (",", encode::csv_field),
+        };
+        fields.into_iter().map(quote).collect::<Vec<_>>().join(sep)
+    }
+}
+
+/// The inline CSS for a [`Style`].
+fn css(style: &Style) -> String {
+    let (fg, bg) = if style.inverse {
+        (style.bg, style.fg)
+    } else {
+        (style.fg, style.bg)
+    };
+    let mut css = Vec::new();
+    if let Some(c) = fg {
+        css.push(format!("color:{}", c.to_hex()));
+    }
+    if let Some(c) = bg {
+        css.push(format!("background-color:{}", c.to_hex()));
+    }
+    if style.bold {
+        css.push("font-weight:bold".to_string());
+    }
+    if style.dim {
+        css.push("opacity:0.7".to_string());
+    }
+    if style.italic {
+        css.push("font-style:italic".to_string());
+    }
+    match (style.underline, style.strike) {
+        (true, true) => css.push("text-decoration:underline line-through".to_string()),
+        (true, false) => css.push("text-decoration:underline".to_string()),
+        (false, true) => css.push("text-decoration:line-through".to_string()),
+        (false, false) => {}
+    }
+    css.join(";")
+}
+
+/// Escapes `text` for HTML and turns its styling into `<span style=…>` elements. The result is
+/// meant to go between [`HTML_OPEN`] and [`HTML_CLOSE`], which keep its whitespace intact.
+pub fn html(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    for (style, run) in ansi::styled_runs(text) {
+        let run = encode::html_escape(run);
+        if style.is_plain() {
+            output.push_str(&run);
+        } else {
+            output.push_str(&format!("<span style=\"{}\">{}</span>", css(&style), run));
+        }
+    }
+    output
+}
+
+/// Draws `text` like a screenshot of a terminal window showing it, with its styling. Every
+/// character is placed on the cell grid explicitly, so wide characters line up like they would in
+/// a terminal regardless of the font the viewer has.
+pub fn svg(text: &str) -> String {
+    let cols = text.split('\n').map(ansi::width).max().unwrap_or(0);
+    let rows = text.split('\n').count();
+    // Always leave room for the window buttons
+    let width = (SVG_PADDING * 2.0 + cols as f64 * SVG_CELL_WIDTH).max(80.0);
+    let height = SVG_TITLE_BAR + SVG_PADDING * 2.0 + rows as f64 * SVG_LINE_HEIGHT;
+
+    let mut backgrounds = Vec::new();
+    let mut texts = Vec::new();
+    let (mut row, mut col) = (0usize, 0usize);
+    for (style, run) in ansi::styled_runs(text) {
+        for (i, piece) in run.split('\n').enumerate() {
+            if i > 0 {
+                row += 1;
+                col = 0;
+            }
+            if piece.is_empty() {
+                continue;
+            }
+
+            let (fg, bg) = if style.inverse {
+                (style.bg, style.fg)
+            } else {
+                (style.fg, style.bg)
+            };
+            let x = SVG_PADDING + col as f64 * SVG_CELL_WIDTH;
+            let top = SVG_TITLE_BAR + SVG_PADDING + row as f64 * SVG_LINE_HEIGHT;
+            let cells = ansi::width(piece);
+            if let Some(bg) = bg {
+                backgrounds.push(format!(
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/>",
+                    x,
+                    top,
+                    cells as f64 * SVG_CELL_WIDTH,
+                    SVG_LINE_HEIGHT,
+                    bg.to_hex()
+                ));
+            }
+
+            let mut attrs = format!(" x=\"{:.1}\" y=\"{:.1}\"", x, top + SVG_FONT_SIZE);
+            if let Some(fg) = fg {
+                attrs.push_str(&format!(" fill=\"{}\"", fg.to_hex()));
+            }
+            if style.bold {
+                attrs.push_str(" font-weight=\"bold\"");
+            }
+            if style.italic {
+                attrs.push_str(" font-style=\"italic\"");
+            }
+            if style.dim {
+                attrs.push_str(" opacity=\"0.7\"");
+            }
+            match (style.underline, style.strike) {
+                (true, true) => attrs.push_str(" text-decoration=\"underline line-through\""),
+                (true, false) => attrs.push_str(" text-decoration=\"underline\""),
+                (false, true) => attrs.push_str(" text-decoration=\"line-through\""),
+                (false, false) => {}
+            }
+            texts.push(format!(
+                "<text{}>{}</text>",
+                attrs,
+                encode::html_escape(piece)
+            ));
+            col += cells;
+        }
+    }
+
+    let mut svg = vec![
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.1}\" height=\"{h:.1}\" viewBox=\"0 0 {w:.1} {h:.1}\">",
+            w = width,
+            h = height
+        ),
+        format!(
+            "<rect width=\"100%\" height=\"100%\" rx=\"6\" fill=\"{}\"/>",
+            SVG_BACKGROUND
+        ),
+    ];
+    for (i, color) in ["#ff5f56", "#ffbd2e", "#27c93f"].iter().enumerate() {
+        svg.push(format!(
+            "<circle cx=\"{}\" cy=\"14\" r=\"6\" fill=\"{}\"/>",
+            16 + i * 20,
+            color
+        ));
+    }
+    svg.extend(backgrounds);
+    svg.push(format!(
+        "<g font-family=\"ui-monospace,Menlo,Consolas,monospace\" font-size=\"{}\" fill=\"{}\" xml:space=\"preserve\">",
+        SVG_FONT_SIZE, SVG_FOREGROUND
+    ));
+    svg.extend(texts);
+    svg.push("</g>".to_string());
+    svg.push("</svg>".to_string());
+    svg.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn rows() {
+        let f = Formatter::new("{name:>10} owes {}").unwrap();
+        let args = ["$5, maybe", "name = Bob \"B\""];
+        assert_eq!(
+            OutputFormat::Text.render(&f, &args).unwrap(),
+            "   Bob \"B\" owes $5, maybe"
+        );
+        assert_eq!(
+            OutputFormat::Csv.render(&f, &args).unwrap(),
+            "\"Bob \"\"B\"\"\",\"$5, maybe\""
+        );
+        assert_eq!(
+            OutputFormat::Tsv.render(&f, &args).unwrap(),
+            "Bob \"B\"\t$5, maybe"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "unicode")]
+    fn svg_cells() {
+        let image = svg("ab\n\x1b[41m读\x1b[0mc");
+        assert!(image.starts_with("<svg "));
+        // Two lines, at most three cells wide
+        assert!(image.contains("width=\"80.0\" height=\"99.2\""));
+        assert!(image.contains("<text x=\"16.0\" y=\"58.0\">ab</text>"));
+        assert!(image.contains(
+            "<rect x=\"16.0\" y=\"63.6\" width=\"16.8\" height=\"19.6\" fill=\"#cd0000\"/>"
+        ));
+        assert!(image.contains("<text x=\"32.8\" y=\"77.6\">c</text>"));
+        assert!(image.ends_with("</svg>"));
+    }
+
+    #[test]
+    fn html_spans() {
+        assert_eq!(html("a < b"), "a &lt; b");
+        assert_eq!(
+            html("\x1b[1;31mred\x1b[0m & \x1b[4;38;2;0;0;255mblue\x1b[0m"),
+            "<span style=\"color:#cd0000;font-weight:bold\">red</span> &amp; \
+             <span style=\"color:#0000ff;text-decoration:underline\">blue</span>"
+        );
+    }
+}
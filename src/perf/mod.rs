@@ -0,0 +1,176 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Self profiling for `--stats-perf`. Timings are only taken once [`enable`] has been called.
+//! Allocations are counted all along (they are just two relaxed atomic adds), but only when built
+//! with `alloc-stats`, which puts a counting allocator in place of the global one.
+
+#[cfg(feature = "alloc-stats")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::OnceCell;
+
+/// The system allocator, counting every allocation it makes.
+#[cfg(feature = "alloc-stats")]
+pub struct CountingAlloc;
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "alloc-stats")]
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// The parts of a run that are timed separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// Parsing format strings into a [`crate::Formatter`]
+    Parse,
+    /// Substituting args into a parsed format string
+    Generate,
+}
+
+/// Total nanoseconds and number of calls for a [`Phase`]
+struct Timer {
+    nanos: AtomicU64,
+    calls: AtomicUsize,
+}
+
+impl Timer {
+    const fn new() -> Self {
+        Self {
+            nanos: AtomicU64::new(0),
+            calls: AtomicUsize::new(0),
+        }
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static STARTED: OnceCell<(Instant, usize, usize)> = OnceCell::new();
+static PARSE: Timer = Timer::new();
+static GENERATE: Timer = Timer::new();
+static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+fn timer(phase: Phase) -> &'static Timer {
+    match phase {
+        Phase::Parse => &PARSE,
+        Phase::Generate => &GENERATE,
+    }
+}
+
+/// Starts profiling. Allocations made before this are left out of the report.
+pub fn enable() {
+    STARTED.get_or_init(|| {
+        (
+            Instant::now(),
+            ALLOCATIONS.load(Ordering::Relaxed),
+            ALLOCATED_BYTES.load(Ordering::Relaxed),
+        )
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Runs `f`, adding the time it took to `phase` when profiling is enabled.
+pub fn measure<T, F: FnOnce() -> T>(phase: Phase, f: F) -> T {
+    if !enabled() {
+        return f();
+    }
+
+    let start = Instant::now();
+    let result = f();
+    let timer = timer(phase);
+    let nanos = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+    timer.nanos.fetch_add(nanos, Ordering::Relaxed);
+    timer.calls.fetch_add(1, Ordering::Relaxed);
+    result
+}
+
+/// Counts `n` bytes of output as written.
+pub fn wrote(n: usize) {
+    WRITTEN.fetch_add(n, Ordering::Relaxed);
+}
+
+/// Summarizes everything measured since [`enable`], one measurement per line.
+pub fn report() -> String {
+    let (started, allocs, bytes) = STARTED.get().copied().unwrap_or((Instant::now(), 0, 0));
+    let line = |name: &str, timer: &Timer, unit: &str| {
+        let calls = timer.calls.load(Ordering::Relaxed);
+        let time = Duration::from_nanos(timer.nanos.load(Ordering::Relaxed));
+        format!("{:<12} {:>10.2?} ({} {})", name, time, calls, unit)
+    };
+
+    let allocations = format!(
+        "{:<12} {:>10} ({} bytes)",
+        "allocations:",
+        ALLOCATIONS.load(Ordering::Relaxed) - allocs,
+        ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes
+    );
+
+    [
+        Some(line("parse:", &PARSE, "format strings")),
+        Some(line("generate:", &GENERATE, "renders")),
+        cfg!(feature = "alloc-stats").then_some(allocations),
+        Some(format!(
+            "{:<12} {:>10} bytes",
+            "written:",
+            WRITTEN.load(Ordering::Relaxed)
+        )),
+        Some(format!("{:<12} {:>10.2?}", "total:", started.elapsed())),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn report_lines() {
+        enable();
+        assert_eq!(measure(Phase::Parse, || 40 + 2), 42);
+        wrote(3);
+        let report = report();
+        let names = report
+            .lines()
+            .map(|l| l.split_whitespace().next().unwrap())
+            .collect::<Vec<_>>();
+        let expected = match cfg!(feature = "alloc-stats") {
+            true => vec!["parse:", "generate:", "allocations:", "written:", "total:"],
+            false => vec!["parse:", "generate:", "written:", "total:"],
+        };
+        assert_eq!(names, expected);
+        assert!(PARSE.calls.load(Ordering::Relaxed) >= 1);
+    }
+}
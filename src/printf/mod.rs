@@ -0,0 +1,395 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `--printf`: format strings with printf(1) conversions instead of rust style specs.
+//!
+//! Supported are `%s`, `%c`, the integer conversions `%d`, `%i`, `%u`, `%x`, `%X` and `%o`, and
+//! the floating point conversions `%f`, `%F`, `%e`, `%E`, `%g` and `%G`, with the `-`, `0`, `+`,
+//! ` ` and `#` flags, a width and a precision.
+//!
+//! Like printf(1), the format string is reused for as long as there are args left, missing args
+//! are treated as empty strings (or zero for numeric conversions), and backslash escapes are
+//! interpreted in the format string.
+
+use std::process::Command;
+
+use crate::{number, Error, MAX_PRECISION, MAX_WIDTH};
+
+/// Cases checked against the system printf by `--printf-compat-check`, as a format string and
+/// its args.
+pub const CASES: &[(&str, &[&str])] = &[
+    ("%s\\n", &["hello"]),
+    ("%5s|%-5s|\\n", &["ab", "cd"]),
+    ("%.2s|%5.1s|\\n", &["abcdef", "xyz"]),
+    ("%d + %i\\n", &["1", "+2"]),
+    ("%05d|%.3d|%-4d|\\n", &["-42", "5", "7"]),
+    ("%+d|% d|%-05d|\\n", &["5", "5", "3"]),
+    ("%d %d\\n", &["0x1f", "010"]),
+    ("%s %s\\n", &["a", "b", "c"]),
+    ("%s-%d|\\n", &[]),
+    ("%-10s %05d\\n", &["foo", "42"]),
+    (
+        "%5.2f|%-8.3f|%08.2f|%+.1f|\\n",
+        &["3.14159", "2.5", "-1.5", "2"],
+    ),
+    ("% f|%#.0f|%.0f|%F\\n", &["1", "3", "2.5", "0.1"]),
+    (
+        "%x %X %#x %#o %o %#X\\n",
+        &["255", "255", "255", "8", "8", "0"],
+    ),
+    ("%08.3x|%#08x|%-#6o|\\n", &["10", "255", "8"]),
+    ("%u %x\\n", &["-1", "-1"]),
+    ("%e %.2E %10.3e %e\\n", &["1500", "0.00042", "-12.5", "0"]),
+    (
+        "%g %g %G %.3g %#g %g\\n",
+        &["1234.5", "0.00001", "1e20", "123456", "2", "100000"],
+    ),
+    ("%c%c|%5c|%-3c|\\n", &["hello", "w", "x", "y"]),
+    ("100%%\\tdone\\\\\\n", &[]),
+    ("unknown \\q escape\\n", &[]),
+];
+
+/// Flags, width and precision of a single conversion.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Conversion {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Conversion {
+    /// Pads `s` to the width, on the left unless `-` was given.
+    fn pad(&self, s: &str) -> String {
+        let len = s.chars().count();
+        if len >= self.width {
+            return s.to_string();
+        }
+        let fill = " ".repeat(self.width - len);
+        if self.left {
+            format!("{}{}", s, fill)
+        } else {
+            format!("{}{}", fill, s)
+        }
+    }
+
+    fn string(&self, arg: &str) -> String {
+        match self.precision {
+            Some(p) => self.pad(&arg.chars().take(p).collect::<String>()),
+            None => self.pad(arg),
+        }
+    }
+
+    fn sign(&self, negative: bool) -> &'static str {
+        match (negative, self.plus, self.space) {
+            (true, _, _) => "-",
+            (false, true, _) => "+",
+            (false, false, true) => " ",
+            _ => "",
+        }
+    }
+
+    /// Pads a number made of a sign, a base prefix and its digits. Zero padding goes between the
+    /// prefix and the digits, and is ignored with `-` or when `zero_allowed` is false.
+    fn number(&self, sign: &str, prefix: &str, digits: &str, zero_allowed: bool) -> String {
+        let len = sign.len() + prefix.len() + digits.len();
+        if self.zero && !self.left && zero_allowed && len < self.width {
+            let zeros = "0".repeat(self.width - len);
+            return format!("{}{}{}{}", sign, prefix, zeros, digits);
+        }
+        self.pad(&format!("{}{}{}", sign, prefix, digits))
+    }
+
+    /// The digits of an integer, extended with zeros to the precision.
+    fn digits(&self, mut digits: String) -> String {
+        if let Some(p) = self.precision {
+            if digits.len() < p {
+                digits.insert_str(0, &"0".repeat(p - digits.len()));
+            }
+        }
+        digits
+    }
+
+    fn integer(&self, n: i64) -> String {
+        let digits = self.digits(n.unsigned_abs().to_string());
+        // Zero padding is ignored with a precision, which already says how many digits to show
+        self.number(self.sign(n < 0), "", &digits, self.precision.is_none())
+    }
+
+    /// `%u`, `%x`, `%X` and `%o`, which show negative numbers in two's complement like printf(1).
+    fn unsigned(&self, n: i64, conv: char) -> String {
+        let n = n as u64;
+        let (digits, prefix) = match conv {
+            'x' => (format!("{:x}", n), "0x"),
+            'X' => (format!("{:X}", n), "0X"),
+            'o' => (format!("{:o}", n), "0"),
+            _ => (n.to_string(), ""),
+        };
+        let digits = self.digits(digits);
+        // `#` only adds a prefix to numbers that aren't zero, and octal only needs a leading zero
+        let prefix = match (self.alternate, conv) {
+            (true, 'o') if !digits.starts_with('0') => prefix,
+            (true, 'x' | 'X') if n != 0 => prefix,
+            _ => "",
+        };
+        self.number("", prefix, &digits, self.precision.is_none())
+    }
+
+    fn float(&self, n: f64, conv: char) -> String {
+        let p = self.precision.unwrap_or(6);
+        let upper = conv.is_ascii_uppercase();
+        let mut digits = match conv {
+            'e' | 'E' => number::python_exponent(n.abs(), upper, p),
+            'g' | 'G' => number::python_general(n.abs(), upper, p, self.alternate, false),
+            _ => number::fixed(n.abs(), p),
+        };
+        // `#` keeps the decimal point even without any decimals
+        if self.alternate && !digits.contains('.') && matches!(conv, 'f' | 'F') {
+            digits.push('.');
+        }
+        self.number(self.sign(n.is_sign_negative()), "", &digits, true)
+    }
+}
+
+/// Parses an integer argument the way printf(1) does: an optional sign, then a `0x` prefix for
+/// hex, a leading `0` for octal, or decimal digits. An empty argument is zero.
+fn parse_int(arg: &str) -> crate::Result<i64> {
+    let s = arg.trim();
+    if s.is_empty() {
+        return Ok(0);
+    }
+    let (negative, digits) = match s.as_bytes()[0] {
+        b'-' => (true, &s[1..]),
+        b'+' => (false, &s[1..]),
+        _ => (false, s),
+    };
+    let parsed = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        i64::from_str_radix(hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8)
+    } else {
+        digits.parse::<i64>()
+    };
+    parsed
+        .map(|n| if negative { -n } else { n })
+        .map_err(|_| Error::Other(format!("{}: invalid number", arg)))
+}
+
+/// Parses a floating point argument, where an empty argument is zero.
+fn parse_float(arg: &str) -> crate::Result<f64> {
+    if arg.trim().is_empty() {
+        return Ok(0.0);
+    }
+    number::parse_float(arg).ok_or_else(|| Error::Other(format!("{}: invalid number", arg)))
+}
+
+/// Interprets the backslash escape starting right after a `\`, returning it and how many
+/// characters it used. Unknown escapes are kept as they are, like printf(1) does.
+fn escape(next: Option<char>) -> (String, usize) {
+    let c = match next {
+        Some(c) => c,
+        None => return ("\\".to_string(), 0),
+    };
+    let unescaped = match c {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        'a' => '\x07',
+        'b' => '\x08',
+        'e' => '\x1b',
+        'f' => '\x0c',
+        'v' => '\x0b',
+        '\\' => '\\',
+        '"' => '"',
+        _ => return (format!("\\{}", c), 1),
+    };
+    (unescaped.to_string(), 1)
+}
+
+/// Renders `fmt_str` with `args` like printf(1) would.
+pub fn format(fmt_str: &str, args: &[String]) -> crate::Result<String> {
+    let mut output = String::new();
+    let mut args = args.iter();
+    loop {
+        let consumed = format_once(fmt_str, &mut args, &mut output)?;
+        if consumed == 0 || args.len() == 0 {
+            return Ok(output);
+        }
+    }
+}
+
+/// One pass over the format string, returning how many args it used.
+fn format_once<'a, I: Iterator<Item = &'a String>>(
+    fmt_str: &str,
+    args: &mut I,
+    output: &mut String,
+) -> crate::Result<usize> {
+    let chars = fmt_str.chars().collect::<Vec<_>>();
+    let mut consumed = 0usize;
+    let mut i = 0usize;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                let (s, used) = escape(chars.get(i + 1).copied());
+                output.push_str(&s);
+                i += 1 + used;
+            }
+            '%' if chars.get(i + 1) == Some(&'%') => {
+                output.push('%');
+                i += 2;
+            }
+            '%' => {
+                let start = i;
+                i += 1;
+                let mut conv = Conversion::default();
+                while let Some(&c) = chars.get(i) {
+                    match c {
+                        '-' => conv.left = true,
+                        '0' => conv.zero = true,
+                        '+' => conv.plus = true,
+                        ' ' => conv.space = true,
+                        '#' => conv.alternate = true,
+                        _ => break,
+                    }
+                    i += 1;
+                }
+                let number = |i: &mut usize| {
+                    let mut n = 0usize;
+                    while let Some(d) = chars.get(*i).and_then(|c| c.to_digit(10)) {
+                        n = n.saturating_mul(10).saturating_add(d as usize);
+                        *i += 1;
+                    }
+                    n
+                };
+                conv.width = number(&mut i);
+                if chars.get(i) == Some(&'.') {
+                    i += 1;
+                    conv.precision = Some(number(&mut i));
+                }
+                // Rust can't format widths or precisions past these, so they're errors, not panics
+                if conv.width > MAX_WIDTH || conv.precision.is_some_and(|p| p > MAX_PRECISION) {
+                    let end = (i + 1).min(chars.len());
+                    let spec = chars[start..end].iter().collect::<String>();
+                    return Err(Error::bad_spec(&spec));
+                }
+
+                let arg = args.next().map(String::as_str);
+                consumed += usize::from(arg.is_some());
+                let arg = arg.unwrap_or("");
+                match chars.get(i) {
+                    Some('s') => output.push_str(&conv.string(arg)),
+                    Some('c') => {
+                        output.push_str(&conv.pad(&arg.chars().take(1).collect::<String>()))
+                    }
+                    Some('d' | 'i') => output.push_str(&conv.integer(parse_int(arg)?)),
+                    Some(&c @ ('u' | 'x' | 'X' | 'o')) => {
+                        output.push_str(&conv.unsigned(parse_int(arg)?, c))
+                    }
+                    Some(&c @ ('f' | 'F' | 'e' | 'E' | 'g' | 'G')) => {
+                        output.push_str(&conv.float(parse_float(arg)?, c))
+                    }
+                    _ => {
+                        let end = (i + 1).min(chars.len());
+                        let spec = chars[start..end].iter().collect::<String>();
+                        return Err(Error::bad_spec(&spec));
+                    }
+                }
+                i += 1;
+            }
+            c => {
+                output.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    Ok(consumed)
+}
+
+/// Runs every one of the [`CASES`] through both the system `printf` and [`format`], returning a
+/// report of the results. Any case that differs makes the whole check fail.
+pub fn compat_check() -> crate::Result<String> {
+    let mut report = Vec::new();
+    let mut failed = 0usize;
+    for (fmt_str, args) in CASES {
+        let system = Command::new("printf")
+            .arg(fmt_str)
+            .args(*args)
+            .output()
+            .map_err(|err| Error::Other(format!("Unable to run the system printf: {}", err)))?;
+        let expected = String::from_utf8_lossy(&system.stdout);
+        let args = args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        let actual = format(fmt_str, &args);
+
+        match actual {
+            Ok(ref actual) if *actual == expected => report.push(format!("ok    {:?}", fmt_str)),
+            _ => {
+                failed += 1;
+                report.push(format!(
+                    "FAIL  {:?} {:?}\n      printf: {:?}\n      --printf: {:?}",
+                    fmt_str, args, expected, actual
+                ));
+            }
+        }
+    }
+
+    report.push(format!(
+        "{} cases, {} passed, {} failed",
+        CASES.len(),
+        CASES.len() - failed,
+        failed
+    ));
+    let report = report.join("\n");
+    if failed > 0 {
+        return Err(Error::Other(report));
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    fn printf(fmt_str: &str, args: &[&str]) -> crate::Result<String> {
+        format(
+            fmt_str,
+            &args.iter().map(|a| a.to_string()).collect::<Vec<_>>(),
+        )
+    }
+
+    #[test]
+    fn conversions() {
+        assert_eq!(
+            printf("%-10s|%05d", &["foo", "42"]).unwrap(),
+            "foo       |00042"
+        );
+        assert_eq!(printf("%05d|%+d", &["-42", "7"]).unwrap(), "-0042|+7");
+        assert_eq!(printf("%s,", &["a", "b", "c"]).unwrap(), "a,b,c,");
+        assert_eq!(printf("%s-%d\\n", &[]).unwrap(), "-0\n");
+        assert_eq!(printf("%d", &["010"]).unwrap(), "8");
+        assert_eq!(
+            printf("%5.2f|%#x|%o|%.3e", &["3.14159", "255", "8", "1500"]).unwrap(),
+            " 3.14|0xff|10|1.500e+03"
+        );
+        assert_eq!(
+            printf("%u|%c", &["-1", "xyz"]).unwrap(),
+            "18446744073709551615|x"
+        );
+        assert!(printf("%f", &["1.2.3"]).is_err());
+        assert!(printf("%d", &["abc"]).is_err());
+        assert!(printf("%q", &["x"]).is_err());
+        assert!(printf("%.70000f", &["1.5"]).is_err());
+        assert!(printf("%99999999999999999999999d", &["1"]).is_err());
+    }
+}
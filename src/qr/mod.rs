@@ -0,0 +1,64 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `--qr`, which draws the output as a QR code for a phone to scan off the terminal.
+
+/// How many modules of light border go around the code, scanners need some to find its edges
+pub const QUIET_ZONE: i32 = 2;
+
+/// Draws `text`, without its styling, as a QR code made of half blocks, so every line of output
+/// holds two rows of modules. Light modules are the filled ones, since most terminals draw light
+/// text on a dark background.
+#[cfg(feature = "qr")]
+pub fn render(text: &str) -> crate::Result<String> {
+    use qrcodegen::{QrCode, QrCodeEcc};
+
+    let code = QrCode::encode_text(&crate::ansi::strip(text), QrCodeEcc::Medium).map_err(|_| {
+        crate::Error::Other("The output is too long to fit in a QR code".to_string())
+    })?;
+    let light = |x: i32, y: i32| !code.get_module(x, y);
+    let range = -QUIET_ZONE..code.size() + QUIET_ZONE;
+
+    let mut lines = Vec::new();
+    for y in range.clone().step_by(2) {
+        let line = range.clone().map(|x| match (light(x, y), light(x, y + 1)) {
+            (true, true) => '█',
+            (true, false) => '▀',
+            (false, true) => '▄',
+            (false, false) => ' ',
+        });
+        lines.push(line.collect::<String>());
+    }
+    Ok(lines.join("\n"))
+}
+
+#[cfg(not(feature = "qr"))]
+pub fn render(_: &str) -> crate::Result<String> {
+    Err(crate::Error::Other(
+        "--qr is not available, fmt was built without the qr feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    #[cfg(feature = "qr")]
+    fn codes() {
+        // The smallest code is 21 modules across, 25 with the border, which takes 13 lines
+        let code = render("hi").unwrap();
+        let lines = code.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 13);
+        assert!(lines.iter().all(|line| line.chars().count() == 25));
+        assert_eq!(lines[0], "█".repeat(25));
+        assert!(lines[1].starts_with("██ ▄▄▄▄▄ █"));
+        assert_eq!(render("\x1b[1mhi\x1b[0m").unwrap(), code);
+        assert_ne!(render("https://example.com").unwrap(), code);
+        assert!(render(&"x".repeat(5000)).is_err());
+    }
+}
@@ -0,0 +1,489 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::cmp::Ordering;
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter as FmtFormatter};
+use std::io::BufRead;
+use std::time::{Duration, Instant};
+
+use crate::{ansi, layout, number, output::OutputFormat, term, FormatArg, FormatArgs, Formatter};
+
+/// The named argument `--unique` adds to every record, holding how many times it occurred
+pub const DUPCOUNT: &str = "dupcount";
+
+/// The named argument `--footer` is rendered with, holding how many records were output
+pub const COUNT: &str = "count";
+
+/// The prefixes of the named arguments `--footer` gets for every named field holding numbers, like
+/// `sum_size`, `min_size` and `max_size` for a field `size`
+pub const AGGREGATES: [&str; 3] = ["sum", "min", "max"];
+
+/// One set of arguments the format string is rendered with. Record modes (like `--each`) map the
+/// same format string over many records instead of rendering it once.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub fields: Vec<String>,
+}
+
+impl Record {
+    pub fn new(fields: Vec<String>) -> Self {
+        Self { fields }
+    }
+
+    /// Looks up a field the same way a format spec would, `key` being either a position or a name.
+    pub fn field(&self, key: &str) -> Option<String> {
+        let args: FormatArgs = self.fields.iter().enumerate().collect();
+        match key.parse::<usize>() {
+            Ok(n) => args.get(n).cloned(),
+            Err(_) => args.get_named(key).cloned(),
+        }
+    }
+}
+
+/// What `--sort` orders the output by, or what `--unique` compares.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RecordKey {
+    /// The rendered lines themselves, ignoring any styling
+    Rendered,
+    /// A field of each record (by position or name), before rendering
+    Field(String),
+}
+
+/// `--each`: every argument becomes a record of its own.
+pub fn each(args: &[String]) -> Vec<Record> {
+    args.iter().map(|a| Record::new(vec![a.clone()])).collect()
+}
+
+/// `--chunk N`: every `n` arguments make up a record. The last record gets whatever is left over.
+pub fn chunk(args: &[String], n: usize) -> Vec<Record> {
+    args.chunks(n.max(1))
+        .map(|c| Record::new(c.to_vec()))
+        .collect()
+}
+
+/// `--zip N`: the arguments are `n` lists of the same length, one after the other, and every record
+/// takes the next argument of each list. With 2 lists, `a b c 1 2 3` makes the records `a 1`,
+/// `b 2` and `c 3`.
+pub fn zip(args: &[String], n: usize) -> crate::Result<Vec<Record>> {
+    let n = n.max(1);
+    if !args.len().is_multiple_of(n) {
+        return Err(crate::Error::Other(format!(
+            "--zip {} needs {} lists of the same length, but {} args can't be split evenly",
+            n,
+            n,
+            args.len()
+        )));
+    }
+    let len = args.len() / n;
+    let records = (0..len).map(|i| {
+        let fields = (0..n).map(|list| args[list * len + i].clone());
+        Record::new(fields.collect())
+    });
+    Ok(records.collect())
+}
+
+/// `--stream`: every line read from `reader` becomes a record of its own. Blank lines are skipped.
+pub fn stream<R: BufRead>(reader: R) -> impl Iterator<Item = crate::Result<Record>> {
+    reader.lines().filter_map(|line| match line {
+        Ok(line) if line.trim().is_empty() => None,
+        Ok(line) => Some(Ok(Record::new(vec![line]))),
+        Err(err) => Some(Err(err.into())),
+    })
+}
+
+/// What `--stats` reports once a record mode finishes.
+#[derive(Debug, Clone)]
+pub struct Stats {
+    /// Records read, including the ones that failed
+    pub processed: usize,
+    /// Records that could not be read or rendered
+    pub failed: usize,
+    started: Instant,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            processed: 0,
+            failed: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Counts `result` as a failure if it is an error, passing it through either way.
+    pub fn tally<T>(&mut self, result: crate::Result<T>) -> crate::Result<T> {
+        if result.is_err() {
+            self.failed += 1;
+        }
+        result
+    }
+
+    fn report(&self, elapsed: Duration) -> String {
+        let secs = elapsed.as_secs_f64();
+        let throughput = if secs > 0.0 {
+            self.processed as f64 / secs
+        } else {
+            0.0
+        };
+        format!(
+            "{} records processed, {} failed in {:.2?} ({:.1} records/s)",
+            self.processed, self.failed, elapsed, throughput
+        )
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut FmtFormatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.report(self.started.elapsed()))
+    }
+}
+
+/// The sum, smallest and largest value of every named field holding numbers, over all of the
+/// records read, for `--footer` to show.
+#[derive(Debug, Default, Clone)]
+pub struct Totals {
+    /// The name, sum, minimum and maximum of each field, in the order they were first seen
+    fields: Vec<(String, [f64; 3])>,
+}
+
+impl Totals {
+    pub fn add(&mut self, record: &Record) {
+        for arg in record.fields.iter().enumerate().map(FormatArg::from) {
+            let (Some(name), Some(n)) = (arg.name(), number::parse_float(arg.value())) else {
+                continue;
+            };
+            match self.fields.iter_mut().find(|(field, _)| field == name) {
+                Some((_, [sum, min, max])) => {
+                    *sum += n;
+                    *min = min.min(n);
+                    *max = max.max(n);
+                }
+                None => self.fields.push((name.to_string(), [n, n, n])),
+            }
+        }
+    }
+
+    /// The named args `--footer` is rendered with, [`COUNT`] being `count` and the rest being the
+    /// [`AGGREGATES`] of every field.
+    pub fn args(&self, count: usize) -> Vec<String> {
+        let mut args = vec![format!("{} = {}", COUNT, count)];
+        for (name, values) in &self.fields {
+            for (aggregate, value) in AGGREGATES.iter().zip(values) {
+                args.push(format!("{}_{} = {}", aggregate, name, number::show(*value)));
+            }
+        }
+        args
+    }
+}
+
+/// Compares two values numerically when both of them are numbers, and as text otherwise.
+pub fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => a.cmp(b),
+    }
+}
+
+/// Stable sorts `records` by the field `key`. Records without that field sort first.
+pub fn sort_records(records: &mut [Record], key: &str) {
+    records.sort_by(|a, b| match (a.field(key), b.field(key)) {
+        (Some(x), Some(y)) => compare_values(&x, &y),
+        (x, y) => x.cmp(&y),
+    });
+}
+
+/// Stable sorts rendered output by its visible text, so escape sequences don't affect the order.
+pub fn sort_rendered(lines: &mut [String]) {
+    lines.sort_by(|a, b| compare_values(&ansi::strip(a), &ansi::strip(b)));
+}
+
+/// Renders `fmt_str` once for every record in the given format, using a single parsed [`Formatter`].
+/// A record that fails to render doesn't stop the others, its error takes the place of its output.
+pub fn render(
+    fmt_str: &str,
+    records: &[Record],
+    format: OutputFormat,
+) -> crate::Result<Vec<crate::Result<String>>> {
+    let f = Formatter::new(fmt_str)?;
+    Ok(records
+        .iter()
+        .map(|r| format.render(&f, &r.fields))
+        .collect())
+}
+
+/// Renders `--separator`. It gets a single empty arg, so a spec like `{:-^term}` draws a rule across
+/// the terminal, and a separator that is one character is stretched into a rule as well.
+pub fn separator(fmt_str: &str) -> crate::Result<String> {
+    let rendered = Formatter::new(fmt_str)?.generate(&[""])?;
+    Ok(layout::rule(&rendered, term::width()))
+}
+
+/// Like [`render`], but only the first of any duplicate records is kept, duplicates being records
+/// that render the same output or that have the same value for a field. Each record can use the
+/// [`DUPCOUNT`] placeholder to show how many times it occurred, like `uniq -c`. Records that fail
+/// to render are never duplicates, each of their errors is kept where the record was.
+pub fn render_unique(
+    fmt_str: &str,
+    records: &[Record],
+    key: &RecordKey,
+    format: OutputFormat,
+) -> crate::Result<Vec<crate::Result<String>>> {
+    fn with_count(record: &Record, count: usize) -> Vec<String> {
+        let mut fields = record.fields.clone();
+        fields.push(format!("{} = {}", DUPCOUNT, count));
+        fields
+    }
+
+    let f = Formatter::new(fmt_str)?;
+    // (index of the first occurrence, number of occurrences), kept in order of first appearance
+    let mut groups: Vec<crate::Result<(usize, usize)>> = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for (i, record) in records.iter().enumerate() {
+        let k = match key {
+            RecordKey::Rendered => match f.generate(&with_count(record, 1)) {
+                Ok(k) => k,
+                Err(err) => {
+                    groups.push(Err(err));
+                    continue;
+                }
+            },
+            RecordKey::Field(name) => record.field(name).unwrap_or_default(),
+        };
+        match seen.get(&k) {
+            Some(&g) => {
+                if let Ok((_, count)) = &mut groups[g] {
+                    *count += 1;
+                }
+            }
+            None => {
+                seen.insert(k, groups.len());
+                groups.push(Ok((i, 1)));
+            }
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|group| {
+            let (i, count) = group?;
+            format.render(&f, &with_count(&records[i], count))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    /// The output of every record, which must all have rendered.
+    fn ok(rendered: crate::Result<Vec<crate::Result<String>>>) -> Vec<String> {
+        rendered
+            .unwrap()
+            .into_iter()
+            .collect::<crate::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn each_arg() {
+        let args = vec!["a".to_string(), "b".to_string()];
+        let records = each(&args);
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            ok(render("<{}>", &records, OutputFormat::Text)),
+            vec!["<a>".to_string(), "<b>".to_string()]
+        );
+        let failed = render("<{1}>", &records, OutputFormat::Text).unwrap();
+        assert!(failed.iter().all(Result::is_err));
+    }
+
+    #[test]
+    fn chunked_args() {
+        let args = ["a", "1", "b", "2", "c"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let records = chunk(&args, 2);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[2].fields, vec!["c"]);
+        assert_eq!(
+            ok(render("{}={}", &records[..2], OutputFormat::Csv)),
+            vec!["a,1", "b,2"]
+        );
+    }
+
+    #[test]
+    fn zipped_args() {
+        let args = ["a", "b", "c", "1", "2", "3"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let records = zip(&args, 2).unwrap();
+        assert_eq!(
+            ok(render("{}={}", &records, OutputFormat::Csv)),
+            vec!["a,1", "b,2", "c,3"]
+        );
+        assert_eq!(zip(&args, 3).unwrap()[1].fields, vec!["b", "1", "3"]);
+        assert!(zip(&args[..5], 2).is_err());
+    }
+
+    #[test]
+    fn stream_lines() {
+        let input = "one\n\ntwo\nname = three\n";
+        let records = stream(input.as_bytes())
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(
+            ok(render("[{}]", &records, OutputFormat::Text)),
+            vec!["[one]", "[two]", "[three]"]
+        );
+    }
+
+    #[test]
+    fn stats_report() {
+        let mut stats = Stats::new();
+        stats.processed = 50;
+        assert!(stats.tally(Ok(())).is_ok());
+        assert!(stats
+            .tally::<()>(Err(crate::Error::Other("x".into())))
+            .is_err());
+        assert_eq!(
+            stats.report(Duration::from_millis(250)),
+            "50 records processed, 1 failed in 250.00ms (200.0 records/s)"
+        );
+        assert_eq!(
+            stats.report(Duration::ZERO),
+            "50 records processed, 1 failed in 0.00ns (0.0 records/s)"
+        );
+    }
+
+    #[test]
+    fn totals() {
+        let args = [
+            "size = 10",
+            "name = x",
+            "size = 2.5",
+            "n = 3",
+            "size = -1",
+            "size_ = big",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>();
+        let mut totals = Totals::default();
+        for record in chunk(&args, 2) {
+            totals.add(&record);
+        }
+        let f = Formatter::new("{count}: {sum_size} in [{min_size}, {max_size}], {sum_n}").unwrap();
+        let footer = f.generate(&totals.args(3)).unwrap();
+        assert_eq!(footer, "3: 11.5 in [-1, 10], 3");
+        assert_eq!(Totals::default().args(0), vec!["count = 0"]);
+    }
+
+    #[test]
+    fn uniqueness() {
+        let args = ["a", "b", "a", "c", "a", "b"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let records = each(&args);
+        assert_eq!(
+            ok(render_unique(
+                "{}",
+                &records,
+                &RecordKey::Rendered,
+                OutputFormat::Text
+            )),
+            vec!["a", "b", "c"]
+        );
+        assert_eq!(
+            ok(render_unique(
+                "{dupcount} {}",
+                &records,
+                &RecordKey::Rendered,
+                OutputFormat::Text
+            )),
+            vec!["3 a", "2 b", "1 c"]
+        );
+
+        let args = ["n = 1", "n = 2", "n = 1"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let records = each(&args);
+        assert_eq!(
+            ok(render_unique(
+                "{n}x{dupcount}",
+                &records,
+                &RecordKey::Field("n".to_string()),
+                OutputFormat::Text
+            )),
+            vec!["1x2", "2x1"]
+        );
+
+        let args = ["n = 1", "m = 2", "n = 1", "m = 2"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let records = each(&args);
+        let rendered =
+            render_unique("{n}", &records, &RecordKey::Rendered, OutputFormat::Text).unwrap();
+        let failed = rendered.iter().map(Result::is_err).collect::<Vec<_>>();
+        assert_eq!(failed, vec![false, true, true]);
+    }
+
+    #[test]
+    fn sorting() {
+        let args = ["size = 10", "size = 9", "name = x", "size = 100"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>();
+        let mut records = each(&args);
+        sort_records(&mut records, "size");
+        let sorted = records
+            .iter()
+            .map(|r| r.fields[0].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sorted,
+            vec!["name = x", "size = 9", "size = 10", "size = 100"]
+        );
+
+        let mut lines = vec![
+            "\x1b[31mzebra\x1b[0m".to_string(),
+            "apple".to_string(),
+            "\x1b[1mmango\x1b[0m".to_string(),
+        ];
+        sort_rendered(&mut lines);
+        assert_eq!(
+            lines,
+            vec!["apple", "\x1b[1mmango\x1b[0m", "\x1b[31mzebra\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn separators() {
+        term::set_width(term::DEFAULT_WIDTH);
+        assert_eq!(separator("-").unwrap(), "-".repeat(term::DEFAULT_WIDTH));
+        assert_eq!(
+            separator("{:-^term}").unwrap(),
+            "-".repeat(term::DEFAULT_WIDTH)
+        );
+        assert_eq!(separator("{:=^6}").unwrap(), "======");
+        assert_eq!(separator("<>").unwrap(), "<>");
+        assert!(separator("{name}").is_err());
+    }
+}
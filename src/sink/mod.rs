@@ -0,0 +1,187 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{
+    ansi::{self, Segment},
+    color::ColorChoice,
+    perf,
+    term::Screen,
+};
+
+/// Somewhere output can be written to.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Target {
+    Stdout,
+    Stderr,
+    File(PathBuf),
+}
+
+impl Target {
+    /// Parses a `--tee` destination, which is either `stdout` or `stderr`.
+    pub fn parse_stream(s: &str) -> Option<Self> {
+        match s.trim() {
+            "stdout" => Some(Self::Stdout),
+            "stderr" => Some(Self::Stderr),
+            _ => None,
+        }
+    }
+}
+
+/// What ends every line written to a [`Sink`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LineEnd {
+    Newline,
+    /// `-n`, the last line doesn't end with a newline
+    Held,
+    /// Every line is drawn over the one before it, on terminals only
+    Overwrite,
+}
+
+/// An open [`Target`] along with whether it gets colored output. Output is always rendered with
+/// its styling, and every sink strips it on its own when it shouldn't have color, so a terminal and
+/// a file can be written at the same time with each getting what suits it.
+pub struct Sink {
+    writer: Box<dyn Write>,
+    color: bool,
+    terminal: bool,
+    end: LineEnd,
+    /// A newline held back from the previous line, only written if another line follows
+    pending: bool,
+    /// `--typewriter`, the pause after every grapheme written to a terminal
+    delay: Option<Duration>,
+    /// `--at`, `--clear` and `--clear-line`, for terminals
+    screen: Screen,
+}
+
+impl Sink {
+    pub fn open(target: &Target, choice: ColorChoice, end: LineEnd) -> crate::Result<Self> {
+        let (writer, terminal): (Box<dyn Write>, bool) = match target {
+            Target::Stdout => (Box::new(std::io::stdout()), std::io::stdout().is_terminal()),
+            Target::Stderr => (Box::new(std::io::stderr()), std::io::stderr().is_terminal()),
+            Target::File(path) => (Box::new(BufWriter::new(File::create(path)?)), false),
+        };
+        Ok(Self {
+            writer,
+            color: choice.for_stream(terminal),
+            terminal,
+            end,
+            pending: false,
+            delay: None,
+            screen: Screen::default(),
+        })
+    }
+
+    /// Makes the sink write one grapheme at a time with `delay` after each, like a typewriter, if
+    /// it is a terminal. Anything else gets its output at once.
+    pub fn typewriter(self, delay: Option<Duration>) -> Self {
+        Self {
+            delay: delay.filter(|_| self.terminal),
+            ..self
+        }
+    }
+
+    /// Makes the sink clear the screen or the line, or move the cursor, before every line it
+    /// writes, as `screen` says, if it is a terminal. Anything else gets its output as it is.
+    pub fn screen(self, screen: Screen) -> Self {
+        Self {
+            screen: match self.terminal {
+                true => screen,
+                false => Screen::default(),
+            },
+            ..self
+        }
+    }
+
+    /// Writes `text` followed by a newline, flushing right away so streamed output shows up
+    /// as it is produced. Without a trailing newline, the newline is only written once another
+    /// line follows.
+    pub fn write_line(&mut self, text: &str) -> crate::Result<()> {
+        let text = if self.color || !ansi::has_escapes(text) {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(ansi::strip(text))
+        };
+        if self.end == LineEnd::Overwrite && self.terminal {
+            return self.overwrite(&text);
+        }
+        let newline = self.end != LineEnd::Held;
+        if self.pending {
+            writeln!(self.writer)?;
+        }
+        let text = match self.screen.is_plain() {
+            true => text,
+            false => {
+                write!(self.writer, "{}", self.screen.prelude())?;
+                Cow::Owned(self.screen.place(&text).into_owned())
+            }
+        };
+        match self.delay {
+            Some(delay) => self.type_out(&text, delay)?,
+            None => write!(self.writer, "{}", text)?,
+        }
+        if newline {
+            writeln!(self.writer)?;
+        }
+        perf::wrote(text.len() + usize::from(self.pending || newline));
+        self.pending = !newline;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Writes `text` one grapheme at a time, pausing for `delay` after each. Escape sequences are
+    /// written whole and without a pause, since they don't show up as anything.
+    fn type_out(&mut self, text: &str, delay: Duration) -> crate::Result<()> {
+        for seg in ansi::segments(text) {
+            match seg {
+                Segment::Escape(e) => write!(self.writer, "{}", e)?,
+                Segment::Text(t) => {
+                    for (_, grapheme) in ansi::grapheme_indices(t) {
+                        write!(self.writer, "{}", grapheme)?;
+                        self.writer.flush()?;
+                        std::thread::sleep(delay);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Draws `text` over the line the cursor is on, as a single line. It is cut off or padded with
+    /// spaces to the width of the terminal, so it never wraps and nothing of a longer line drawn
+    /// before it is left behind. With `--at` the line is drawn there instead.
+    fn overwrite(&mut self, text: &str) -> crate::Result<()> {
+        let (start, col) = match self.screen.at {
+            Some((_, col)) => (self.screen.prelude(), col),
+            None => ("\r".to_string(), 1),
+        };
+        // The last column is left alone, some terminals move on to the next line once it is used
+        let width = crate::term::width().saturating_sub(col);
+        let line = ansi::truncate(&text.replace('\n', " "), width, "");
+        let pad = " ".repeat(width.saturating_sub(ansi::width(&line)));
+        write!(self.writer, "{}{}{}", start, line, pad)?;
+        perf::wrote(line.len() + pad.len() + start.len());
+        self.pending = true;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Ends a line drawn with [`LineEnd::Overwrite`], so whatever is written next starts on a line
+    /// of its own.
+    pub fn end_line(&mut self) -> crate::Result<()> {
+        if self.end == LineEnd::Overwrite && self.pending {
+            writeln!(self.writer)?;
+            self.pending = false;
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
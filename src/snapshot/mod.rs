@@ -0,0 +1,105 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::path::{Path, PathBuf};
+
+use crate::{ansi, Error};
+
+/// What `fmt snapshot` does with the rendered output.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Snapshot {
+    /// `--record FILE`, saves the output to the file
+    Record(PathBuf),
+    /// `--verify FILE`, compares the output with what was saved to the file
+    Verify(PathBuf),
+}
+
+impl Snapshot {
+    /// Records or verifies `output`, which is kept exactly as rendered, styling included.
+    pub fn run(&self, output: &str) -> crate::Result<()> {
+        match self {
+            Snapshot::Record(path) => Ok(std::fs::write(path, format!("{}\n", output))?),
+            Snapshot::Verify(path) => verify(path, output),
+        }
+    }
+}
+
+fn verify(path: &Path, output: &str) -> crate::Result<()> {
+    let saved = std::fs::read_to_string(path)?;
+    let saved = saved.strip_suffix('\n').unwrap_or(&saved);
+    if saved == output {
+        return Ok(());
+    }
+    Err(Error::Other(format!(
+        "Output does not match the snapshot in {}\n{}",
+        path.display(),
+        diff(saved, output)
+    )))
+}
+
+/// Shows escape sequences as `\e`, so changes to styling are visible in a diff.
+fn visible(line: &str) -> String {
+    line.replace('\x1b', "\\e")
+}
+
+/// A line by line diff of `expected` and `actual`, with removed lines starting with `-` and added
+/// lines with `+`. Escape sequences are shown rather than interpreted, and a changed line whose
+/// visible text is the same as the line it replaces is marked as only differing in styling.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let old = expected.split('\n').collect::<Vec<_>>();
+    let new = actual.split('\n').collect::<Vec<_>>();
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            lines.push(format!("  {}", visible(old[i])));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && j < new.len() && ansi::strip(old[i]) == ansi::strip(new[j]) {
+            lines.push(format!("- {}", visible(old[i])));
+            lines.push(format!("+ {}  (only styling differs)", visible(new[j])));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("- {}", visible(old[i])));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", visible(new[j])));
+            j += 1;
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn diffs() {
+        assert_eq!(diff("a\nb", "a\nb"), "  a\n  b");
+        assert_eq!(diff("a\nb\nc", "a\nc\nd"), "  a\n- b\n  c\n+ d");
+        assert_eq!(diff("a\nb", "c\nd"), "- a\n- b\n+ c\n+ d");
+        assert_eq!(
+            diff("\x1b[31mred\x1b[0m", "\x1b[32mred\x1b[0m"),
+            "- \\e[31mred\\e[0m\n+ \\e[32mred\\e[0m  (only styling differs)"
+        );
+    }
+}
@@ -0,0 +1,85 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `--spinner`, which animates a spinner next to the output while a command runs.
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+    time::Duration,
+};
+
+use crate::{ansi, Error};
+
+/// The frames of the spinner, shown one after the other
+pub const FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// The frames of the spinner when only ASCII can be shown
+pub const ASCII_FRAMES: [&str; 4] = ["|", "/", "-", "\\"];
+
+/// How long every frame is shown for
+pub const FRAME_TIME: Duration = Duration::from_millis(80);
+
+/// Runs `command` (the program and its arguments) to completion, collecting everything it
+/// writes. While it runs, and only if it is given `frames`, a spinner is drawn on stdout in front
+/// of `message`, which is cleared again once the command is done.
+pub fn spin(message: &str, command: &[String], frames: Option<&[&str]>) -> crate::Result<Output> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| Error::InvalidFlag("--spinner requires a command".to_string()))?;
+    let mut child = Command::new(program);
+    child
+        .args(args)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let running = std::thread::spawn(move || child.output());
+
+    let mut stdout = std::io::stdout();
+    let animate = frames.is_some();
+    for frame in frames.unwrap_or(&FRAMES).iter().cycle() {
+        if running.is_finished() {
+            break;
+        }
+        if animate {
+            write!(stdout, "\r{} {}", frame, message)?;
+            stdout.flush()?;
+        }
+        std::thread::sleep(FRAME_TIME);
+    }
+    if animate {
+        let blank = " ".repeat(ansi::width(message) + 2);
+        write!(stdout, "\r{}\r", blank)?;
+        stdout.flush()?;
+    }
+
+    running
+        .join()
+        .expect("The thread running the command panicked")
+        .map_err(|err| Error::Other(format!("Unable to run {}: {}", program, err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    #[cfg(unix)]
+    fn commands() {
+        let command = ["sh", "-c", "echo out; echo err >&2; exit 3"].map(String::from);
+        let output = spin("working", &command, None).unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stdout, b"out\n");
+        assert_eq!(output.stderr, b"err\n");
+        assert!(spin("working", &["true".to_string()], None)
+            .unwrap()
+            .status
+            .success());
+        assert!(spin("working", &["no-such-command-here".to_string()], None).is_err());
+        assert!(spin("working", &[], None).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! `fmt table`, which reads delimited rows from stdin and lays them out as an aligned table.
+
+use crate::{
+    ansi::{self, Style},
+    color::ColorLevel,
+    decor::BoxStyle,
+    layout::COLUMN_GAP,
+    output::OutputFormat,
+    Alignment, Formatter,
+};
+
+/// How `fmt table` reads and draws its rows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Table {
+    /// `--csv` or `--tsv`, detected from the first line when neither is given
+    pub delimiter: Option<char>,
+    /// `--headers`, the first row names the columns
+    pub headers: bool,
+    /// `--align`, the alignment of each column, left for any that aren't given
+    pub align: Vec<Alignment>,
+    /// `--border`, the lines drawn around and between the cells
+    pub border: Option<BoxStyle>,
+}
+
+impl Table {
+    /// Parses `--align` specs, one character per column: `<` or `l`, `^` or `c`, and `>` or `r`.
+    /// Commas between them are allowed.
+    pub fn parse_align(s: &str) -> Option<Vec<Alignment>> {
+        s.chars()
+            .filter(|&c| c != ',')
+            .map(|c| match c {
+                '<' | 'l' => Some(Alignment::Left),
+                '^' | 'c' => Some(Alignment::Center),
+                '>' | 'r' => Some(Alignment::Right),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Lays the rows of `input` out as a table. Headers are bold when `level` allows it.
+    pub fn render(&self, input: &str, level: ColorLevel) -> String {
+        let delimiter = self.delimiter.unwrap_or_else(|| detect(input));
+        let mut rows = parse_rows(input, delimiter);
+        let cols = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let mut widths = vec![0usize; cols];
+        for row in &mut rows {
+            row.resize(cols, String::new());
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(ansi::width(cell));
+            }
+        }
+
+        let bold = Style {
+            bold: true,
+            ..Style::default()
+        };
+        let lines = rows.iter().enumerate().map(|(i, row)| {
+            let cells = row
+                .iter()
+                .zip(&widths)
+                .enumerate()
+                .map(|(col, (cell, &w))| {
+                    let align = self.align.get(col).copied().unwrap_or(Alignment::Left);
+                    let padded = Formatter::prepare_string(cell, align, w);
+                    match self.headers && i == 0 {
+                        true => bold.paint(&padded, level),
+                        false => padded,
+                    }
+                });
+            cells.collect::<Vec<_>>()
+        });
+
+        let mut output = Vec::with_capacity(rows.len() + 4);
+        match self.border {
+            None => {
+                for (i, cells) in lines.enumerate() {
+                    output.push(cells.join(&" ".repeat(COLUMN_GAP)).trim_end().to_string());
+                    if self.headers && i == 0 {
+                        let dashes = widths.iter().map(|&w| "-".repeat(w));
+                        output.push(dashes.collect::<Vec<_>>().join(&" ".repeat(COLUMN_GAP)));
+                    }
+                }
+            }
+            Some(style) => {
+                let [top_left, top_right, bottom_left, bottom_right, h, v] = style.parts();
+                let [top, bottom, cross, left, right] = style.joints();
+                let edge = |start: char, joint: char, end: char| {
+                    let fills = widths.iter().map(|&w| h.to_string().repeat(w + 2));
+                    let joint = joint.to_string();
+                    format!("{}{}{}", start, fills.collect::<Vec<_>>().join(&joint), end)
+                };
+                output.push(edge(top_left, top, top_right));
+                for (i, cells) in lines.enumerate() {
+                    let sep = format!(" {} ", v);
+                    output.push(format!("{} {} {}", v, cells.join(&sep), v));
+                    if self.headers && i == 0 && rows.len() > 1 {
+                        output.push(edge(left, cross, right));
+                    }
+                }
+                output.push(edge(bottom_left, bottom, bottom_right));
+            }
+        }
+        output.join("\n")
+    }
+
+    /// Writes the rows of `input` out again as `format` rows, for `--output csv` or `tsv`, which
+    /// turns one delimited format into the other or normalizes the quoting of either.
+    pub fn convert(&self, input: &str, format: OutputFormat) -> String {
+        let delimiter = self.delimiter.unwrap_or_else(|| detect(input));
+        parse_rows(input, delimiter)
+            .iter()
+            .map(|row| format.row(row.iter().map(String::as_str)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Tabs when the first line has any, commas otherwise.
+fn detect(input: &str) -> char {
+    match input.lines().next().unwrap_or("").contains('\t') {
+        true => '\t',
+        false => ',',
+    }
+}
+
+/// Splits `input` into rows of cells. With commas, cells can be quoted to hold commas, newlines,
+/// or quotes (written twice); tab separated cells are taken as they are.
+fn parse_rows(input: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if delimiter == ',' && quoted => {
+                if chars.next_if_eq(&'"').is_some() {
+                    cell.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if delimiter == ',' && cell.is_empty() => quoted = true,
+            _ if quoted => cell.push(c),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut cell));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ if c == delimiter => row.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    if !cell.is_empty() || !row.is_empty() {
+        row.push(cell);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn delimited_rows() {
+        assert_eq!(
+            parse_rows("a,\"b,c\"\n\"say \"\"hi\"\"\",\"two\nlines\"\r\n", ','),
+            vec![vec!["a", "b,c"], vec!["say \"hi\"", "two\nlines"]]
+        );
+        assert_eq!(
+            parse_rows("a\t\"b\"\nc", '\t'),
+            vec![vec!["a", "\"b\""], vec!["c"]]
+        );
+        assert!(parse_rows("", ',').is_empty());
+        assert_eq!(detect("a\tb,c\nd"), '\t');
+        assert_eq!(detect("a,b"), ',');
+    }
+
+    #[test]
+    fn converted_rows() {
+        let input = "name,note\nmain.rs,\"a\tb\"\n\"x,y\",\"say \"\"hi\"\"\"\n";
+        let table = Table::default();
+        assert_eq!(
+            table.convert(input, OutputFormat::Tsv),
+            "name\tnote\nmain.rs\ta\\tb\nx,y\tsay \"hi\""
+        );
+        assert_eq!(
+            table.convert(input, OutputFormat::Csv),
+            "name,note\nmain.rs,a\tb\n\"x,y\",\"say \"\"hi\"\"\""
+        );
+        assert_eq!(table.convert("a\tb,c\n", OutputFormat::Csv), "a,\"b,c\"");
+    }
+
+    #[test]
+    fn plain_tables() {
+        let input = "name,size\nmain.rs,12\nlib.rs,3400\n";
+        let table = Table {
+            headers: true,
+            align: Table::parse_align("<>").unwrap(),
+            ..Table::default()
+        };
+        assert_eq!(
+            table.render(input, ColorLevel::None),
+            "name     size\n-------  ----\nmain.rs    12\nlib.rs   3400"
+        );
+        let table = Table::default();
+        assert_eq!(table.render("a\tbb\nccc", ColorLevel::None), "a    bb\nccc");
+        assert_eq!(table.render("x\ny", ColorLevel::Basic), "x\ny");
+        let table = Table {
+            headers: true,
+            ..Table::default()
+        };
+        assert_eq!(
+            table.render("x\ny", ColorLevel::Basic),
+            "\x1b[1mx\x1b[0m\n-\ny"
+        );
+    }
+
+    #[test]
+    fn bordered_tables() {
+        let input = "id,state\n1,ok\n22,failed";
+        let table = Table {
+            headers: true,
+            align: Table::parse_align("r,c").unwrap(),
+            border: Some(BoxStyle::Single),
+            ..Table::default()
+        };
+        assert_eq!(
+            table.render(input, ColorLevel::None),
+            [
+                "┌────┬────────┐",
+                "│ id │ state  │",
+                "├────┼────────┤",
+                "│  1 │   ok   │",
+                "│ 22 │ failed │",
+                "└────┴────────┘",
+            ]
+            .join("\n")
+        );
+        let table = Table {
+            border: Some(BoxStyle::Ascii),
+            ..Table::default()
+        };
+        assert_eq!(
+            table.render("a,b", ColorLevel::None),
+            "+---+---+\n| a | b |\n+---+---+"
+        );
+        assert_eq!(Table::parse_align("<x"), None);
+    }
+}
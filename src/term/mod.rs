@@ -0,0 +1,333 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+use std::borrow::Cow;
+use std::io::{IsTerminal, Write};
+
+use once_cell::sync::OnceCell;
+#[cfg(feature = "terminal")]
+use terminal_size::{terminal_size, Width};
+
+use crate::color::{self, ColorLevel};
+
+/// Used when the width can't be queried from the terminal or from `COLUMNS`.
+pub const DEFAULT_WIDTH: usize = 80;
+
+static WIDTH: OnceCell<usize> = OnceCell::new();
+
+/// Makes `width` the width of the terminal, in place of the detected one (`--width N`). Only the
+/// first call has any effect.
+pub fn set_width(width: usize) {
+    let _ = WIDTH.set(width);
+}
+
+/// The width of the terminal in cells, unless [`set_width`] said otherwise. Falls back to the
+/// `COLUMNS` environment variable and then to [`DEFAULT_WIDTH`] when output is not attached to a
+/// terminal.
+pub fn width() -> usize {
+    if let Some(&width) = WIDTH.get() {
+        return width;
+    }
+    #[cfg(feature = "terminal")]
+    if let Some((Width(w), _)) = terminal_size() {
+        if w > 0 {
+            return w as usize;
+        }
+    }
+
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.trim().parse::<usize>().ok())
+        .filter(|&c| c > 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Whether the locale can show more than ASCII, going by the first of `LC_ALL`, `LC_CTYPE` and
+/// `LANG` that is set. Without any of them it is assumed to, like on Windows.
+pub fn unicode_locale() -> bool {
+    let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()));
+    is_unicode(locale.as_deref())
+}
+
+fn is_unicode(locale: Option<&str>) -> bool {
+    match locale {
+        Some(locale) => {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => true,
+    }
+}
+
+/// Moves the cursor to the top left corner and clears the terminal.
+const CLEAR_SCREEN: &str = "\x1b[H\x1b[2J";
+
+/// Clears the terminal and moves the cursor to its top left corner, if stdout is a terminal.
+pub fn clear_screen() -> crate::Result<()> {
+    let mut stdout = std::io::stdout();
+    if stdout.is_terminal() {
+        write!(stdout, "{}", CLEAR_SCREEN)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
+
+/// Where the output is drawn on a terminal, and what is cleared before it is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Screen {
+    /// `--clear`, the whole screen is cleared first
+    pub clear: bool,
+    /// `--clear-line`, the line the output starts on is cleared first
+    pub clear_line: bool,
+    /// `--at ROW,COL`, where the output starts, counting from 1
+    pub at: Option<(usize, usize)>,
+}
+
+impl Screen {
+    /// Whether the output is drawn where the cursor happens to be, as usual.
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The sequences written before the output: clearing the screen, moving the cursor, and
+    /// clearing the line, in that order, so `--clear-line` clears the line of `--at`.
+    pub fn prelude(&self) -> String {
+        let mut prelude = String::new();
+        if self.clear {
+            prelude.push_str(CLEAR_SCREEN);
+        }
+        match self.at {
+            Some((row, col)) => prelude.push_str(&format!("\x1b[{};{}H", row, col)),
+            None if self.clear_line => prelude.push('\r'),
+            None => {}
+        }
+        if self.clear_line {
+            prelude.push_str("\x1b[2K");
+        }
+        prelude
+    }
+
+    /// `text` with every line after the first moved to the column of `--at`, so the output stays
+    /// in one block instead of going back to the left edge.
+    pub fn place<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.at {
+            Some((_, col)) if col > 1 && text.contains('\n') => {
+                Cow::Owned(text.replace('\n', &format!("\n\x1b[{}G", col)))
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// The bell character, which makes terminals beep or flash
+pub const BELL: &str = "\x07";
+
+/// `text` as it can go into an OSC sequence: styling, line breaks and other control characters
+/// are left out, since they would end the sequence early or show up as garbage.
+fn osc_text(text: &str) -> String {
+    crate::ansi::strip(text)
+        .replace('\n', " ")
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect()
+}
+
+/// The OSC 0 sequence that sets the title of the terminal (and of its icon or tab) to `title`.
+pub fn title_sequence(title: &str) -> String {
+    format!("\x1b]0;{}\x07", osc_text(title))
+}
+
+/// The sequences that make a terminal show `text` as a desktop notification: OSC 9 (iTerm2,
+/// ConEmu, Windows Terminal) and OSC 777 (rxvt, foot, GNOME terminals). Terminals ignore the ones
+/// they don't know.
+pub fn notify_sequence(text: &str) -> String {
+    let text = osc_text(text);
+    format!("\x1b]9;{0}\x07\x1b]777;notify;fmt;{0}\x07", text)
+}
+
+/// Writes `sequence` to the terminal, through stdout or otherwise stderr, whichever of them is a
+/// terminal. When neither is, there is no terminal to talk to and nothing happens.
+pub fn send(sequence: &str) -> crate::Result<()> {
+    if std::io::stdout().is_terminal() {
+        let mut stdout = std::io::stdout();
+        write!(stdout, "{}", sequence)?;
+        stdout.flush()?;
+    } else if std::io::stderr().is_terminal() {
+        write!(std::io::stderr(), "{}", sequence)?;
+    }
+    Ok(())
+}
+
+/// Sets the title of the terminal to `title`, see [`send`].
+pub fn set_title(title: &str) -> crate::Result<()> {
+    send(&title_sequence(title))
+}
+
+/// Makes the console interpret escape sequences on Windows, where legacy consoles otherwise show
+/// them as they are. Returns false if that isn't possible, in which case output shouldn't be
+/// styled. Anywhere else, and for output that isn't going to a console, there is nothing to do.
+pub fn enable_ansi() -> bool {
+    #[cfg(windows)]
+    return windows::enable_virtual_terminal(windows::STD_OUTPUT_HANDLE)
+        && windows::enable_virtual_terminal(windows::STD_ERROR_HANDLE);
+    #[cfg(not(windows))]
+    return true;
+}
+
+/// Just enough of the console API to turn on escape sequences, which isn't worth a dependency.
+#[cfg(windows)]
+mod windows {
+    type Handle = *mut std::ffi::c_void;
+
+    pub const STD_OUTPUT_HANDLE: u32 = -11i32 as u32;
+    pub const STD_ERROR_HANDLE: u32 = -12i32 as u32;
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn GetStdHandle(std_handle: u32) -> Handle;
+        fn GetConsoleMode(console: Handle, mode: *mut u32) -> i32;
+        fn SetConsoleMode(console: Handle, mode: u32) -> i32;
+    }
+
+    /// Turns on virtual terminal processing for the console behind `std_handle`. Handles that
+    /// aren't consoles (pipes, files, terminal emulators like mintty) are left alone.
+    pub fn enable_virtual_terminal(std_handle: u32) -> bool {
+        // SAFETY: the handle comes straight from GetStdHandle, and the console functions fail
+        // gracefully on handles that are invalid or not consoles
+        unsafe {
+            let handle = GetStdHandle(std_handle);
+            let mut mode = 0u32;
+            if GetConsoleMode(handle, &mut mode) == 0 {
+                return true;
+            }
+            mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING != 0
+                || SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING) != 0
+        }
+    }
+}
+
+/// What output can show, consulted by everything that styles text (help, styled specs, the
+/// output flags) so none of them send escape sequences where they would garble the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Capabilities {
+    /// Whether output goes to a terminal
+    pub terminal: bool,
+    /// The colors output can use, [`ColorLevel::None`] if it shouldn't be styled at all
+    pub color: ColorLevel,
+}
+
+impl Capabilities {
+    /// The capabilities of output going to a terminal, or not, under the active color policy.
+    pub fn new(terminal: bool) -> Self {
+        let colored = cfg!(feature = "color") && color::choice().for_stream(terminal);
+        Self {
+            terminal,
+            color: if colored {
+                color::level()
+            } else {
+                ColorLevel::None
+            },
+        }
+    }
+
+    /// The capabilities of stdout.
+    pub fn detect() -> Self {
+        Self::new(std::io::stdout().is_terminal())
+    }
+
+    /// The capabilities of stderr, which are always detected since [`set_capabilities`] is only
+    /// about the output.
+    pub fn stderr() -> Self {
+        Self::new(std::io::stderr().is_terminal())
+    }
+}
+
+static CAPABILITIES: OnceCell<Capabilities> = OnceCell::new();
+
+/// Makes `caps` the [`Capabilities`] of the output in place of those detected from stdout. Only
+/// the first call has any effect, and only if they weren't needed before it.
+pub fn set_capabilities(caps: Capabilities) {
+    let _ = CAPABILITIES.set(caps);
+}
+
+/// The [`Capabilities`] of the output, detected from stdout the first time they are needed
+/// unless [`set_capabilities`] said otherwise.
+pub fn capabilities() -> Capabilities {
+    *CAPABILITIES.get_or_init(Capabilities::detect)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::{assert_eq, assert_ne, assert_str_eq};
+
+    #[test]
+    fn titles() {
+        assert_eq!(title_sequence("building"), "\x1b]0;building\x07");
+        assert_eq!(
+            title_sequence("\x1b[1mstep\x1b[0m 2\nof 3\x07"),
+            "\x1b]0;step 2 of 3\x07"
+        );
+        assert_eq!(
+            notify_sequence("build done\n"),
+            "\x1b]9;build done \x07\x1b]777;notify;fmt;build done \x07"
+        );
+    }
+
+    #[test]
+    fn locales() {
+        assert!(is_unicode(Some("en_US.UTF-8")));
+        assert!(is_unicode(Some("de_DE.utf8")));
+        assert!(is_unicode(None));
+        assert!(!is_unicode(Some("C")));
+        assert!(!is_unicode(Some("POSIX")));
+        assert!(!is_unicode(Some("en_US.ISO-8859-1")));
+    }
+
+    #[test]
+    fn screens() {
+        assert!(Screen::default().is_plain());
+        assert_eq!(Screen::default().prelude(), "");
+        let screen = Screen {
+            clear: true,
+            at: Some((3, 10)),
+            ..Screen::default()
+        };
+        assert!(!screen.is_plain());
+        assert_eq!(screen.prelude(), "\x1b[H\x1b[2J\x1b[3;10H");
+        assert_eq!(screen.place("cpu\nmem"), "cpu\n\x1b[10Gmem");
+        assert_eq!(screen.place("cpu"), "cpu");
+        let screen = Screen {
+            clear_line: true,
+            ..Screen::default()
+        };
+        assert_eq!(screen.prelude(), "\r\x1b[2K");
+        assert_eq!(screen.place("a\nb"), "a\nb");
+        let screen = Screen {
+            clear_line: true,
+            at: Some((1, 1)),
+            ..Screen::default()
+        };
+        assert_eq!(screen.prelude(), "\x1b[1;1H\x1b[2K");
+        assert_eq!(screen.place("a\nb"), "a\nb");
+    }
+
+    #[test]
+    fn capabilities() {
+        let piped = Capabilities::new(false);
+        assert!(!piped.terminal);
+        match color::choice() {
+            color::ColorChoice::Always => assert_eq!(piped.color, color::level()),
+            _ => assert_eq!(piped.color, ColorLevel::None),
+        }
+        if color::choice() == color::ColorChoice::Never || cfg!(not(feature = "color")) {
+            assert_eq!(Capabilities::new(true).color, ColorLevel::None);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// Copyright (c) 2022 Tony Barbitta
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! Runs `--printf-compat-check` so CI catches `--printf` drifting away from the system printf.
+
+use std::process::Command;
+
+#[test]
+fn printf_compat() {
+    if Command::new("printf").arg("").status().is_err() {
+        eprintln!("no printf found on this system, skipping");
+        return;
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_fmt"))
+        .arg("--printf-compat-check")
+        .output()
+        .expect("Unable to run fmt");
+    assert!(
+        output.status.success(),
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}